@@ -0,0 +1,363 @@
+use std::{fmt, sync::Arc};
+
+use anyhow::anyhow;
+use argon2::{
+    password_hash::{
+        rand_core::{OsRng, RngCore},
+        PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+    },
+    Argon2,
+};
+use axum::{
+    extract::{Extension, FromRequestParts, OptionalFromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    models::{
+        api_key_model::ApiScope,
+        booking_model::Booking,
+        promotion_model::normalize_customer,
+        reservation_model::SeatReservation,
+        user_model::{Role, User},
+    },
+    repositories::Repositories,
+    tenancy::Tenant,
+};
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Partner keys are sent as `Authorization: ApiKey <key>`, user tokens as `Bearer <token>`.
+const API_KEY_SCHEME: &str = "ApiKey ";
+const API_KEY_PREFIX: &str = "ck_";
+/// Characters of a key kept in the clear so admins can tell keys apart.
+const API_KEY_VISIBLE_LEN: usize = 11;
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    /// Id of the user.
+    sub: String,
+    email: String,
+    role: Role,
+    /// Tenant the token was issued by. It is not accepted by any other.
+    tenant: String,
+    iat: i64,
+    exp: i64,
+}
+
+/// Issues and checks the HS256 access tokens handed out at login.
+#[derive(Clone)]
+pub struct JwtSigner {
+    encoding_key: Arc<EncodingKey>,
+    decoding_key: Arc<DecodingKey>,
+}
+
+impl JwtSigner {
+    pub fn new(key: &[u8]) -> Self {
+        JwtSigner {
+            encoding_key: Arc::new(EncodingKey::from_secret(key)),
+            decoding_key: Arc::new(DecodingKey::from_secret(key)),
+        }
+    }
+
+    pub fn issue(&self, user: &User, tenant: &Tenant, ttl: Duration) -> jsonwebtoken::errors::Result<String> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+            email: user.email.clone(),
+            role: user.role,
+            tenant: tenant.to_string(),
+            iat: now.timestamp(),
+            exp: (now + ttl).timestamp(),
+        };
+
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+    }
+
+    /// Returns the user a token was issued to, or `None` if it is malformed, expired, was
+    /// not signed with this key or belongs to another tenant.
+    pub fn verify(&self, token: &str, tenant: &Tenant) -> Option<AuthUser> {
+        let claims = decode::<Claims>(token, &self.decoding_key, &Validation::new(Algorithm::HS256))
+            .ok()?
+            .claims;
+        if claims.tenant != tenant.to_string() {
+            return None;
+        }
+
+        Some(AuthUser {
+            id: ObjectId::parse_str(&claims.sub).ok()?,
+            email: claims.email,
+            role: claims.role,
+        })
+    }
+}
+
+/// Caller of a request, taken from its `Authorization: Bearer` token. Extracting it
+/// rejects the request with `UNAUTHORIZED` when the token is missing or invalid; use
+/// `Option<AuthUser>` where anonymous callers are welcome.
+#[derive(Clone, Debug)]
+pub struct AuthUser {
+    pub id: ObjectId,
+    /// Normalized email, which is also how buyers of bookings are matched.
+    pub email: String,
+    pub role: Role,
+}
+
+impl AuthUser {
+    fn from_parts(parts: &Parts) -> Result<Option<Self>, StatusCode> {
+        // Already checked by `authorize`.
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            return Ok(Some(user.clone()));
+        }
+
+        let Some(authorization) = parts.headers.get(header::AUTHORIZATION) else {
+            return Ok(None);
+        };
+        let authorization = authorization.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
+        // Partner keys are handled by `authorize`.
+        let Some(token) = authorization.strip_prefix("Bearer ") else {
+            return Ok(None);
+        };
+        let signer = parts
+            .extensions
+            .get::<JwtSigner>()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        let tenant = parts.extensions.get::<Tenant>().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        signer.verify(token.trim(), tenant).map(Some).ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        AuthUser::from_parts(parts)?.ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+impl<S: Send + Sync> OptionalFromRequestParts<S> for AuthUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Option<Self>, Self::Rejection> {
+        AuthUser::from_parts(parts)
+    }
+}
+
+/// Third-party site calling with one of the tenant's API keys.
+#[derive(Clone, Debug)]
+pub struct Partner {
+    pub api_key_id: ObjectId,
+}
+
+/// Whoever is making a request that needs a caller: a logged in user or a partner.
+#[derive(Clone, Debug)]
+pub enum Caller {
+    User(AuthUser),
+    Partner(Partner),
+}
+
+impl Caller {
+    /// Customers book under their own email. Staff and partners book for anyone.
+    pub fn can_book_for(&self, buyer_email: &str) -> bool {
+        match self {
+            Caller::User(user) => user.role.allows(Role::Staff) || normalize_customer(buyer_email) == user.email,
+            Caller::Partner(_) => true,
+        }
+    }
+
+    /// Customers manage the bookings made under their email and partners the ones made
+    /// with their key. Staff manage all of them.
+    pub fn owns_booking(&self, booking: &Booking) -> bool {
+        match self {
+            Caller::User(_) => self.can_book_for(&booking.buyer.email),
+            Caller::Partner(partner) => booking.api_key_id == Some(partner.api_key_id),
+        }
+    }
+
+    /// Holds belong to the user or partner key that placed them.
+    pub fn owns_hold(&self, reservation: &SeatReservation) -> bool {
+        match self {
+            Caller::User(user) => reservation.user_id == Some(user.id),
+            Caller::Partner(partner) => reservation.api_key_id == Some(partner.api_key_id),
+        }
+    }
+
+    pub fn user_id(&self) -> Option<ObjectId> {
+        match self {
+            Caller::User(user) => Some(user.id),
+            Caller::Partner(_) => None,
+        }
+    }
+
+    pub fn api_key_id(&self) -> Option<ObjectId> {
+        match self {
+            Caller::User(_) => None,
+            Caller::Partner(partner) => Some(partner.api_key_id),
+        }
+    }
+
+    /// Whether the caller is a user with at least `role`. Partners have no role.
+    pub fn has_role(&self, role: Role) -> bool {
+        match self {
+            Caller::User(user) => user.role.allows(role),
+            Caller::Partner(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Caller::User(user) => write!(f, "user {} <{}> ({:?})", user.id, user.email, user.role),
+            Caller::Partner(partner) => write!(f, "API key {}", partner.api_key_id),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(partner) = parts.extensions.get::<Partner>() {
+            return Ok(Caller::Partner(partner.clone()));
+        }
+
+        AuthUser::from_parts(parts)?.map(Caller::User).ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Who can call a route: users with at least `role`, and partners whose key has `scope`.
+/// Without a role anyone can, except partner keys lacking the scope.
+#[derive(Clone, Copy, Debug)]
+pub struct Access {
+    role: Option<Role>,
+    scope: Option<ApiScope>,
+}
+
+impl Access {
+    pub const fn role(role: Role) -> Self {
+        Access { role: Some(role), scope: None }
+    }
+
+    pub const fn public(scope: ApiScope) -> Self {
+        Access { role: None, scope: Some(scope) }
+    }
+
+    pub const fn or_scope(self, scope: ApiScope) -> Self {
+        Access {
+            role: self.role,
+            scope: Some(scope),
+        }
+    }
+}
+
+pub fn generate_api_key() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", API_KEY_PREFIX, URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Keys are long and random, so a fast hash is enough to keep them out of the database.
+pub fn hash_api_key(key: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(key.as_bytes()))
+}
+
+pub fn api_key_prefix(key: &str) -> String {
+    key.chars().take(API_KEY_VISIBLE_LEN).collect()
+}
+
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix(API_KEY_SCHEME)
+        .map(str::trim)
+}
+
+/// Checks the caller against the route's `Access`. Added as a route layer in `main.rs`, so
+/// the router states who can call each route. Partner keys are looked up and counted here.
+pub async fn authorize(
+    State(access): State<Access>,
+    Extension(repositories): Extension<Repositories>,
+    user: Option<AuthUser>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if let Some(key) = api_key(request.headers()) {
+        let api_key = repositories
+            .api_keys
+            .find_by_hash(&hash_api_key(key))
+            .await?
+            .filter(|api_key| api_key.revoked_at.is_none())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let api_key_id = api_key.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        repositories.api_keys.record_use(api_key_id, DateTime::now()).await?;
+
+        if !access.scope.is_some_and(|scope| api_key.scopes.contains(&scope)) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        request.extensions_mut().insert(Partner { api_key_id });
+        return Ok(next.run(request).await);
+    }
+
+    if let Some(required) = access.role {
+        let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+        if !user.role.allows(required) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        request.extensions_mut().insert(user);
+    }
+
+    Ok(next.run(request).await)
+}
+
+pub fn hash_password(password: &str) -> Option<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .ok()
+        .map(|hash| hash.to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Creates the admin account from `ADMIN_EMAIL` and `ADMIN_PASSWORD` when the tenant does
+/// not have it yet, so a fresh deployment can be logged into.
+pub async fn ensure_admin(repositories: &Repositories, email: &str, password: &str) -> anyhow::Result<()> {
+    let email = normalize_customer(email);
+    if repositories.users.find_by_email(&email).await.map_err(|e| anyhow!("{}", e))?.is_some() {
+        return Ok(());
+    }
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err(anyhow!("ADMIN_PASSWORD must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+
+    let password_hash = hash_password(password).ok_or_else(|| anyhow!("failed to hash ADMIN_PASSWORD"))?;
+    repositories
+        .users
+        .insert(User {
+            id: None,
+            email,
+            name: "Administrator".to_string(),
+            role: Role::Admin,
+            password_hash,
+            created_at: DateTime::now(),
+        })
+        .await
+        .map_err(|e| anyhow!("{}", e))?;
+
+    Ok(())
+}
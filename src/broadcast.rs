@@ -0,0 +1,367 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, DateTime, Document},
+    options::{CreateCollectionOptions, CursorType, FindOneOptions, FindOptions, IndexOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::{
+    models::ws_message_model::Event,
+    repositories::mongo_repository::is_duplicate_key_error,
+    websockets::{SharedState, Topic},
+};
+
+/// Capped collection the instances of a tenant share their events through.
+const EVENTS_COLLECTION: &str = "ws_events";
+const COUNTERS_COLLECTION: &str = "ws_counters";
+/// Size of the capped collection. The oldest events are overwritten first.
+const EVENTS_MAX_DOCUMENTS: u64 = 1000;
+const EVENTS_MAX_BYTES: u64 = 16 * 1024 * 1024;
+/// How long to wait before tailing the collection again once the cursor dies.
+const TAIL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// How many times an event is numbered again when other instances keep taking its number.
+const PUBLISH_ATTEMPTS: usize = 20;
+
+/// Where WebSocket events go to reach their subscribers.
+#[async_trait]
+pub trait Broadcaster: Send + Sync {
+    /// Sends the event to the clients subscribed to any of `topics`, on every instance of the
+    /// API. `change_id` is the change stream event the event reports, if it came from one.
+    /// Every instance watching the database sees the same id, so the event is only sent once.
+    async fn publish(&self, topics: Vec<Topic>, event: Event, change_id: Option<String>);
+}
+
+fn event_value(event: &Event) -> Option<Value> {
+    match serde_json::to_value(event) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            eprintln!("Failed to serialize event: {}", e);
+            None
+        }
+    }
+}
+
+/// Delivers events to the clients of this instance only. Enough when a single instance runs.
+pub struct InProcessBroadcaster {
+    shared_state: Arc<Mutex<SharedState>>,
+}
+
+impl InProcessBroadcaster {
+    pub fn new(shared_state: Arc<Mutex<SharedState>>) -> Self {
+        InProcessBroadcaster { shared_state }
+    }
+}
+
+#[async_trait]
+impl Broadcaster for InProcessBroadcaster {
+    async fn publish(&self, topics: Vec<Topic>, event: Event, _change_id: Option<String>) {
+        if let Some(event) = event_value(&event) {
+            self.shared_state.lock().await.publish(topics, event);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EventRecord {
+    #[serde(rename = "_id")]
+    seq: i64,
+    topics: Vec<String>,
+    /// JSON of the event, stored as is so it reaches clients exactly as it was published.
+    event: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    change_id: Option<String>,
+}
+
+/// Shares events between instances through a capped collection in the tenant's database.
+/// Every instance tails it and delivers what it reads to its own clients, so sequence
+/// numbers are the same on all of them and clients can resume on any.
+///
+/// An event is numbered one after the newest event in the collection and written under that
+/// number as its `_id`. When another instance wrote that number first, the insert fails and
+/// the event is numbered again, so events are always written in the order of their numbers
+/// and tailing instances never see a lower number after a higher one.
+pub struct MongoBroadcaster {
+    events: Collection<EventRecord>,
+    /// Number the events continue from while the collection is still empty.
+    first_seq: i64,
+}
+
+impl MongoBroadcaster {
+    /// Creates the collections when needed and starts tailing the events into `shared_state`.
+    pub async fn start(database: &Database, shared_state: Arc<Mutex<SharedState>>) -> mongodb::error::Result<Self> {
+        let exists = database
+            .list_collection_names(doc! { "name": EVENTS_COLLECTION })
+            .await?
+            .contains(&EVENTS_COLLECTION.to_string());
+        if !exists {
+            let options = CreateCollectionOptions::builder()
+                .capped(true)
+                .size(EVENTS_MAX_BYTES)
+                .max(EVENTS_MAX_DOCUMENTS)
+                .build();
+            if let Err(e) = database.create_collection(EVENTS_COLLECTION, options).await {
+                // Another instance may have created it in the meantime.
+                eprintln!("Failed to create {} collection: {}", EVENTS_COLLECTION, e);
+            }
+        }
+
+        let events: Collection<EventRecord> = database.collection(EVENTS_COLLECTION);
+        let change_index = IndexModel::builder()
+            .keys(doc! { "change_id": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { "change_id": { "$type": "string" } })
+                    .build(),
+            )
+            .build();
+        events.create_index(change_index, None).await?;
+
+        // Numbers start from the time the log is first set up, like the in-process ones, so
+        // clients resuming from events numbered before are told to resync. Every instance
+        // reads the same starting point, so the first event cannot be numbered twice.
+        let counters: Collection<Document> = database.collection(COUNTERS_COLLECTION);
+        counters
+            .update_one(
+                doc! { "_id": EVENTS_COLLECTION },
+                doc! { "$setOnInsert": { "seq": DateTime::now().timestamp_millis() } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        let first_seq = counters
+            .find_one(doc! { "_id": EVENTS_COLLECTION }, None)
+            .await?
+            .and_then(|counter| counter.get_i64("seq").ok())
+            .unwrap_or_default();
+
+        let broadcaster = MongoBroadcaster { events, first_seq };
+        let seq = broadcaster.last_seq().await?;
+        shared_state.lock().await.set_seq(seq.max(0) as u64);
+
+        tokio::spawn(tail_events(broadcaster.events.clone(), shared_state));
+
+        Ok(broadcaster)
+    }
+
+    /// Number of the newest event written by any instance.
+    async fn last_seq(&self) -> mongodb::error::Result<i64> {
+        let options = FindOneOptions::builder().sort(doc! { "$natural": -1 }).build();
+        let last = self.events.find_one(None, options).await?;
+
+        Ok(last.map_or(self.first_seq, |record| record.seq))
+    }
+
+    async fn is_published(&self, change_id: &str) -> mongodb::error::Result<bool> {
+        Ok(self.events.find_one(doc! { "change_id": change_id }, None).await?.is_some())
+    }
+}
+
+#[async_trait]
+impl Broadcaster for MongoBroadcaster {
+    async fn publish(&self, topics: Vec<Topic>, event: Event, change_id: Option<String>) {
+        let mut record = EventRecord {
+            seq: 0,
+            topics: topics.iter().map(ToString::to_string).collect(),
+            event: serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string()),
+            change_id,
+        };
+
+        for _ in 0..PUBLISH_ATTEMPTS {
+            record.seq = match self.last_seq().await {
+                Ok(seq) => seq + 1,
+                Err(e) => {
+                    eprintln!("Failed to number event: {}", e);
+                    return;
+                }
+            };
+
+            match self.events.insert_one(&record, None).await {
+                Ok(_) => return,
+                // Either another instance took the number first or it already published this change.
+                Err(e) if is_duplicate_key_error(&e) => {
+                    let Some(change_id) = &record.change_id else {
+                        continue;
+                    };
+                    match self.is_published(change_id).await {
+                        Ok(true) => return,
+                        Ok(false) => {}
+                        Err(e) => {
+                            eprintln!("Failed to publish event: {}", e);
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to publish event: {}", e);
+                    return;
+                }
+            }
+        }
+
+        eprintln!("Failed to publish event: no free number after {} attempts", PUBLISH_ATTEMPTS);
+    }
+}
+
+/// Delivers the events of the capped collection to the clients of this instance as they are
+/// written, starting with the ones already there so they can be replayed. Events are written
+/// in the order of their numbers, so resuming after the last one read misses nothing.
+async fn tail_events(events: Collection<EventRecord>, shared_state: Arc<Mutex<SharedState>>) {
+    let mut last_seq = 0;
+    loop {
+        let options = FindOptions::builder().cursor_type(CursorType::TailableAwait).build();
+        match events.find(doc! { "_id": { "$gt": last_seq } }, options).await {
+            Ok(mut cursor) => {
+                while let Some(record) = cursor.next().await {
+                    let record = match record {
+                        Ok(record) => record,
+                        Err(e) => {
+                            eprintln!("Failed to read published event: {}", e);
+                            break;
+                        }
+                    };
+                    last_seq = last_seq.max(record.seq);
+
+                    let topics = record.topics.iter().filter_map(|topic| Topic::from_str(topic).ok()).collect();
+                    match serde_json::from_str(&record.event) {
+                        Ok(event) => shared_state.lock().await.deliver(record.seq.max(0) as u64, topics, event),
+                        Err(e) => eprintln!("Failed to read published event {}: {}", record.seq, e),
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to tail published events: {}", e),
+        }
+
+        // Tailable cursors die when the collection is empty or falls behind.
+        tokio::time::sleep(TAIL_RETRY_INTERVAL).await;
+    }
+}
+
+/// These need a MongoDB server, given by `MONGODB_TEST_URI`. Tailable cursors also work on a
+/// standalone server. Run them with `cargo test -- --ignored`.
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc, time::Duration};
+
+    use futures::future::join_all;
+    use mongodb::{bson::oid::ObjectId, Client, Database};
+    use serde_json::Value;
+    use shuttle_runtime::SecretStore;
+    use tokio::sync::Mutex;
+
+    use super::{Broadcaster, MongoBroadcaster};
+    use crate::{models::ws_message_model::Event, settings::Settings, websockets::{SharedState, Topic}};
+
+    /// One instance of the API: its own hub, publishing and tailing through the shared database.
+    struct Instance {
+        shared_state: Arc<Mutex<SharedState>>,
+        broadcaster: MongoBroadcaster,
+    }
+
+    impl Instance {
+        async fn start(database: &Database) -> Self {
+            let secrets: SecretStore = serde_json::from_value(serde_json::json!({})).unwrap();
+            let shared_state = Arc::new(Mutex::new(SharedState::new(&Settings::from_secrets(&secrets).unwrap())));
+            let broadcaster = MongoBroadcaster::start(database, shared_state.clone()).await.unwrap();
+            Instance { shared_state, broadcaster }
+        }
+
+        async fn history(&self) -> Vec<(u64, Value)> {
+            self.shared_state.lock().await.history()
+        }
+    }
+
+    async fn test_database() -> Database {
+        let uri = env::var("MONGODB_TEST_URI").unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
+        let client = Client::with_uri_str(uri).await.unwrap();
+        client.database(&format!("cinema-broadcast-test-{}", ObjectId::new()))
+    }
+
+    fn event(session: usize) -> Event {
+        Event::SeatAvailability {
+            session_id: session.to_string(),
+            seats: Vec::new(),
+        }
+    }
+
+    /// Waits until both instances delivered `count` events and returns what they delivered.
+    async fn delivered(a: &Instance, b: &Instance, count: usize) -> (Vec<(u64, Value)>, Vec<(u64, Value)>) {
+        for _ in 0..100 {
+            let (a_history, b_history) = (a.history().await, b.history().await);
+            if a_history.len() >= count && b_history.len() >= count {
+                return (a_history, b_history);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        (a.history().await, b.history().await)
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_TEST_URI"]
+    async fn instances_deliver_the_same_events_in_the_same_order() {
+        let database = test_database().await;
+        let a = Instance::start(&database).await;
+        let b = Instance::start(&database).await;
+
+        // Both instances publish at the same time, so they race for the same numbers.
+        let publishes = (0..40).map(|i| {
+            let instance = if i % 2 == 0 { &a } else { &b };
+            instance.broadcaster.publish(vec![Topic::Admin], event(i), None)
+        });
+        join_all(publishes).await;
+
+        let (a_history, b_history) = delivered(&a, &b, 40).await;
+        database.drop(None).await.unwrap();
+
+        assert_eq!(a_history.len(), 40);
+        assert_eq!(a_history, b_history);
+        let seqs: Vec<u64> = a_history.iter().map(|(seq, _)| *seq).collect();
+        assert!(seqs.windows(2).all(|pair| pair[1] == pair[0] + 1), "numbers have gaps or are out of order: {:?}", seqs);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_TEST_URI"]
+    async fn a_change_seen_by_both_instances_is_delivered_once() {
+        let database = test_database().await;
+        let a = Instance::start(&database).await;
+        let b = Instance::start(&database).await;
+
+        let change_id = Some("change-1".to_string());
+        tokio::join!(
+            a.broadcaster.publish(vec![Topic::Admin], event(1), change_id.clone()),
+            b.broadcaster.publish(vec![Topic::Admin], event(1), change_id),
+        );
+        b.broadcaster.publish(vec![Topic::Admin], event(2), None).await;
+
+        let (a_history, b_history) = delivered(&a, &b, 2).await;
+        database.drop(None).await.unwrap();
+
+        assert_eq!(a_history.len(), 2);
+        assert_eq!(a_history, b_history);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_TEST_URI"]
+    async fn a_restarted_instance_replays_the_shared_events() {
+        let database = test_database().await;
+        let a = Instance::start(&database).await;
+        for i in 0..3 {
+            a.broadcaster.publish(vec![Topic::Admin], event(i), None).await;
+        }
+
+        // Started after the events were published, like an instance that was restarted.
+        let b = Instance::start(&database).await;
+        a.broadcaster.publish(vec![Topic::Admin], event(3), None).await;
+
+        let (a_history, b_history) = delivered(&a, &b, 4).await;
+        database.drop(None).await.unwrap();
+
+        assert_eq!(b_history.len(), 4);
+        assert_eq!(a_history, b_history);
+    }
+}
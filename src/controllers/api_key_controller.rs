@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    auth::{api_key_prefix, generate_api_key, hash_api_key},
+    models::api_key_model::{ApiKey, ApiKeyRequest, ApiKeyResponse, IssuedApiKey},
+    repositories::Repositories,
+};
+
+pub async fn load_api_keys(
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<ApiKeyResponse>>, StatusCode> {
+    let api_keys = repositories.api_keys.list().await?;
+
+    Ok(Json(api_keys.into_iter().map(Into::into).collect()))
+}
+
+/// Issues a key for a partner. The response is the only place the key is ever shown.
+pub async fn add_api_key(
+    Extension(repositories): Extension<Repositories>,
+    Json(request): Json<ApiKeyRequest>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), StatusCode> {
+    if request.name.trim().is_empty() || request.scopes.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut scopes = Vec::new();
+    for scope in request.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let key = generate_api_key();
+    let api_key = repositories
+        .api_keys
+        .insert(ApiKey {
+            id: None,
+            name: request.name,
+            prefix: api_key_prefix(&key),
+            key_hash: hash_api_key(&key),
+            scopes,
+            uses: 0,
+            last_used_at: None,
+            created_at: DateTime::now(),
+            rotated_at: None,
+            revoked_at: None,
+        })
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedApiKey {
+            key,
+            api_key: api_key.into(),
+        }),
+    ))
+}
+
+/// Replaces the key with a new one, keeping its scopes and usage. The old key stops
+/// working straight away.
+pub async fn rotate_api_key(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<IssuedApiKey>, StatusCode> {
+    let api_key_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let key = generate_api_key();
+    if !repositories
+        .api_keys
+        .rotate(api_key_id, &api_key_prefix(&key), &hash_api_key(&key), DateTime::now())
+        .await?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let api_key = repositories.api_keys.find(api_key_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(IssuedApiKey {
+        key,
+        api_key: api_key.into(),
+    }))
+}
+
+/// Disables the key for good. It stays listed with its usage.
+pub async fn revoke_api_key(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<ApiKeyResponse>, StatusCode> {
+    let api_key_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if !repositories.api_keys.revoke(api_key_id, DateTime::now()).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let api_key = repositories.api_keys.find(api_key_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(api_key.into()))
+}
@@ -0,0 +1,106 @@
+use axum::{extract::Extension, http::StatusCode, response::Json};
+use chrono::Duration;
+use mongodb::bson::DateTime;
+
+use crate::{
+    auth::{hash_password, verify_password, AuthUser, JwtSigner, MIN_PASSWORD_LEN},
+    models::{
+        promotion_model::normalize_customer,
+        user_model::{LoginRequest, RegisterRequest, Role, TokenResponse, User, UserRequest, UserResponse},
+    },
+    repositories::Repositories,
+    settings::Settings,
+    tenancy::Tenant,
+};
+
+fn token_response(signer: &JwtSigner, settings: &Settings, tenant: &Tenant, user: User) -> Result<TokenResponse, StatusCode> {
+    let ttl = Duration::minutes(settings.access_token_ttl_minutes);
+    let access_token = signer.issue(&user, tenant, ttl).map_err(|e| {
+        eprintln!("Failed to issue access token: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: ttl.num_seconds(),
+        user: user.into(),
+    })
+}
+
+/// Stores a new account. Fails with `CONFLICT` when the email is taken.
+async fn create_user(
+    repositories: &Repositories,
+    email: &str,
+    name: String,
+    password: &str,
+    role: Role,
+) -> Result<User, StatusCode> {
+    let email = normalize_customer(email);
+    if name.trim().is_empty() || !email.contains('@') || password.len() < MIN_PASSWORD_LEN {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let password_hash = hash_password(password).ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    repositories
+        .users
+        .insert(User {
+            id: None,
+            email,
+            name,
+            role,
+            password_hash,
+            created_at: DateTime::now(),
+        })
+        .await?
+        .ok_or(StatusCode::CONFLICT)
+}
+
+pub async fn login(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(signer): Extension<JwtSigner>,
+    Extension(tenant): Extension<Tenant>,
+    Json(request): Json<LoginRequest>,
+) -> Result<Json<TokenResponse>, StatusCode> {
+    let user = repositories
+        .users
+        .find_by_email(&normalize_customer(&request.email))
+        .await?
+        .filter(|user| verify_password(&request.password, &user.password_hash))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(token_response(&signer, &settings, &tenant, user)?))
+}
+
+/// Signs a customer up and logs them in.
+pub async fn register(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(signer): Extension<JwtSigner>,
+    Extension(tenant): Extension<Tenant>,
+    Json(request): Json<RegisterRequest>,
+) -> Result<(StatusCode, Json<TokenResponse>), StatusCode> {
+    let user = create_user(&repositories, &request.email, request.name, &request.password, Role::Customer).await?;
+
+    Ok((StatusCode::CREATED, Json(token_response(&signer, &settings, &tenant, user)?)))
+}
+
+pub async fn current_user(
+    user: AuthUser,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<UserResponse>, StatusCode> {
+    let user = repositories.users.find(user.id).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(user.into()))
+}
+
+/// Creates an account with any role, e.g. for box office staff.
+pub async fn add_user(
+    Extension(repositories): Extension<Repositories>,
+    Json(request): Json<UserRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), StatusCode> {
+    let user = create_user(&repositories, &request.email, request.name, &request.password, request.role).await?;
+
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
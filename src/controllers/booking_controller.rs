@@ -0,0 +1,387 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    auth::Caller,
+    broadcast::Broadcaster,
+    controllers::{
+        price_list_controller::quote_seats, promotion_controller::apply_promotion,
+        reservation_controller::notify_seat_changes,
+    },
+    models::{
+        booking_model::{Booking, BookingRequest, BookingStatus, Ticket},
+        price_list_model::{QuoteSeat, TicketType},
+        promotion_model::{normalize_customer, AppliedPromotion, PromotionOutcome},
+        reservation_model::{SeatRef, SeatStatus},
+    },
+    repositories::Repositories,
+    settings::Settings,
+    ticket_signing::TicketSigner,
+};
+
+fn booking_seats(booking: &Booking) -> Vec<SeatRef> {
+    booking
+        .tickets
+        .iter()
+        .map(|ticket| SeatRef {
+            row: ticket.row.clone(),
+            number: ticket.number,
+        })
+        .collect()
+}
+
+pub async fn load_booking(repositories: &Repositories, id_str: &str) -> Result<Booking, StatusCode> {
+    let booking_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    repositories
+        .bookings
+        .find(booking_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Moves the booking to `to`, failing with `CONFLICT` if the lifecycle does not allow it
+/// or another request changed the booking first.
+async fn transition(
+    repositories: &Repositories,
+    mut booking: Booking,
+    to: BookingStatus,
+) -> Result<Booking, StatusCode> {
+    if !booking.status.can_transition_to(to) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let booking_id = booking.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let now = DateTime::now();
+    if !repositories
+        .bookings
+        .update_status(booking_id, booking.status, to, now)
+        .await?
+    {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    booking.status = to;
+    booking.updated_at = now;
+    Ok(booking)
+}
+
+/// Frees the seats of a booking that no longer needs them.
+async fn release_booking_seats(
+    repositories: &Repositories,
+    broadcaster: &Arc<dyn Broadcaster>,
+    booking: &Booking,
+) -> Result<(), StatusCode> {
+    if repositories.reservations.delete_hold(booking.hold_id).await? {
+        notify_seat_changes(broadcaster, booking.session_id, booking_seats(booking), SeatStatus::Available).await;
+    }
+
+    Ok(())
+}
+
+/// Gives back the promo code use of a booking that will not be paid for.
+async fn release_booking_promotion(repositories: &Repositories, booking: &Booking) -> Result<(), StatusCode> {
+    if let Some(promotion) = &booking.promotion {
+        repositories
+            .promotions
+            .release(promotion.promotion_id, &normalize_customer(&booking.buyer.email))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Customers and partners can only see and change their own bookings.
+pub fn ensure_booking_access(caller: &Caller, booking: &Booking) -> Result<(), StatusCode> {
+    if caller.owns_booking(booking) {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Creates a pending booking from a live hold. A promo code that cannot be used fails the
+/// booking with `CONFLICT` and a body naming the reason. Customers book under their own
+/// email, and bookings made with a partner key belong to that key.
+pub async fn create_booking(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(signer): Extension<TicketSigner>,
+    Json(request): Json<BookingRequest>,
+) -> Result<Response, StatusCode> {
+    let session_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if request.buyer.name.trim().is_empty() || !request.buyer.email.contains('@') {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if !caller.can_book_for(&request.buyer.email) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let now = DateTime::now();
+    let reservations = repositories.reservations.list_for_hold(request.hold_id).await?;
+    // Holds of anyone else are treated as unknown, so their ids cannot be probed.
+    if reservations.is_empty() || !reservations.iter().all(|reservation| caller.owns_hold(reservation)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if reservations.iter().any(|reservation| reservation.session_id != session_id) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Only a live, unconfirmed hold can be turned into a booking.
+    if reservations
+        .iter()
+        .any(|reservation| reservation.expires_at.is_none_or(|expires_at| expires_at <= now))
+    {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    if request.seats.iter().any(|requested| {
+        !reservations
+            .iter()
+            .any(|reservation| reservation.row == requested.row && reservation.number == requested.number)
+    }) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let seats: Vec<QuoteSeat> = reservations
+        .iter()
+        .map(|reservation| QuoteSeat {
+            row: reservation.row.clone(),
+            number: reservation.number,
+            ticket_type: request
+                .seats
+                .iter()
+                .find(|requested| requested.row == reservation.row && requested.number == reservation.number)
+                .map_or(TicketType::Adult, |requested| requested.ticket_type),
+        })
+        .collect();
+    let mut quote = quote_seats(&repositories, &settings, session_id, &seats).await?;
+
+    let customer = normalize_customer(&request.buyer.email);
+    let promotion = match &request.promo_code {
+        Some(code) => {
+            let Some(promotion) = apply_promotion(&repositories, &settings, &mut quote, code, Some(&customer)).await?
+            else {
+                return Ok((StatusCode::CONFLICT, Json(quote.promotion)).into_response());
+            };
+            if let Some(reason) = repositories.promotions.redeem(&promotion, &customer).await? {
+                let rejection = PromotionOutcome::rejected(promotion.code, reason);
+                return Ok((StatusCode::CONFLICT, Json(rejection)).into_response());
+            }
+            promotion.id.map(|promotion_id| AppliedPromotion {
+                promotion_id,
+                code: promotion.code,
+                discount: quote.discount,
+            })
+        }
+        None => None,
+    };
+
+    let booking_id = ObjectId::new();
+    let tickets: Vec<Ticket> = quote
+        .lines
+        .into_iter()
+        .map(|line| {
+            let ticket_id = ObjectId::new();
+            Ticket {
+                id: ticket_id,
+                row: line.row,
+                number: line.number,
+                category: line.category,
+                ticket_type: line.ticket_type,
+                price: line.amount - line.discount,
+                token: signer.sign(booking_id, ticket_id),
+                checked_in_at: None,
+            }
+        })
+        .collect();
+
+    let booking = Booking {
+        id: Some(booking_id),
+        session_id,
+        hold_id: request.hold_id,
+        buyer: request.buyer,
+        total: quote.total,
+        tickets,
+        currency: quote.currency,
+        promotion,
+        api_key_id: caller.api_key_id(),
+        status: BookingStatus::Pending,
+        created_at: now,
+        updated_at: now,
+    };
+
+    match repositories.bookings.insert(booking.clone()).await? {
+        Some(booking) => Ok((StatusCode::CREATED, Json(booking)).into_response()),
+        None => {
+            release_booking_promotion(&repositories, &booking).await?;
+            Err(StatusCode::CONFLICT)
+        }
+    }
+}
+
+pub async fn get_booking(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Booking>, StatusCode> {
+    let booking = load_booking(&repositories, &id_str).await?;
+    ensure_booking_access(&caller, &booking)?;
+
+    Ok(Json(booking))
+}
+
+pub async fn list_session_bookings(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<Booking>>, StatusCode> {
+    let session_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let bookings = repositories.bookings.list_for_session(session_id).await?;
+
+    Ok(Json(bookings))
+}
+
+pub async fn confirm_booking(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+    Extension(broadcaster): Extension<Arc<dyn Broadcaster>>,
+) -> Result<Json<Booking>, StatusCode> {
+    let booking = load_booking(&repositories, &id_str).await?;
+    ensure_booking_access(&caller, &booking)?;
+
+    // The status is claimed first so two requests cannot both confirm the booking.
+    let pending = booking.clone();
+    let booking = transition(&repositories, booking, BookingStatus::Confirmed).await?;
+    let booking_id = booking.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let confirmed = repositories
+        .reservations
+        .confirm_hold(booking.hold_id, DateTime::now())
+        .await;
+    if !matches!(confirmed, Ok(true)) {
+        // A booking without its seats cannot stay confirmed, so the status is put back first.
+        repositories
+            .bookings
+            .update_status(booking_id, BookingStatus::Confirmed, BookingStatus::Pending, DateTime::now())
+            .await?;
+        confirmed?;
+
+        // The hold ran out before the booking was confirmed, so its seats may be gone.
+        let booking = transition(&repositories, pending, BookingStatus::Cancelled).await?;
+        release_booking_promotion(&repositories, &booking).await?;
+        return Err(StatusCode::CONFLICT);
+    }
+
+    notify_seat_changes(&broadcaster, booking.session_id, booking_seats(&booking), SeatStatus::Booked).await;
+
+    Ok(Json(booking))
+}
+
+pub async fn cancel_booking(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+    Extension(broadcaster): Extension<Arc<dyn Broadcaster>>,
+) -> Result<Json<Booking>, StatusCode> {
+    let booking = load_booking(&repositories, &id_str).await?;
+    ensure_booking_access(&caller, &booking)?;
+    let booking = transition(&repositories, booking, BookingStatus::Cancelled).await?;
+    release_booking_seats(&repositories, &broadcaster, &booking).await?;
+    release_booking_promotion(&repositories, &booking).await?;
+
+    Ok(Json(booking))
+}
+
+pub async fn refund_booking(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(broadcaster): Extension<Arc<dyn Broadcaster>>,
+) -> Result<Json<Booking>, StatusCode> {
+    let booking = load_booking(&repositories, &id_str).await?;
+    let booking = transition(&repositories, booking, BookingStatus::Refunded).await?;
+    release_booking_seats(&repositories, &broadcaster, &booking).await?;
+    release_booking_promotion(&repositories, &booking).await?;
+
+    Ok(Json(booking))
+}
+
+/// Cancels the pending booking made from a hold that ran out, since its seats are gone,
+/// and gives back its promo code use so abandoned checkouts do not count against the limits.
+pub async fn cancel_expired_booking(repositories: &Repositories, hold_id: ObjectId) -> Result<(), StatusCode> {
+    let Some(booking) = repositories.bookings.find_for_hold(hold_id).await? else {
+        return Ok(());
+    };
+    if booking.status == BookingStatus::Pending {
+        let booking = transition(repositories, booking, BookingStatus::Cancelled).await?;
+        release_booking_promotion(repositories, &booking).await?;
+    }
+
+    Ok(())
+}
+
+/// Checks that a session can be removed without losing tickets that were already used.
+pub async fn ensure_session_cancellable(
+    repositories: &Repositories,
+    session_id: ObjectId,
+) -> Result<(), StatusCode> {
+    let bookings = repositories.bookings.list_for_session(session_id).await?;
+    if bookings
+        .iter()
+        .any(|booking| booking.status == BookingStatus::CheckedIn)
+    {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    Ok(())
+}
+
+/// Cascade applied when a session is cancelled: pending bookings are cancelled,
+/// confirmed ones are refunded, their promo code uses are given back and every seat
+/// taken for the session is freed.
+/// Bookings themselves are kept as a record of the sale.
+pub async fn cancel_session_bookings(
+    repositories: &Repositories,
+    session_id: ObjectId,
+) -> Result<(), StatusCode> {
+    let bookings = repositories.bookings.list_for_session(session_id).await?;
+    let now = DateTime::now();
+    for booking in bookings {
+        let Some(booking_id) = booking.id else {
+            continue;
+        };
+        let next = match booking.status {
+            BookingStatus::Pending => BookingStatus::Cancelled,
+            BookingStatus::Confirmed => BookingStatus::Refunded,
+            _ => continue,
+        };
+        if repositories
+            .bookings
+            .update_status(booking_id, booking.status, next, now)
+            .await?
+        {
+            release_booking_promotion(repositories, &booking).await?;
+        }
+    }
+
+    repositories.reservations.delete_for_session(session_id).await?;
+
+    Ok(())
+}
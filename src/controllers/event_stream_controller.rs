@@ -0,0 +1,82 @@
+use std::{convert::Infallible, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Extension, Query},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
+};
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    auth::{AuthUser, JwtSigner},
+    models::user_model::Role,
+    tenancy::Tenant,
+    websockets::{SharedState, Topic},
+};
+
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+#[derive(Deserialize)]
+pub struct EventStreamQuery {
+    /// Comma separated topics, the same ones WebSocket clients subscribe to.
+    topics: String,
+    /// Access token, since `EventSource` cannot set an `Authorization` header.
+    token: Option<String>,
+}
+
+/// Streams the events the WebSocket hub sends, for clients that cannot use WebSockets. Each
+/// event's id is its `seq`, so a reconnecting `EventSource` resumes through `Last-Event-ID`.
+/// When the missed events are no longer kept, the stream starts with a `resync` event.
+/// Staff identify with a bearer token or a `?token=` query parameter to get the `admin` topic.
+pub async fn event_stream(
+    Query(query): Query<EventStreamQuery>,
+    headers: HeaderMap,
+    user: Option<AuthUser>,
+    Extension(signer): Extension<JwtSigner>,
+    Extension(tenant): Extension<Tenant>,
+    Extension(shared_state): Extension<Arc<Mutex<SharedState>>>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, StatusCode> {
+    let user = match (user, &query.token) {
+        (Some(user), _) => Some(user),
+        (None, Some(token)) => Some(signer.verify(token, &tenant).ok_or(StatusCode::UNAUTHORIZED)?),
+        (None, None) => None,
+    };
+    let topics = query
+        .topics
+        .split(',')
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+        .map(Topic::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    if topics.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if topics.contains(&Topic::Admin) && !user.is_some_and(|user| user.role.allows(Role::Staff)) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let last_seq = match headers.get(LAST_EVENT_ID_HEADER) {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .ok_or(StatusCode::BAD_REQUEST)?,
+        ),
+        None => None,
+    };
+
+    let (client, resync_required) = shared_state.lock().await.connect_sse(topics, last_seq);
+    let resync = resync_required.then(|| SseEvent::default().event("resync").data(r#"{"resync_required":true}"#));
+    let events = stream::unfold(client, |client| async move {
+        let event = client.next().await?;
+        Some((event, client))
+    });
+
+    let stream = stream::iter(resync).chain(events).map(Ok);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -1,200 +1,269 @@
-use crate::models::hall_model::{Hall, HallDetail, HallUpdate};
+use crate::{
+    controllers::{
+        reservation_controller::ensure_seats_in_layout,
+        venue_controller::{ensure_venue_exists, is_valid_time_zone, venue_filter},
+    },
+    events::{DomainEvent, EventBus},
+    models::{
+        hall_model::{Hall, HallDetail, HallUpdate},
+        seat_layout_model::SeatLayout,
+        session_model::Session,
+        venue_model::VenueFilter,
+    },
+    repositories::Repositories,
+    settings::Settings,
+};
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
     response::Json,
     Json as AxumJson,
 };
-use futures::TryStreamExt;
-use mongodb::{
-    bson::{doc, from_document, oid::ObjectId, Bson, Document},
-    Client,
-};
-use serde_json::Value;
-use std::sync::Arc;
+use chrono_tz::Tz;
+use mongodb::bson::{oid::ObjectId, DateTime};
 
-pub async fn load_halls_with_details(
-    Extension(client): Extension<Arc<Client>>,
-) -> Result<Json<Vec<Hall>>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let halls_collection = db.collection::<Hall>("halls");
+/// Time zone of the hall, looking its venue up when the hall does not set one.
+pub async fn hall_time_zone(repositories: &Repositories, settings: &Settings, hall: &Hall) -> Result<Tz, StatusCode> {
+    let venue = match hall.venue_id.filter(|_| hall.time_zone.is_none()) {
+        Some(venue_id) => repositories.venues.find(venue_id).await?,
+        None => None,
+    };
 
-    let pipeline = vec![];
+    Ok(settings.hall_time_zone(hall, venue.as_ref()))
+}
 
-    let mut cursor = match halls_collection.aggregate(pipeline, None).await {
-        Ok(cursor) => cursor,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+/// Time zone of the session's hall, or `TIME_ZONE` for a session without one.
+pub async fn session_time_zone(
+    repositories: &Repositories,
+    settings: &Settings,
+    session: &Session,
+) -> Result<Tz, StatusCode> {
+    let hall = match session.hall_id {
+        Some(hall_id) => repositories.halls.find(hall_id).await?,
+        None => None,
     };
 
-    let mut result: Vec<Hall> = Vec::new();
-    while let Some(doc) = cursor.try_next().await.expect("Failed to iterate") {
-        let hall_detail: Hall = match from_document(doc) {
-            Ok(hall) => hall,
-            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-        };
-        result.push(hall_detail);
+    match hall {
+        Some(hall) => hall_time_zone(repositories, settings, &hall).await,
+        None => Ok(settings.time_zone),
     }
+}
+
+fn has_negative_buffer(pre_show_minutes: Option<i64>, cleaning_minutes: Option<i64>) -> bool {
+    pre_show_minutes.is_some_and(|minutes| minutes < 0) || cleaning_minutes.is_some_and(|minutes| minutes < 0)
+}
 
-    Ok(Json(result))
+fn has_unknown_time_zone(time_zone: Option<&str>) -> bool {
+    time_zone.is_some_and(|time_zone| !is_valid_time_zone(time_zone))
+}
+
+pub async fn load_halls_with_details(
+    Query(filter): Query<VenueFilter>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<Hall>>, StatusCode> {
+    let halls = match venue_filter(&repositories, &filter).await? {
+        Some(venue_id) => repositories.halls.list_for_venue(venue_id).await?,
+        None => repositories.halls.list().await?,
+    };
+
+    Ok(Json(halls))
 }
 
 pub async fn load_hall_with_details(
     Path(id_str): Path<String>,
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
 ) -> Result<Json<HallDetail>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let halls_collection = db.collection::<Hall>("halls");
-
     let hall_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
 
-    let pipeline = vec![
-        doc! {
-            "$match": { "_id": hall_id }
-        },
-        doc! {
-            "$lookup": {
-                "from": "sessions",
-                "localField": "_id",
-                "foreignField": "hall_id",
-                "as": "sessions"
-            }
-        },
-        doc! {
-            "$lookup": {
-                "from": "movies",
-                "let": { "movie_id": "$sessions.movie_id" },
-                "pipeline": [
-                    { "$match": { "$expr": { "$in": [ "$_id", "$$movie_id" ] } } }
-                ],
-                "as": "movies"
-            }
-        },
-    ];
-
-    let mut cursor = match halls_collection.aggregate(pipeline, None).await {
-        Ok(cursor) => cursor,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-
-    if let Some(doc) = cursor.try_next().await.expect("Failed to iterate") {
-        let hall_detail: HallDetail = match from_document(doc) {
-            Ok(detail) => detail,
-            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-        };
-        Ok(Json(hall_detail))
-    } else {
-        Err(StatusCode::NOT_FOUND)
+    match repositories.halls.find_with_details(hall_id).await? {
+        Some(hall_detail) => Ok(Json(hall_detail)),
+        None => Err(StatusCode::NOT_FOUND),
     }
 }
 
 pub async fn add_hall(
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
     AxumJson(hall): AxumJson<Hall>,
 ) -> Result<Json<Hall>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let halls_collection = db.collection::<Hall>("halls");
+    if has_negative_buffer(hall.pre_show_minutes, hall.cleaning_minutes) || has_unknown_time_zone(hall.time_zone.as_deref()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    ensure_venue_exists(&repositories, hall.venue_id).await?;
+    if let Some(layout) = &hall.layout {
+        if let Err(e) = layout.validate(hall.capacity) {
+            eprintln!("Invalid seat layout: {}", e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
 
-    match halls_collection.insert_one(hall.clone(), None).await {
-        Ok(_) => Ok(Json(hall)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    let hall = repositories.halls.insert(hall).await?;
+    if let Some(hall_id) = hall.id {
+        events.publish(DomainEvent::HallAdded(hall_id));
     }
+
+    Ok(Json(hall))
 }
 
 pub async fn update_hall(
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
     axum::extract::Path(id_str): axum::extract::Path<String>,
     Json(update_data): Json<HallUpdate>,
 ) -> Result<Json<HallUpdate>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let halls_collection = db.collection::<Document>("halls");
-
     let hall_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
 
-    let json =
-        serde_json::to_value(&update_data).unwrap_or_else(|_| Value::Object(Default::default()));
-
-    let mut update_doc = Document::new();
-    if let Value::Object(obj) = json {
-        for (key, value) in obj {
-            if !value.is_null() {
-                let bson_value = match Bson::try_from(value) {
-                    Ok(bv) => bv,
-                    Err(_) => continue,
-                };
-                update_doc.insert(key, bson_value);
+    if has_negative_buffer(update_data.pre_show_minutes, update_data.cleaning_minutes)
+        || has_unknown_time_zone(update_data.time_zone.as_deref())
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(venue_id) = update_data.venue_id {
+        ensure_venue_exists(&repositories, Some(venue_id)).await?;
+        let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+        // Moving a hall would move its upcoming sessions to another venue along with it.
+        if hall.venue_id.is_some_and(|current| current != venue_id) {
+            let upcoming = repositories
+                .sessions
+                .list_for_hall(hall_id, DateTime::now(), DateTime::MAX)
+                .await?;
+            if !upcoming.is_empty() {
+                eprintln!("Hall {} has upcoming sessions and cannot change venue", hall_id);
+                return Err(StatusCode::CONFLICT);
             }
         }
     }
 
-    let update = doc! {
-        "$set": update_doc,
-    };
-
-    match halls_collection
-        .update_one(doc! {"_id": hall_id}, update, None)
-        .await
-    {
-        Ok(update_result) => {
-            if update_result.matched_count == 1 {
-                Ok(Json(update_data))
-            } else {
-                Err(StatusCode::NOT_FOUND)
+    if let Some(capacity) = update_data.capacity {
+        let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+        if let Some(layout) = &hall.layout {
+            if layout.seat_count() != capacity {
+                eprintln!("Capacity {} does not match the {} seats in the layout", capacity, layout.seat_count());
+                return Err(StatusCode::BAD_REQUEST);
             }
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    if repositories.halls.update(hall_id, &update_data).await? {
+        events.publish(DomainEvent::HallUpdated(hall_id));
+        Ok(Json(update_data))
+    } else {
+        Err(StatusCode::NOT_FOUND)
     }
 }
 
 pub async fn delete_hall(
     Path(id_str): Path<String>,
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
 ) -> Result<Json<String>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let halls_collection = db.collection::<Hall>("halls");
-    let sessions_collection = db.collection::<Document>("sessions");
+    let hall_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
 
+    if repositories.halls.delete(hall_id).await? {
+        events.publish(DomainEvent::HallDeleted(hall_id));
+        Ok(Json("Hall ID set to null in associated sessions successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub async fn get_hall_layout(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<SeatLayout>, StatusCode> {
     let hall_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
 
-    match halls_collection
-        .delete_one(doc! {"_id": hall_id}, None)
-        .await
-    {
-        Ok(delete_result) => {
-            if delete_result.deleted_count == 1 {
-                let update_result = sessions_collection.update_many(
-                    doc! {"hall_id": hall_id.to_string()},
-                    doc! {"$set": {"hall_id": null}},
-                    None
-                ).await;
-                
-                match update_result {
-                    Ok(update_result) => {
-                        if update_result.modified_count > 0 {
-                            println!("Successfully updated {} session(s).", update_result.modified_count);
-                            Ok(Json("Hall ID set to null in associated sessions successfully".to_string()))
-                        } else {
-                            println!("No sessions found for hall_id: {}", hall_id.to_string());
-                            Err(StatusCode::NOT_FOUND)
-                        }
-                    },
-                    Err(e) => {
-                        eprintln!("Error updating sessions: {:?}", e);
-                        Err(StatusCode::INTERNAL_SERVER_ERROR)
-                    }
-                }
-            } else {
-                Err(StatusCode::NOT_FOUND)
-            }
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+
+    hall.layout.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn add_hall_layout(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
+    Json(layout): Json<SeatLayout>,
+) -> Result<Json<SeatLayout>, StatusCode> {
+    save_hall_layout(&repositories, &events, &id_str, layout, false).await
+}
+
+pub async fn replace_hall_layout(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
+    Json(layout): Json<SeatLayout>,
+) -> Result<Json<SeatLayout>, StatusCode> {
+    save_hall_layout(&repositories, &events, &id_str, layout, true).await
+}
+
+async fn save_hall_layout(
+    repositories: &Repositories,
+    events: &EventBus,
+    id_str: &str,
+    layout: SeatLayout,
+    replace: bool,
+) -> Result<Json<SeatLayout>, StatusCode> {
+    let hall_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    match (&hall.layout, replace) {
+        (Some(_), false) => return Err(StatusCode::CONFLICT),
+        (None, true) => return Err(StatusCode::NOT_FOUND),
+        _ => {}
+    }
+
+    if let Err(e) = layout.validate(hall.capacity) {
+        eprintln!("Invalid seat layout: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if replace {
+        // Seats already held or booked for upcoming sessions have to survive the new layout.
+        let upcoming = repositories.sessions.list_for_hall(hall_id, DateTime::now(), DateTime::MAX).await?;
+        let session_ids: Vec<ObjectId> = upcoming.iter().filter_map(|session| session.id).collect();
+        ensure_seats_in_layout(repositories, &session_ids, Some(&layout)).await?;
+    }
+
+    if repositories.halls.set_layout(hall_id, Some(&layout)).await? {
+        events.publish(DomainEvent::HallUpdated(hall_id));
+        Ok(Json(layout))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub async fn delete_hall_layout(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(events): Extension<EventBus>,
+) -> Result<Json<String>, StatusCode> {
+    let hall_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if hall.layout.is_none() {
+        return Err(StatusCode::NOT_FOUND);
     }
+
+    repositories.halls.set_layout(hall_id, None).await?;
+    events.publish(DomainEvent::HallUpdated(hall_id));
+
+    Ok(Json("Hall layout deleted successfully".to_string()))
 }
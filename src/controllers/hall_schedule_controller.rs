@@ -0,0 +1,323 @@
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{Duration, NaiveDate, NaiveTime};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::Deserialize;
+
+use crate::{
+    controllers::hall_controller::hall_time_zone,
+    models::{
+        hall_model::Hall,
+        hall_schedule_model::{
+            BlockedReason, BlockedTime, HallCalendar, HallClosure, HallClosureRequest, HallOpeningHours,
+            HallUnavailability, OpeningHours, TimeRange,
+        },
+    },
+    repositories::Repositories,
+    settings::{instant_in, local_time_in, Settings},
+};
+
+/// Longest span the calendar can be asked for at once.
+const MAX_CALENDAR_DAYS: u32 = 31;
+
+/// Checks that a session running `[start, end)` in the hall falls inside one of its opening
+/// windows and does not touch any of its closures. A hall without opening hours is always open.
+/// Opening hours are read on the hall's wall clock.
+pub async fn check_hall_schedule(
+    repositories: &Repositories,
+    settings: &Settings,
+    hall: &Hall,
+    start: DateTime,
+    end: DateTime,
+) -> Result<Option<HallUnavailability>, StatusCode> {
+    let hall_id = hall.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let opening_hours = repositories.hall_schedules.list_opening_hours(hall_id).await?;
+    let time_zone = hall_time_zone(repositories, settings, hall).await?;
+    let (local_start, local_end) = (local_time_in(time_zone, start), local_time_in(time_zone, end));
+    if !opening_hours.is_empty() && !opening_hours.iter().any(|entry| entry.hours.contains(local_start, local_end)) {
+        return Ok(Some(HallUnavailability::OutsideOpeningHours));
+    }
+
+    let closure = repositories
+        .hall_schedules
+        .list_closures(hall_id, start, end)
+        .await?
+        .into_iter()
+        .next();
+
+    Ok(closure.map(HallUnavailability::closed))
+}
+
+async fn ensure_hall_exists(repositories: &Repositories, id_str: &str) -> Result<ObjectId, StatusCode> {
+    let hall_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match repositories.halls.find(hall_id).await? {
+        Some(_) => Ok(hall_id),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn load_hall_opening_hours(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<HallOpeningHours>>, StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+    let opening_hours = repositories.hall_schedules.list_opening_hours(hall_id).await?;
+
+    Ok(Json(opening_hours))
+}
+
+/// Adds a weekly opening window to the hall. Once a hall has any, sessions have to fit in one.
+pub async fn add_hall_opening_hours(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Json(hours): Json<OpeningHours>,
+) -> Result<(StatusCode, Json<HallOpeningHours>), StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+
+    let opening_hours = repositories
+        .hall_schedules
+        .insert_opening_hours(HallOpeningHours {
+            id: None,
+            hall_id,
+            hours,
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(opening_hours)))
+}
+
+pub async fn delete_hall_opening_hours(
+    Path((id_str, hours_id)): Path<(String, String)>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<String>, StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+    let hours_id = match ObjectId::parse_str(&hours_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.hall_schedules.delete_opening_hours(hall_id, hours_id).await? {
+        Ok(Json("Opening hours deleted successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Closures of the hall that have not ended yet.
+pub async fn load_hall_closures(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<HallClosure>>, StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+    let closures = repositories
+        .hall_schedules
+        .list_closures(hall_id, DateTime::now(), DateTime::MAX)
+        .await?;
+
+    Ok(Json(closures))
+}
+
+/// Blocks the hall for a holiday, private event or maintenance. Sessions already scheduled
+/// in that time have to be moved or deleted first.
+pub async fn add_hall_closure(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Json(request): Json<HallClosureRequest>,
+) -> Result<(StatusCode, Json<HallClosure>), StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+    if request.end <= request.start {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (start, end) = (DateTime::from_chrono(request.start), DateTime::from_chrono(request.end));
+    let sessions = repositories.sessions.list_for_hall(hall_id, start, end).await?;
+    if !sessions.is_empty() {
+        eprintln!("Closure of hall {} overlaps {} scheduled session(s)", hall_id, sessions.len());
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let closure = repositories
+        .hall_schedules
+        .insert_closure(HallClosure {
+            id: None,
+            hall_id,
+            kind: request.kind,
+            note: request.note,
+            start,
+            end,
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(closure)))
+}
+
+pub async fn delete_hall_closure(
+    Path((id_str, closure_id)): Path<(String, String)>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<String>, StatusCode> {
+    let hall_id = ensure_hall_exists(&repositories, &id_str).await?;
+    let closure_id = match ObjectId::parse_str(&closure_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.hall_schedules.delete_closure(hall_id, closure_id).await? {
+        Ok(Json("Closure deleted successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CalendarQuery {
+    /// First local date shown. Defaults to today.
+    pub from: Option<NaiveDate>,
+    #[serde(default = "default_calendar_days")]
+    pub days: u32,
+}
+
+fn default_calendar_days() -> u32 {
+    7
+}
+
+/// Sorts and joins overlapping or touching ranges, given in milliseconds.
+fn merge_ranges(mut ranges: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    ranges.sort();
+    let mut merged: Vec<(i64, i64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Parts of `[from, to)` not covered by the merged `ranges`.
+fn gaps(ranges: &[(i64, i64)], from: i64, to: i64) -> Vec<(i64, i64)> {
+    let mut gaps = Vec::new();
+    let mut cursor = from;
+    for &(start, end) in ranges {
+        if start > cursor {
+            gaps.push((cursor, start.min(to)));
+        }
+        cursor = cursor.max(end);
+        if cursor >= to {
+            break;
+        }
+    }
+    if cursor < to {
+        gaps.push((cursor, to));
+    }
+    gaps
+}
+
+fn blocked(start: i64, end: i64, reason: BlockedReason, source_id: Option<ObjectId>, note: Option<String>) -> BlockedTime {
+    BlockedTime {
+        start: DateTime::from_millis(start),
+        end: DateTime::from_millis(end),
+        reason,
+        source_id,
+        note,
+    }
+}
+
+/// Free and blocked time of the hall over the requested days. Time is blocked when the hall
+/// is not open, closed, or taken by a session and the cleaning after it.
+pub async fn hall_calendar(
+    Path(id_str): Path<String>,
+    Query(query): Query<CalendarQuery>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+) -> Result<Json<HallCalendar>, StatusCode> {
+    let hall_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    if !(1..=MAX_CALENDAR_DAYS).contains(&query.days) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Days are the hall's local days.
+    let time_zone = hall_time_zone(&repositories, &settings, &hall).await?;
+    let first_day = query
+        .from
+        .unwrap_or_else(|| local_time_in(time_zone, DateTime::now()).date());
+    let last_day = first_day + Duration::days(query.days as i64);
+    let from = instant_in(time_zone, first_day.and_time(NaiveTime::MIN)).ok_or(StatusCode::BAD_REQUEST)?;
+    let to = instant_in(time_zone, last_day.and_time(NaiveTime::MIN)).ok_or(StatusCode::BAD_REQUEST)?;
+    let (from_millis, to_millis) = (from.timestamp_millis(), to.timestamp_millis());
+    let clip = |start: i64, end: i64| (start.max(from_millis), end.min(to_millis));
+
+    let mut blocked_times = Vec::new();
+
+    let opening_hours = repositories.hall_schedules.list_opening_hours(hall_id).await?;
+    if !opening_hours.is_empty() {
+        // Windows opening the day before the range can run past midnight into it.
+        let mut open = Vec::new();
+        let mut date = first_day - Duration::days(1);
+        while date < last_day {
+            for entry in &opening_hours {
+                let Some((opens, closes)) = entry.hours.window(date) else {
+                    continue;
+                };
+                if let (Some(opens), Some(closes)) = (instant_in(time_zone, opens), instant_in(time_zone, closes)) {
+                    open.push(clip(opens.timestamp_millis(), closes.timestamp_millis()));
+                }
+            }
+            date += Duration::days(1);
+        }
+        let open: Vec<_> = open.into_iter().filter(|(start, end)| start < end).collect();
+        for (start, end) in gaps(&merge_ranges(open), from_millis, to_millis) {
+            blocked_times.push(blocked(start, end, BlockedReason::OutsideOpeningHours, None, None));
+        }
+    }
+
+    for closure in repositories.hall_schedules.list_closures(hall_id, from, to).await? {
+        let (start, end) = clip(closure.start.timestamp_millis(), closure.end.timestamp_millis());
+        blocked_times.push(blocked(start, end, closure.kind.into(), closure.id, closure.note));
+    }
+
+    let cleaning_millis = settings.cleaning_minutes(&hall) * 60 * 1000;
+    let sessions = repositories
+        .sessions
+        .list_for_hall(hall_id, DateTime::from_millis(from_millis - cleaning_millis), to)
+        .await?;
+    for session in sessions {
+        let (start, end) = clip(session.start.timestamp_millis(), session.end.timestamp_millis() + cleaning_millis);
+        if start < end {
+            blocked_times.push(blocked(start, end, BlockedReason::Session, session.id, session.title));
+        }
+    }
+
+    blocked_times.sort_by_key(|time| time.start);
+    let taken = merge_ranges(
+        blocked_times
+            .iter()
+            .map(|time| (time.start.timestamp_millis(), time.end.timestamp_millis()))
+            .collect(),
+    );
+    let free = gaps(&taken, from_millis, to_millis)
+        .into_iter()
+        .map(|(start, end)| TimeRange {
+            start: DateTime::from_millis(start),
+            end: DateTime::from_millis(end),
+        })
+        .collect();
+
+    Ok(Json(HallCalendar {
+        hall_id,
+        from,
+        to,
+        free,
+        blocked: blocked_times,
+    }))
+}
@@ -1,4 +1,16 @@
+pub mod api_key_controller;
+pub mod auth_controller;
+pub mod booking_controller;
+pub mod event_stream_controller;
 pub mod home_controller;
 pub mod session_controller;
+pub mod session_series_controller;
+pub mod ticket_controller;
 pub mod movie_controller;
-pub mod hall_controller;
\ No newline at end of file
+pub mod hall_controller;
+pub mod hall_schedule_controller;
+pub mod price_list_controller;
+pub mod promotion_controller;
+pub mod reservation_controller;
+pub mod schedule_controller;
+pub mod venue_controller;
\ No newline at end of file
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    controllers::{
+        hall_controller::session_time_zone, promotion_controller::apply_promotion,
+        reservation_controller::load_session_layout,
+    },
+    models::{
+        price_list_model::{PriceList, Quote, QuoteLine, QuoteRequest, QuoteSeat},
+        promotion_model::normalize_customer,
+    },
+    repositories::Repositories,
+    settings::{local_time_in, Settings},
+};
+
+pub async fn load_price_lists(
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<PriceList>>, StatusCode> {
+    let price_lists = repositories.price_lists.list().await?;
+
+    Ok(Json(price_lists))
+}
+
+pub async fn load_price_list(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<PriceList>, StatusCode> {
+    let price_list_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match repositories.price_lists.find(price_list_id).await? {
+        Some(price_list) => Ok(Json(price_list)),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn add_price_list(
+    Extension(repositories): Extension<Repositories>,
+    Json(mut price_list): Json<PriceList>,
+) -> Result<(StatusCode, Json<PriceList>), StatusCode> {
+    if let Err(e) = price_list.validate() {
+        eprintln!("Invalid price list: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    price_list.id = None;
+    let price_list = repositories.price_lists.insert(price_list).await?;
+
+    Ok((StatusCode::CREATED, Json(price_list)))
+}
+
+pub async fn replace_price_list(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Json(mut price_list): Json<PriceList>,
+) -> Result<Json<PriceList>, StatusCode> {
+    let price_list_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if let Err(e) = price_list.validate() {
+        eprintln!("Invalid price list: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    price_list.id = Some(price_list_id);
+    if repositories.price_lists.replace(&price_list).await? {
+        Ok(Json(price_list))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub async fn delete_price_list(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<String>, StatusCode> {
+    let price_list_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.price_lists.delete(price_list_id).await? {
+        Ok(Json("Price list deleted and detached from its sessions successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Prices the given seats of a session with the session's price list. Seats must exist in the
+/// hall layout and be listed once; a session without a price list, or a seat the list has no
+/// price for, cannot be sold and yields `CONFLICT`.
+pub async fn quote_seats(
+    repositories: &Repositories,
+    settings: &Settings,
+    session_id: ObjectId,
+    seats: &[QuoteSeat],
+) -> Result<Quote, StatusCode> {
+    if seats.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (session, layout) = load_session_layout(repositories, session_id).await?;
+    let price_list_id = session.price_list_id.ok_or(StatusCode::CONFLICT)?;
+    let price_list = repositories
+        .price_lists
+        .find(price_list_id)
+        .await?
+        .ok_or(StatusCode::CONFLICT)?;
+    let start = local_time_in(session_time_zone(repositories, settings, &session).await?, session.start);
+
+    let mut seen = HashSet::new();
+    let mut lines = Vec::new();
+    for requested in seats {
+        if !seen.insert((requested.row.as_str(), requested.number)) {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let seat = layout
+            .seat(&requested.row, requested.number)
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let Some(price) = price_list.price(seat.category, requested.ticket_type, start) else {
+            eprintln!(
+                "Price list {} has no price for {:?}/{:?}",
+                price_list_id, seat.category, requested.ticket_type
+            );
+            return Err(StatusCode::CONFLICT);
+        };
+
+        lines.push(QuoteLine {
+            row: seat.row,
+            number: seat.number,
+            category: seat.category,
+            ticket_type: requested.ticket_type,
+            base_amount: price.base_amount,
+            amount: price.amount,
+            applied_rules: price.applied_rules,
+            discount: 0,
+        });
+    }
+
+    let subtotal = lines.iter().map(|line| line.amount).sum();
+    Ok(Quote {
+        session_id,
+        price_list_id,
+        currency: price_list.currency,
+        lines,
+        subtotal,
+        discount: 0,
+        total: subtotal,
+        promotion: None,
+    })
+}
+
+/// Prices a seat selection. With a promo code the quote shows the discount, or why the
+/// code would be refused at checkout.
+pub async fn quote_session(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Json(request): Json<QuoteRequest>,
+) -> Result<Json<Quote>, StatusCode> {
+    let session_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let mut quote = quote_seats(&repositories, &settings, session_id, &request.seats).await?;
+    if let Some(code) = &request.promo_code {
+        let customer = request.email.as_deref().map(normalize_customer);
+        apply_promotion(&repositories, &settings, &mut quote, code, customer.as_deref()).await?;
+    }
+
+    Ok(Json(quote))
+}
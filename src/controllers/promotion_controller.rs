@@ -0,0 +1,160 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    controllers::hall_controller::session_time_zone,
+    models::{
+        price_list_model::Quote,
+        promotion_model::{
+            normalize_code, AppliedPromotion, Promotion, PromotionOutcome, PromotionRejection,
+        },
+    },
+    repositories::Repositories,
+    settings::{local_time_in, Settings},
+};
+
+pub async fn load_promotions(
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<Promotion>>, StatusCode> {
+    let promotions = repositories.promotions.list().await?;
+
+    Ok(Json(promotions))
+}
+
+pub async fn load_promotion(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Promotion>, StatusCode> {
+    let promotion_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match repositories.promotions.find(promotion_id).await? {
+        Some(promotion) => Ok(Json(promotion)),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+pub async fn add_promotion(
+    Extension(repositories): Extension<Repositories>,
+    Json(mut promotion): Json<Promotion>,
+) -> Result<(StatusCode, Json<Promotion>), StatusCode> {
+    promotion.code = normalize_code(&promotion.code);
+    if let Err(e) = promotion.validate() {
+        eprintln!("Invalid promotion: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    promotion.id = None;
+    promotion.uses = 0;
+    match repositories.promotions.insert(promotion).await? {
+        Some(promotion) => Ok((StatusCode::CREATED, Json(promotion))),
+        None => Err(StatusCode::CONFLICT),
+    }
+}
+
+pub async fn replace_promotion(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Json(mut promotion): Json<Promotion>,
+) -> Result<Json<Promotion>, StatusCode> {
+    let promotion_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    promotion.code = normalize_code(&promotion.code);
+    if let Err(e) = promotion.validate() {
+        eprintln!("Invalid promotion: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(existing) = repositories.promotions.find_by_code(&promotion.code).await? {
+        if existing.id != Some(promotion_id) {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    promotion.id = Some(promotion_id);
+    if !repositories.promotions.replace(&promotion).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    repositories
+        .promotions
+        .find(promotion_id)
+        .await?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn delete_promotion(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<String>, StatusCode> {
+    let promotion_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.promotions.delete(promotion_id).await? {
+        Ok(Json("Promotion deleted successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Takes the promo code off the quoted tickets and records the outcome in `quote.promotion`.
+/// A rejected code leaves the prices untouched. The per-customer limit is only checked when
+/// `customer` is known. Returns the promotion when it applies.
+pub async fn apply_promotion(
+    repositories: &Repositories,
+    settings: &Settings,
+    quote: &mut Quote,
+    code: &str,
+    customer: Option<&str>,
+) -> Result<Option<Promotion>, StatusCode> {
+    let code = normalize_code(code);
+    let Some(promotion) = repositories.promotions.find_by_code(&code).await? else {
+        quote.promotion = Some(PromotionOutcome::rejected(code, PromotionRejection::UnknownCode));
+        return Ok(None);
+    };
+    let promotion_id = promotion.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let session = repositories
+        .sessions
+        .find(quote.session_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let local_start = local_time_in(session_time_zone(repositories, settings, &session).await?, session.start);
+    let mut checked = promotion.check(&session, local_start, &quote.currency, quote.subtotal, Utc::now());
+    if let (Ok(()), Some(customer), Some(max_uses)) = (checked, customer, promotion.max_uses_per_customer) {
+        if repositories.promotions.customer_uses(promotion_id, customer).await? >= max_uses {
+            checked = Err(PromotionRejection::CustomerLimitReached);
+        }
+    }
+    if let Err(reason) = checked {
+        quote.promotion = Some(PromotionOutcome::rejected(code, reason));
+        return Ok(None);
+    }
+
+    let amounts: Vec<i64> = quote.lines.iter().map(|line| line.amount).collect();
+    for (line, discount) in quote.lines.iter_mut().zip(promotion.discounts(&amounts)) {
+        line.discount = discount;
+    }
+    quote.discount = quote.lines.iter().map(|line| line.discount).sum();
+    quote.total = quote.subtotal - quote.discount;
+    quote.promotion = Some(PromotionOutcome::Applied(AppliedPromotion {
+        promotion_id,
+        code,
+        discount: quote.discount,
+    }));
+
+    Ok(Some(promotion))
+}
@@ -0,0 +1,278 @@
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    auth::Caller,
+    broadcast::Broadcaster,
+    controllers::booking_controller::cancel_expired_booking,
+    models::{
+        reservation_model::{
+            SeatAvailability, SeatHoldRequest, SeatHoldResponse, SeatRef, SeatReservation,
+            SeatStatus, SeatStatusChange,
+        },
+        seat_layout_model::SeatLayout,
+        session_model::Session,
+        ws_message_model::Event,
+    },
+    repositories::Repositories,
+    settings::Settings,
+    websockets::Topic,
+};
+
+/// How often expired holds are looked for and released.
+const HOLD_SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn load_session_layout(
+    repositories: &Repositories,
+    session_id: ObjectId,
+) -> Result<(Session, SeatLayout), StatusCode> {
+    let session = repositories
+        .sessions
+        .find(session_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let hall_id = session.hall_id.ok_or(StatusCode::CONFLICT)?;
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::CONFLICT)?;
+    let layout = hall.layout.ok_or(StatusCode::CONFLICT)?;
+
+    Ok((session, layout))
+}
+
+/// Checks that every seat held or booked for the sessions exists in `layout`, failing with
+/// `CONFLICT` otherwise, so no reservation ends up pointing at a seat that is gone.
+pub async fn ensure_seats_in_layout(
+    repositories: &Repositories,
+    session_ids: &[ObjectId],
+    layout: Option<&SeatLayout>,
+) -> Result<(), StatusCode> {
+    let now = DateTime::now();
+    for &session_id in session_ids {
+        let reservations = repositories.reservations.list_for_session(session_id, now).await?;
+        if let Some(reservation) = reservations
+            .iter()
+            .find(|reservation| layout.is_none_or(|layout| layout.seat(&reservation.row, reservation.number).is_none()))
+        {
+            eprintln!(
+                "Seat {}{} taken for session {} is missing from the hall layout",
+                reservation.row, reservation.number, session_id
+            );
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn notify_seat_changes(
+    broadcaster: &Arc<dyn Broadcaster>,
+    session_id: ObjectId,
+    seats: Vec<SeatRef>,
+    status: SeatStatus,
+) {
+    let changes: Vec<SeatStatusChange> = seats
+        .into_iter()
+        .map(|seat| SeatStatusChange {
+            row: seat.row,
+            number: seat.number,
+            status,
+        })
+        .collect();
+
+    broadcaster
+        .publish(
+            vec![Topic::SessionSeats(session_id)],
+            Event::SeatAvailability {
+                session_id: session_id.to_hex(),
+                seats: changes,
+            },
+            None,
+        )
+        .await;
+}
+
+pub async fn get_session_seats(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<SeatAvailability>>, StatusCode> {
+    let session_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let (_, layout) = load_session_layout(&repositories, session_id).await?;
+    let reservations = repositories
+        .reservations
+        .list_for_session(session_id, DateTime::now())
+        .await?;
+    let taken: HashMap<SeatRef, SeatStatus> = reservations
+        .iter()
+        .map(|reservation| (reservation.seat(), reservation.status()))
+        .collect();
+
+    let seats = layout
+        .seats()
+        .map(|seat| {
+            let key = SeatRef {
+                row: seat.row.clone(),
+                number: seat.number,
+            };
+            SeatAvailability {
+                status: taken.get(&key).copied().unwrap_or(SeatStatus::Available),
+                row: seat.row,
+                number: seat.number,
+                category: seat.category,
+            }
+        })
+        .collect();
+
+    Ok(Json(seats))
+}
+
+/// Stores a hold and cancels the pending bookings of the expired holds it took the seats
+/// from, as the sweeper would have. Returns `false` when a seat is taken.
+pub async fn place_hold(
+    repositories: &Repositories,
+    reservations: Vec<SeatReservation>,
+    now: DateTime,
+) -> Result<bool, StatusCode> {
+    let insertion = repositories.reservations.insert_hold(reservations, now).await?;
+
+    let holds: HashSet<ObjectId> = insertion.displaced.iter().map(|reservation| reservation.hold_id).collect();
+    for hold_id in holds {
+        if let Err(status) = cancel_expired_booking(repositories, hold_id).await {
+            eprintln!("Failed to cancel the booking of expired hold {}: {}", hold_id, status);
+        }
+    }
+
+    Ok(insertion.placed)
+}
+
+pub async fn hold_seats(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(broadcaster): Extension<Arc<dyn Broadcaster>>,
+    Json(request): Json<SeatHoldRequest>,
+) -> Result<(StatusCode, Json<SeatHoldResponse>), StatusCode> {
+    let session_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let (session, layout) = load_session_layout(&repositories, session_id).await?;
+    let now = DateTime::now();
+    if session.end <= now {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let unique_seats: HashSet<&SeatRef> = request.seats.iter().collect();
+    if request.seats.is_empty() || unique_seats.len() != request.seats.len() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(seat) = request.seats.iter().find(|seat| layout.seat(&seat.row, seat.number).is_none()) {
+        eprintln!("Seat {}{} does not exist in the hall layout", seat.row, seat.number);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let hold_id = ObjectId::new();
+    let expires_at = DateTime::from_millis(now.timestamp_millis() + settings.seat_hold_ttl_seconds * 1000);
+    let reservations = request
+        .seats
+        .iter()
+        .map(|seat| SeatReservation {
+            id: None,
+            session_id,
+            hold_id,
+            row: seat.row.clone(),
+            number: seat.number,
+            expires_at: Some(expires_at),
+            user_id: caller.user_id(),
+            api_key_id: caller.api_key_id(),
+        })
+        .collect();
+
+    if !place_hold(&repositories, reservations, now).await? {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    notify_seat_changes(&broadcaster, session_id, request.seats.clone(), SeatStatus::Held).await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SeatHoldResponse {
+            hold_id,
+            session_id,
+            seats: request.seats,
+            expires_at,
+        }),
+    ))
+}
+
+/// Releases a hold of the caller. Holds of anyone else are reported as not found.
+pub async fn release_hold(
+    Path(id_str): Path<String>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+    Extension(broadcaster): Extension<Arc<dyn Broadcaster>>,
+) -> Result<Json<String>, StatusCode> {
+    let hold_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let reservations = repositories.reservations.list_for_hold(hold_id).await?;
+    if !reservations.iter().all(|reservation| caller.owns_hold(reservation)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if !repositories.reservations.release_hold(hold_id).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    if let Some(first) = reservations.first() {
+        let seats = reservations.iter().map(SeatReservation::seat).collect();
+        notify_seat_changes(&broadcaster, first.session_id, seats, SeatStatus::Available).await;
+    }
+
+    Ok(Json("Hold released successfully".to_string()))
+}
+
+/// Periodically frees seats whose hold ran out, cancels the pending bookings made from those
+/// holds and tells watching clients about it.
+pub fn spawn_hold_sweeper(repositories: Repositories, broadcaster: Arc<dyn Broadcaster>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HOLD_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let expired = match repositories.reservations.purge_expired(DateTime::now()).await {
+                Ok(expired) => expired,
+                Err(e) => {
+                    eprintln!("Failed to release expired holds: {}", e);
+                    continue;
+                }
+            };
+
+            let mut freed: HashMap<ObjectId, Vec<SeatRef>> = HashMap::new();
+            let mut holds = HashSet::new();
+            for reservation in expired {
+                freed.entry(reservation.session_id).or_default().push(reservation.seat());
+                holds.insert(reservation.hold_id);
+            }
+            for hold_id in holds {
+                if let Err(status) = cancel_expired_booking(&repositories, hold_id).await {
+                    eprintln!("Failed to cancel the booking of expired hold {}: {}", hold_id, status);
+                }
+            }
+            for (session_id, seats) in freed {
+                notify_seat_changes(&broadcaster, session_id, seats, SeatStatus::Available).await;
+            }
+        }
+    });
+}
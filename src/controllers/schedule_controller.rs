@@ -0,0 +1,305 @@
+use std::cmp::Reverse;
+
+use axum::{extract::Extension, http::StatusCode, response::Json};
+use chrono::{Duration, NaiveTime};
+use chrono_tz::Tz;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    controllers::{
+        hall_controller::hall_time_zone,
+        hall_schedule_controller::check_hall_schedule,
+        session_controller::{ensure_price_list_exists, is_hall_available, session_end},
+    },
+    events::{DomainEvent, EventBus},
+    models::{
+        hall_model::Hall,
+        hall_schedule_model::{HallClosure, OpeningHours},
+        movie_model::Movie,
+        schedule_model::{PlanMode, PlanShortfall, PlannedSession, SchedulePlan, SchedulePlanRequest},
+        session_model::{Session, SessionResponse},
+    },
+    repositories::Repositories,
+    settings::{instant_in, local_time_in, Settings},
+};
+
+struct Demand {
+    movie_id: ObjectId,
+    movie: Movie,
+    target: Option<u32>,
+    weight: f64,
+    scheduled: u32,
+}
+
+/// Picks the movie that should get the next slot, among the ones that still want
+/// screenings and fit. Weights are shared out like seats in a highest averages vote,
+/// so screenings stay proportional to them as the week fills up.
+fn pick_movie(demand: &[Demand], fits: impl Fn(&Movie) -> bool) -> Option<usize> {
+    demand
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.target.is_none_or(|target| entry.scheduled < target))
+        .filter(|(_, entry)| fits(&entry.movie))
+        .max_by(|(a_index, a), (b_index, b)| {
+            let a_score = a.weight / (a.scheduled + 1) as f64;
+            let b_score = b.weight / (b.scheduled + 1) as f64;
+            // Ties go to the movie listed first.
+            a_score.total_cmp(&b_score).then(b_index.cmp(a_index))
+        })
+        .map(|(index, _)| index)
+}
+
+/// Rounds a non-negative span up to a whole number of slots.
+fn round_up(millis: i64, slot_millis: i64) -> i64 {
+    (millis + slot_millis - 1) / slot_millis * slot_millis
+}
+
+fn overlaps_planned(
+    planned: &[PlannedSession],
+    hall_id: ObjectId,
+    start: DateTime,
+    end: DateTime,
+    cleaning_millis: i64,
+) -> bool {
+    planned.iter().any(|session| {
+        session.hall_id == hall_id
+            && session.start.timestamp_millis() < end.timestamp_millis() + cleaning_millis
+            && session.end.timestamp_millis() + cleaning_millis > start.timestamp_millis()
+    })
+}
+
+struct HallPlan {
+    hall: Hall,
+    /// The week and its opening hours are read on this wall clock.
+    time_zone: Tz,
+    /// Windows the planner fills.
+    windows: Vec<OpeningHours>,
+    /// Stored opening hours, which every session has to fit in as well.
+    opening_hours: Vec<OpeningHours>,
+    closures: Vec<HallClosure>,
+    /// Sessions already stored for the hall around the planned week.
+    sessions: Vec<Session>,
+}
+
+impl HallPlan {
+    fn is_open(&self, start: DateTime, end: DateTime) -> bool {
+        let (local_start, local_end) = (local_time_in(self.time_zone, start), local_time_in(self.time_zone, end));
+        let fits_hours = self.opening_hours.is_empty()
+            || self.opening_hours.iter().any(|hours| hours.contains(local_start, local_end));
+        fits_hours
+            && !self
+                .closures
+                .iter()
+                .any(|closure| closure.start < end && closure.end > start)
+    }
+
+    /// Whether `[start, end)` keeps clear of the stored sessions, cleaning time included.
+    fn is_free(&self, start: DateTime, end: DateTime, cleaning_millis: i64) -> bool {
+        !self.sessions.iter().any(|session| {
+            session.start.timestamp_millis() < end.timestamp_millis() + cleaning_millis
+                && session.end.timestamp_millis() + cleaning_millis > start.timestamp_millis()
+        })
+    }
+}
+
+/// Proposes a week of non-overlapping sessions for the given movies and halls, and stores
+/// them in commit mode. Each day the halls are filled from the largest one down, slot by
+/// slot from opening time, around the sessions and closures that already exist. Halls are
+/// planned within their stored opening hours unless the request narrows them down.
+pub async fn plan_schedule(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(events): Extension<EventBus>,
+    Json(request): Json<SchedulePlanRequest>,
+) -> Result<Json<SchedulePlan>, StatusCode> {
+    if let Err(e) = request.validate() {
+        eprintln!("Invalid schedule plan: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    ensure_price_list_exists(&repositories, request.price_list_id).await?;
+
+    let mut demand = Vec::new();
+    for planned in &request.movies {
+        let movie = repositories
+            .movies
+            .find(planned.movie_id)
+            .await?
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        if movie.duration <= 0 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        demand.push(Demand {
+            movie_id: planned.movie_id,
+            movie,
+            target: planned.screenings,
+            weight: planned.weight,
+            scheduled: 0,
+        });
+    }
+
+    let mut halls = Vec::new();
+    for planned in &request.halls {
+        let hall = repositories
+            .halls
+            .find(planned.hall_id)
+            .await?
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let time_zone = hall_time_zone(&repositories, &settings, &hall).await?;
+        let opening_hours: Vec<OpeningHours> = repositories
+            .hall_schedules
+            .list_opening_hours(planned.hall_id)
+            .await?
+            .into_iter()
+            .map(|entry| entry.hours)
+            .collect();
+        let windows = if planned.opening_hours.is_empty() {
+            opening_hours.clone()
+        } else {
+            planned.opening_hours.clone()
+        };
+        if windows.is_empty() {
+            eprintln!("Hall {} has no opening hours to plan in", planned.hall_id);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        // Closures and sessions are loaded with a day of margin for windows running past midnight.
+        let week_from = instant_in(time_zone, (request.week_start - Duration::days(1)).and_time(NaiveTime::MIN))
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let week_to = instant_in(time_zone, (request.week_start + Duration::days(8)).and_time(NaiveTime::MIN))
+            .ok_or(StatusCode::BAD_REQUEST)?;
+        let closures = repositories
+            .hall_schedules
+            .list_closures(planned.hall_id, week_from, week_to)
+            .await?;
+        let sessions = repositories
+            .sessions
+            .list_for_hall(planned.hall_id, week_from, week_to)
+            .await?;
+        halls.push(HallPlan {
+            hall,
+            time_zone,
+            windows,
+            opening_hours,
+            closures,
+            sessions,
+        });
+    }
+    halls.sort_by_key(|hall| Reverse(hall.hall.capacity));
+
+    let now = DateTime::now();
+    let slot_millis = request.slot_minutes as i64 * 60 * 1000;
+    let mut sessions: Vec<PlannedSession> = Vec::new();
+
+    for day in 0..7 {
+        let date = request.week_start + Duration::days(day);
+        for plan_hall in &halls {
+            let hall = &plan_hall.hall;
+            let Some(hall_id) = hall.id else {
+                continue;
+            };
+            let cleaning_millis = settings.cleaning_minutes(hall) * 60 * 1000;
+
+            for hours in &plan_hall.windows {
+                let Some((opens, closes)) = hours.window(date) else {
+                    continue;
+                };
+                let (Some(opens), Some(closes)) =
+                    (instant_in(plan_hall.time_zone, opens), instant_in(plan_hall.time_zone, closes))
+                else {
+                    continue;
+                };
+
+                // Start on the first slot boundary that is not in the past.
+                let late_by = (now.timestamp_millis() - opens.timestamp_millis()).max(0);
+                let mut cursor = opens.timestamp_millis() + round_up(late_by, slot_millis);
+
+                while cursor < closes.timestamp_millis() {
+                    let start = DateTime::from_millis(cursor);
+                    let Some(index) = pick_movie(&demand, |movie| session_end(&settings, hall, movie, start) <= closes)
+                    else {
+                        break;
+                    };
+                    let end = session_end(&settings, hall, &demand[index].movie, start);
+
+                    if overlaps_planned(&sessions, hall_id, start, end, cleaning_millis)
+                        || !plan_hall.is_open(start, end)
+                        || !plan_hall.is_free(start, end, cleaning_millis)
+                    {
+                        cursor += slot_millis;
+                        continue;
+                    }
+
+                    let entry = &mut demand[index];
+                    entry.scheduled += 1;
+                    sessions.push(PlannedSession {
+                        movie_id: entry.movie_id,
+                        hall_id,
+                        title: entry.movie.title.clone(),
+                        start,
+                        end,
+                    });
+
+                    let free_at = end.timestamp_millis() + cleaning_millis;
+                    cursor += round_up(free_at - cursor, slot_millis);
+                }
+            }
+        }
+    }
+    sessions.sort_by_key(|session| session.start);
+
+    let shortfalls = demand
+        .iter()
+        .filter_map(|entry| match entry.target {
+            Some(requested) if entry.scheduled < requested => Some(PlanShortfall {
+                movie_id: entry.movie_id,
+                requested,
+                scheduled: entry.scheduled,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    let mut created: Vec<SessionResponse> = Vec::new();
+    let mut skipped = Vec::new();
+    if request.mode == PlanMode::Commit {
+        let mut accepted = Vec::new();
+        for planned in &sessions {
+            let Some(plan_hall) = halls.iter().find(|plan_hall| plan_hall.hall.id == Some(planned.hall_id)) else {
+                continue;
+            };
+            // Someone may have scheduled into or closed the hall since the plan was made.
+            let closed = check_hall_schedule(&repositories, &settings, &plan_hall.hall, planned.start, planned.end)
+                .await?
+                .is_some();
+            if closed || !is_hall_available(&repositories, &settings, &plan_hall.hall, planned.start, planned.end, None).await? {
+                skipped.push(planned.clone());
+                continue;
+            }
+
+            accepted.push(Session {
+                id: None,
+                title: Some(planned.title.clone()),
+                movie_id: Some(planned.movie_id),
+                hall_id: Some(planned.hall_id),
+                price_list_id: request.price_list_id,
+                series_id: None,
+                start: planned.start,
+                end: planned.end,
+            });
+        }
+
+        for session in repositories.sessions.insert_many(accepted).await? {
+            if let Some(session_id) = session.id {
+                events.publish(DomainEvent::SessionAdded(session_id));
+            }
+            created.push(session.into());
+        }
+    }
+
+    Ok(Json(SchedulePlan {
+        mode: request.mode,
+        sessions,
+        shortfalls,
+        created,
+        skipped,
+    }))
+}
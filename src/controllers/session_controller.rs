@@ -1,352 +1,382 @@
 use axum::{
-    extract::{Extension, Path}, http::StatusCode, response::Json
+    extract::{Extension, Path, Query}, http::StatusCode, response::Json
+};
+use chrono::{DateTime as ChronoDateTime, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use std::{collections::HashMap, fmt};
+use crate::{
+    controllers::{
+        booking_controller::{cancel_session_bookings, ensure_session_cancellable},
+        hall_controller::hall_time_zone,
+        hall_schedule_controller::check_hall_schedule,
+        reservation_controller::ensure_seats_in_layout,
+        venue_controller::venue_filter,
+    },
+    models::{
+        hall_model::Hall,
+        hall_schedule_model::HallUnavailability,
+        local_time_model::{resolve_local_time, LocalTimeError, LocalTimes},
+        movie_model::Movie,
+        session_model::{Session, SessionDetail, SessionListQuery, SessionResponse, SessionUpdate},
+        venue_model::Venue,
+    },
+    repositories::{Repositories, RepositoryError, RepositoryResult},
+    settings::{local_time_in, Settings},
 };
-use mongodb::{bson::{self, doc, from_document, oid::ObjectId, DateTime}, Client};
-use chrono::{ DateTime as ChronoDateTime, Utc };
-use std::sync::Arc;
-use crate::models::session_model::{Session, SessionDetail, SessionResponse, SessionUpdate};
-use futures::TryStreamExt;
-
-async fn is_hall_available(
-    client: &Arc<Client>,
-    hall_id: &ObjectId,
-    start: &Option<ChronoDateTime<Utc>>,
-    end: &Option<ChronoDateTime<Utc>>,
-    exclude_session_id: Option<ObjectId>,
-) -> bool {
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<SessionUpdate>("sessions");
 
-    let start_bson = start.map(|dt| bson::DateTime::from_chrono(dt)).unwrap();
-    let end_bson = end.map(|dt| bson::DateTime::from_chrono(dt)).unwrap();
+/// Why a session could not be created or updated.
+#[derive(Debug)]
+pub enum SessionError {
+    Status(StatusCode),
+    /// The hall is not open or is closed at the requested time.
+    HallUnavailable(HallUnavailability),
+    /// The session was asked to move to a hall at another venue.
+    CrossVenueMove,
+    /// A wall-clock time falls into a daylight saving change.
+    InvalidLocalTime(LocalTimeError),
+}
 
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Status(status) => write!(f, "{}", status),
+            SessionError::HallUnavailable(unavailability) => write!(f, "{}", unavailability.message()),
+            SessionError::CrossVenueMove => write!(f, "Sessions cannot be moved to a hall at another venue"),
+            SessionError::InvalidLocalTime(local_time_error) => write!(f, "{}", local_time_error.message()),
+        }
+    }
+}
 
-    let mut query = doc! {
-        "hall_id": hall_id,
-        "$and": [
-            { "start": { "$lt": end_bson } },
-            { "end": { "$gt": start_bson } },
-        ],
+impl From<StatusCode> for SessionError {
+    fn from(status: StatusCode) -> Self {
+        SessionError::Status(status)
+    }
+}
+
+impl From<RepositoryError> for SessionError {
+    fn from(err: RepositoryError) -> Self {
+        SessionError::Status(err.into())
+    }
+}
+
+/// Checks that no other session in the hall overlaps `[start, end)`, counting the cleaning
+/// time the hall needs after every session.
+pub async fn is_hall_available(
+    repositories: &Repositories,
+    settings: &Settings,
+    hall: &Hall,
+    start: DateTime,
+    end: DateTime,
+    exclude_session_id: Option<ObjectId>,
+) -> RepositoryResult<bool> {
+    let Some(hall_id) = hall.id else {
+        return Ok(false);
     };
 
-    if let Some(exclude_id) = exclude_session_id {
-        query.insert("_id", doc! { "$ne": exclude_id });
+    // Widening the new session by the cleaning time on both sides covers the cleaning
+    // after it as well as the cleaning after the session right before it.
+    let cleaning_millis = settings.cleaning_minutes(hall) * 60 * 1000;
+    let count = repositories
+        .sessions
+        .count_overlapping(
+            hall_id,
+            DateTime::from_millis(start.timestamp_millis() - cleaning_millis),
+            DateTime::from_millis(end.timestamp_millis() + cleaning_millis),
+            exclude_session_id,
+        )
+        .await?;
+
+    Ok(count == 0)
+}
+
+/// When a screening of `movie` starting at `start` ends: the hall's pre-show ads and
+/// trailers followed by the film itself.
+pub fn session_end(settings: &Settings, hall: &Hall, movie: &Movie, start: DateTime) -> DateTime {
+    let minutes = settings.pre_show_minutes(hall) + movie.duration as i64;
+    DateTime::from_millis(start.timestamp_millis() + minutes * 60 * 1000)
+}
+
+/// Fills in the local times of the sessions, loading the venues once for all of them.
+/// Returns the time zone of each session, in the same order.
+async fn localize_sessions(
+    repositories: &Repositories,
+    settings: &Settings,
+    sessions: &mut [SessionDetail],
+) -> Result<Vec<Tz>, StatusCode> {
+    let venues: HashMap<ObjectId, Venue> = repositories
+        .venues
+        .list()
+        .await?
+        .into_iter()
+        .filter_map(|venue| venue.id.map(|id| (id, venue)))
+        .collect();
+
+    let mut time_zones = Vec::with_capacity(sessions.len());
+    for session in sessions.iter_mut() {
+        let time_zone = match &session.hall {
+            Some(hall) => settings.hall_time_zone(hall, hall.venue_id.and_then(|id| venues.get(&id))),
+            None => settings.time_zone,
+        };
+        session.local = Some(LocalTimes::new(time_zone, session.start, session.end));
+        time_zones.push(time_zone);
+    }
+
+    Ok(time_zones)
+}
+
+/// Instant asked for either in UTC or on the hall's wall clock. Sending both is an error.
+fn requested_instant(
+    utc: Option<ChronoDateTime<Utc>>,
+    local: Option<NaiveDateTime>,
+    time_zone: Tz,
+    session_data: &SessionUpdate,
+) -> Result<Option<DateTime>, SessionError> {
+    match (utc, local) {
+        (Some(_), Some(_)) => Err(StatusCode::BAD_REQUEST.into()),
+        (Some(utc), None) => Ok(Some(DateTime::from_chrono(utc))),
+        (None, Some(local)) => resolve_local_time(
+            time_zone,
+            local,
+            session_data.ambiguous_time,
+            session_data.nonexistent_time,
+        )
+        .map(Some)
+        .map_err(SessionError::InvalidLocalTime),
+        (None, None) => Ok(None),
     }
+}
 
-    let count = sessions_collection.count_documents(query, None).await.unwrap_or(0);
+pub async fn ensure_price_list_exists(
+    repositories: &Repositories,
+    price_list_id: Option<ObjectId>,
+) -> Result<(), StatusCode> {
+    if let Some(price_list_id) = price_list_id {
+        if repositories.price_lists.find(price_list_id).await?.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
 
-    count == 0
+    Ok(())
 }
 
 
-pub async fn load_sessions_with_details(Extension(client): Extension<Arc<Client>>) -> Result<Json<Vec<SessionDetail>>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
-
-    let pipeline = vec![
-        doc! {
-            "$lookup": {
-                "from": "movies",
-                "localField": "movie_id",
-                "foreignField": "_id",
-                "as": "movie",
-            },
-        },
-        doc! {
-            "$lookup": {
-                "from": "halls",
-                "localField": "hall_id",
-                "foreignField": "_id",
-                "as": "hall"
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$movie",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$hall",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-        doc! {
-            "$project": {
-                "movie.description": 0,
-                "movie.poster": 0,
-            }
-        },
-    ];
+/// Lists sessions, optionally only those at one venue and on one local day. `date=today`
+/// means today on the wall clock of each session's hall.
+pub async fn load_sessions_with_details(
+    Query(query): Query<SessionListQuery>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+) -> Result<Json<Vec<SessionDetail>>, StatusCode> {
+    let mut sessions = repositories.sessions.list_with_details().await?;
+    if let Some(venue_id) = venue_filter(&repositories, &query.venue).await? {
+        sessions.retain(|session| session.hall.as_ref().is_some_and(|hall| hall.venue_id == Some(venue_id)));
+    }
 
-    let mut cursor = match sessions_collection.aggregate(pipeline, None).await {
-        Ok(cursor) => cursor,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-    
-
-    let mut result: Vec<SessionDetail> = Vec::new();
-    while let Some(doc) = cursor.try_next().await.expect("Failed to iterate") {
-        let session_detail: SessionDetail = match from_document(doc) {
-            Ok(session) => session,
-            Err(e) => {
-                eprintln!("Aggregation error: {:?}", e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            },
+    let time_zones = localize_sessions(&repositories, &settings, &mut sessions).await?;
+
+    if let Some(date) = query.date.as_deref() {
+        let date = match date {
+            "today" => None,
+            date => Some(date.parse::<NaiveDate>().map_err(|_| StatusCode::BAD_REQUEST)?),
         };
-        result.push(session_detail);
+        let now = DateTime::now();
+        sessions = sessions
+            .into_iter()
+            .zip(time_zones)
+            .filter(|(session, time_zone)| {
+                let day = date.unwrap_or_else(|| local_time_in(*time_zone, now).date());
+                local_time_in(*time_zone, session.start).date() == day
+            })
+            .map(|(session, _)| session)
+            .collect();
     }
 
-    Ok(Json(result))
+    Ok(Json(sessions))
 }
 
 pub async fn fetch_session_by_id(
     Path(id_str): Path<String>,
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
 ) -> Result<Json<Option<SessionDetail>>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
-
     let id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
 
-    let pipeline = vec![
-        doc! {
-            "$match": {
-                "_id": id,
-            }
-        },
-        doc! {
-            "$lookup": {
-                "from": "movies",
-                "localField": "movie_id",
-                "foreignField": "_id",
-                "as": "movie",
-            },
-        },
-        doc! {
-            "$lookup": {
-                "from": "halls",
-                "localField": "hall_id",
-                "foreignField": "_id",
-                "as": "hall"
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$movie",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$hall",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-    ];
-
-    let mut cursor = match sessions_collection.aggregate(pipeline, None).await {
-        Ok(cursor) => cursor,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-
-    match cursor.try_next().await {
-        Ok(Some(doc)) => {
-            let session: Result<SessionDetail, _> = from_document(doc);
-            match session {
-                Ok(session) => Ok(Json(Some(session))),
-                Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-            }
-        },
-        Ok(None) => Ok(Json(None)), 
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    let mut session = repositories.sessions.find_with_details(id).await?;
+    if let Some(session) = session.as_mut() {
+        localize_sessions(&repositories, &settings, std::slice::from_mut(session)).await?;
     }
+
+    Ok(Json(session))
 }
 
-pub async fn get_sessions(Extension(client): Extension<Arc<Client>>) -> Result<Vec<SessionDetail>, StatusCode> {
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
-
-    let pipeline = vec![
-        doc! {
-            "$lookup": {
-                "from": "movies",
-                "localField": "movie_id",
-                "foreignField": "_id",
-                "as": "movie",
-            },
-        },
-        doc! {
-            "$lookup": {
-                "from": "halls",
-                "localField": "hall_id",
-                "foreignField": "_id",
-                "as": "hall"
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$movie",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-        doc! {
-            "$unwind": {
-                "path": "$hall",
-                "preserveNullAndEmptyArrays": true
-            }
-        },
-        doc! {
-            "$project": {
-                "movie.description": 0,
-                "movie.poster": 0,
-            }
-        },
-    ];
+pub async fn get_sessions(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+) -> Result<Vec<SessionDetail>, StatusCode> {
+    let mut sessions = repositories.sessions.list_with_details().await?;
+    localize_sessions(&repositories, &settings, &mut sessions).await?;
 
-    let mut cursor = match sessions_collection.aggregate(pipeline, None).await {
-        Ok(cursor) => cursor,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-    };
-    
-
-    let mut result: Vec<SessionDetail> = Vec::new();
-    while let Some(doc) = cursor.try_next().await.expect("Failed to iterate") {
-        let session_detail: SessionDetail = match from_document(doc) {
-            Ok(session) => session,
-            Err(e) => {
-                eprintln!("Aggregation error: {:?}", e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            },
-        };
-        result.push(session_detail);
-    }
-
-    Ok(result)
+    Ok(sessions)
 }
 
 
 
+/// Creates a session. When it has a movie, its end is derived from the movie duration
+/// and the hall's pre-show time and any `end` sent by the client is ignored. The session
+/// has to fit in the hall's opening hours and stay clear of its closures. Times can be
+/// given in UTC or on the hall's wall clock.
 pub async fn add_ws_session(
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
     Json(session_data): Json<SessionUpdate>,
-) -> Result<SessionResponse, StatusCode> {
-    if session_data.end <= session_data.start {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-
-    if let Some(hall_id) = session_data.hall_id {
-        if !is_hall_available(&client, &hall_id, &session_data.start, &session_data.end, None).await {
-            return Err(StatusCode::CONFLICT);
+) -> Result<SessionResponse, SessionError> {
+    let hall_id = session_data.hall_id.ok_or(StatusCode::BAD_REQUEST)?;
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::BAD_REQUEST)?;
+    let time_zone = hall_time_zone(&repositories, &settings, &hall).await?;
+
+    let start = requested_instant(session_data.start, session_data.local_start, time_zone, &session_data)?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let end = match session_data.movie_id {
+        Some(movie_id) => {
+            let movie = repositories.movies.find(movie_id).await?.ok_or(StatusCode::BAD_REQUEST)?;
+            session_end(&settings, &hall, &movie, start)
         }
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+        None => requested_instant(session_data.end, session_data.local_end, time_zone, &session_data)?
+            .ok_or(StatusCode::BAD_REQUEST)?,
+    };
+    if end <= start {
+        return Err(StatusCode::BAD_REQUEST.into());
     }
 
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
+    if let Some(unavailability) = check_hall_schedule(&repositories, &settings, &hall, start, end).await? {
+        return Err(SessionError::HallUnavailable(unavailability));
+    }
+    if !is_hall_available(&repositories, &settings, &hall, start, end, None).await? {
+        return Err(StatusCode::CONFLICT.into());
+    }
+    ensure_price_list_exists(&repositories, session_data.price_list_id).await?;
 
     let session_to_insert = Session {
         id: None,
         title: session_data.title,
         movie_id: session_data.movie_id,
         hall_id: session_data.hall_id,
-        start: DateTime::from_millis(session_data.start.unwrap().timestamp_millis()),
-        end: DateTime::from_millis(session_data.end.unwrap().timestamp_millis()),
+        price_list_id: session_data.price_list_id,
+        series_id: None,
+        start,
+        end,
     };
 
-    match sessions_collection.insert_one(&session_to_insert, None).await {
-        Ok(insert_result) => {
-            let created_session = SessionResponse {
-                id: Some(insert_result.inserted_id.as_object_id().unwrap()),
-                title: session_to_insert.title,
-                movie_id: session_to_insert.movie_id,
-                hall_id: session_to_insert.hall_id,
-                start: session_to_insert.start,
-                end: session_to_insert.end              
-            };
-            Ok(created_session)
-        },
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+    let created_session = repositories.sessions.insert(session_to_insert).await?;
+    let mut response: SessionResponse = created_session.into();
+    response.local = Some(LocalTimes::new(time_zone, response.start, response.end));
+
+    Ok(response)
 }
 
 pub async fn update_ws_session(
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
     axum::extract::Path(id_str): axum::extract::Path<String>,
     Json(session_data): Json<SessionUpdate>,
-) -> Result<SessionResponse, StatusCode> {
+) -> Result<SessionResponse, SessionError> {
     let session_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(_) => return Err(StatusCode::BAD_REQUEST.into()),
     };
 
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
-
-    let current_session = sessions_collection.find_one(doc! {"_id": session_id}, None).await.unwrap();
-    let hall_id = current_session.as_ref().and_then(|doc| doc.hall_id.clone()).or(session_data.hall_id.clone()).ok_or(StatusCode::BAD_REQUEST)?;
-
-    if let (Some(start), Some(end)) = (session_data.start, session_data.end) {
-        if end <= start {
-            return Err(StatusCode::BAD_REQUEST);
+    let mut session = repositories
+        .sessions
+        .find(session_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let hall_id = session_data.hall_id.or(session.hall_id).ok_or(StatusCode::BAD_REQUEST)?;
+    let hall = repositories.halls.find(hall_id).await?.ok_or(StatusCode::BAD_REQUEST)?;
+    if let Some(current_hall_id) = session.hall_id.filter(|current| *current != hall_id) {
+        if let Some(current_hall) = repositories.halls.find(current_hall_id).await? {
+            if current_hall.venue_id != hall.venue_id {
+                return Err(SessionError::CrossVenueMove);
+            }
         }
+        // Bookings move along with the session, so their seats have to exist in the new hall.
+        ensure_seats_in_layout(&repositories, &[session_id], hall.layout.as_ref()).await?;
+    }
 
-        if !is_hall_available(&client, &hall_id, &Some(start), &Some(end), Some(session_id)).await {
-            return Err(StatusCode::CONFLICT);
-        }
+    if session_data.movie_id.is_some() {
+        session.movie_id = session_data.movie_id;
+    }
+    let movie = match session.movie_id {
+        Some(movie_id) => repositories.movies.find(movie_id).await?,
+        None => None,
+    };
+    if session_data.movie_id.is_some() && movie.is_none() {
+        return Err(StatusCode::BAD_REQUEST.into());
     }
 
-    let update_doc = doc! {
-        "$set": {
-            "title": session_data.title,
-            "movie_id": session_data.movie_id,
-            "hall_id": hall_id,
-            "start": session_data.start,
-            "end": session_data.end,
+    let time_zone = hall_time_zone(&repositories, &settings, &hall).await?;
+    if let Some(start) = requested_instant(session_data.start, session_data.local_start, time_zone, &session_data)? {
+        session.start = start;
+    }
+    match &movie {
+        Some(movie) => session.end = session_end(&settings, &hall, movie, session.start),
+        None => {
+            if let Some(end) = requested_instant(session_data.end, session_data.local_end, time_zone, &session_data)? {
+                session.end = end;
+            }
         }
-    };
+    }
+    if session.end <= session.start {
+        return Err(StatusCode::BAD_REQUEST.into());
+    }
 
-    sessions_collection.update_one(doc! {"_id": session_id}, update_doc, None).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(unavailability) = check_hall_schedule(&repositories, &settings, &hall, session.start, session.end).await? {
+        return Err(SessionError::HallUnavailable(unavailability));
+    }
+    if !is_hall_available(&repositories, &settings, &hall, session.start, session.end, Some(session_id)).await? {
+        return Err(StatusCode::CONFLICT.into());
+    }
 
-    let updated_session = sessions_collection.find_one(doc! {"_id": session_id}, None).await.unwrap().expect("Session not found after update");
+    session.hall_id = Some(hall_id);
+    if session_data.title.is_some() {
+        session.title = session_data.title;
+    }
+    if session_data.price_list_id.is_some() {
+        ensure_price_list_exists(&repositories, session_data.price_list_id).await?;
+        session.price_list_id = session_data.price_list_id;
+    }
 
-    let response = SessionResponse {
-        id: Some(updated_session.id.unwrap()),
-        title: updated_session.title,
-        movie_id: updated_session.movie_id,
-        hall_id: updated_session.hall_id,
-        start: updated_session.start,
-        end: updated_session.end
-    };
+    if !repositories.sessions.replace(&session).await? {
+        return Err(StatusCode::NOT_FOUND.into());
+    }
+
+    let mut response: SessionResponse = session.into();
+    response.local = Some(LocalTimes::new(time_zone, response.start, response.end));
 
     Ok(response)
 }
 
+/// Deletes the session and returns it, so the change can be published to its topics.
 pub async fn delete_ws_session(
-    Extension(client): Extension<Arc<Client>>,
+    Extension(repositories): Extension<Repositories>,
     axum::extract::Path(id_str): axum::extract::Path<String>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<Session, StatusCode> {
     let session_id = match ObjectId::parse_str(&id_str) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
 
-    let db = client.database("cinema-axum");
-    let sessions_collection = db.collection::<Session>("sessions");
+    let session = repositories.sessions.find(session_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    ensure_session_cancellable(&repositories, session_id).await?;
 
-    match sessions_collection.delete_one(doc! {"_id": session_id}, None).await {
-        Ok(delete_result) => {
-            if delete_result.deleted_count == 1 {
-                Ok(StatusCode::OK)
-            } else {
-                Err(StatusCode::NOT_FOUND)
-            }
-        },
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    if !repositories.sessions.delete(session_id).await? {
+        return Err(StatusCode::NOT_FOUND);
     }
+
+    cancel_session_bookings(&repositories, session_id).await?;
+
+    Ok(session)
 }
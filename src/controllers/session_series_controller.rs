@@ -0,0 +1,405 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::{
+    controllers::{
+        booking_controller::{cancel_session_bookings, ensure_session_cancellable},
+        hall_controller::hall_time_zone,
+        hall_schedule_controller::check_hall_schedule,
+        reservation_controller::ensure_seats_in_layout,
+        session_controller::{ensure_price_list_exists, is_hall_available, session_end},
+    },
+    events::{DomainEvent, EventBus},
+    models::{
+        hall_model::Hall,
+        hall_schedule_model::HallUnavailability,
+        session_model::{Session, SessionResponse},
+        session_series_model::{
+            OccurrenceConflict, OccurrenceConflictReason, SessionSeries, SessionSeriesChanges,
+            SessionSeriesDetail,
+        },
+    },
+    repositories::Repositories,
+    settings::{instant_in, local_time_in, Settings},
+};
+
+/// Checks the rule and that the movie, hall and price list of the series exist.
+async fn validate_series(repositories: &Repositories, series: &SessionSeries) -> Result<(), StatusCode> {
+    if let Err(e) = series.rule.validate(series.start_date) {
+        eprintln!("Invalid recurrence rule: {}", e);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let movie = repositories
+        .movies
+        .find(series.movie_id)
+        .await?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    if movie.duration <= 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if repositories.halls.find(series.hall_id).await?.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    ensure_price_list_exists(repositories, series.price_list_id).await?;
+
+    Ok(())
+}
+
+/// Removes a future occurrence the same way a single session is deleted. Returns `false`
+/// when tickets for it were already used, in which case it is kept.
+async fn cancel_occurrence(
+    repositories: &Repositories,
+    events: &EventBus,
+    session: &Session,
+) -> Result<bool, StatusCode> {
+    let session_id = session.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    match ensure_session_cancellable(repositories, session_id).await {
+        Ok(()) => {}
+        Err(StatusCode::CONFLICT) => return Ok(false),
+        Err(e) => return Err(e),
+    }
+
+    if repositories.sessions.delete(session_id).await? {
+        cancel_session_bookings(repositories, session_id).await?;
+        events.publish(DomainEvent::SessionDeleted {
+            id: session_id,
+            previous: Some(session.clone()),
+        });
+    }
+
+    Ok(true)
+}
+
+/// Why an occurrence cannot take the hall at `[start, end)`, if it cannot.
+async fn occurrence_conflict(
+    repositories: &Repositories,
+    settings: &Settings,
+    hall: &Hall,
+    start: DateTime,
+    end: DateTime,
+    session_id: Option<ObjectId>,
+) -> Result<Option<OccurrenceConflictReason>, StatusCode> {
+    match check_hall_schedule(repositories, settings, hall, start, end).await? {
+        Some(HallUnavailability::OutsideOpeningHours) => return Ok(Some(OccurrenceConflictReason::OutsideOpeningHours)),
+        Some(HallUnavailability::Closed { .. }) => return Ok(Some(OccurrenceConflictReason::HallClosed)),
+        None => {}
+    }
+    if !is_hall_available(repositories, settings, hall, start, end, session_id).await? {
+        return Ok(Some(OccurrenceConflictReason::HallUnavailable));
+    }
+
+    Ok(None)
+}
+
+/// Brings the future sessions of the series in line with its definition: occurrences on dates
+/// that are still planned are updated in place, moving to the new start time if it changed,
+/// ones on dates that no longer are get cancelled and new ones are created where the hall is
+/// free. Past sessions are never touched. Moving the series to a hall that lacks a seat taken
+/// for one of its occurrences fails with `CONFLICT` before anything is changed.
+async fn sync_series_sessions(
+    repositories: &Repositories,
+    settings: &Settings,
+    events: &EventBus,
+    series: SessionSeries,
+) -> Result<SessionSeriesChanges, StatusCode> {
+    let series_id = series.id.ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    let movie = repositories
+        .movies
+        .find(series.movie_id)
+        .await?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let hall = repositories
+        .halls
+        .find(series.hall_id)
+        .await?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    // The series runs at the same wall-clock time in the hall's time zone, whatever the offset.
+    let time_zone = hall_time_zone(repositories, settings, &hall).await?;
+    let now = DateTime::now();
+
+    let mut conflicts = Vec::new();
+    let mut planned = Vec::new();
+    // Dates whose start time does not exist locally. Their sessions are left as they are.
+    let mut skipped = Vec::new();
+    for date in series.rule.dates(series.start_date) {
+        match instant_in(time_zone, date.and_time(series.start_time)) {
+            Some(start) if start > now => planned.push((date, start)),
+            Some(_) => {}
+            None => {
+                skipped.push(date);
+                conflicts.push(OccurrenceConflict {
+                    date,
+                    session_id: None,
+                    reason: OccurrenceConflictReason::InvalidLocalTime,
+                });
+            }
+        }
+    }
+
+    let existing = repositories.sessions.list_for_series(series_id).await?;
+    // Occurrences moving to another hall keep their bookings, so every seat taken for them
+    // has to exist in the new hall too.
+    let moving: Vec<ObjectId> = existing
+        .iter()
+        .filter(|session| session.start > now && session.hall_id != Some(series.hall_id))
+        .filter(|session| {
+            let date = local_time_in(time_zone, session.start).date();
+            planned.iter().any(|(planned_date, _)| *planned_date == date)
+        })
+        .filter_map(|session| session.id)
+        .collect();
+    ensure_seats_in_layout(repositories, &moving, hall.layout.as_ref()).await?;
+
+    let mut created: Vec<SessionResponse> = Vec::new();
+    let mut updated: Vec<SessionResponse> = Vec::new();
+    let mut cancelled: Vec<SessionResponse> = Vec::new();
+
+    for mut session in existing.into_iter().filter(|session| session.start > now) {
+        let Some(session_id) = session.id else {
+            continue;
+        };
+        let date = local_time_in(time_zone, session.start).date();
+
+        // Occurrences are matched by date, so a new start time moves them along with their
+        // bookings instead of cancelling them.
+        let Some(index) = planned.iter().position(|(planned_date, _)| *planned_date == date) else {
+            if skipped.contains(&date) {
+                continue;
+            }
+            if cancel_occurrence(repositories, events, &session).await? {
+                cancelled.push(session.into());
+            } else {
+                conflicts.push(OccurrenceConflict {
+                    date,
+                    session_id: Some(session_id),
+                    reason: OccurrenceConflictReason::HasCheckedInTickets,
+                });
+            }
+            continue;
+        };
+        let (_, start) = planned.remove(index);
+
+        let end = session_end(settings, &hall, &movie, start);
+        let moved = session.hall_id != Some(series.hall_id) || session.start != start || session.end != end;
+        let changed = moved
+            || session.title != series.title
+            || session.movie_id != Some(series.movie_id)
+            || session.price_list_id != series.price_list_id;
+        if !changed {
+            continue;
+        }
+        if moved {
+            if let Some(reason) =
+                occurrence_conflict(repositories, settings, &hall, start, end, Some(session_id)).await?
+            {
+                conflicts.push(OccurrenceConflict {
+                    date,
+                    session_id: Some(session_id),
+                    reason,
+                });
+                continue;
+            }
+        }
+
+        let previous = session.clone();
+        session.title = series.title.clone();
+        session.movie_id = Some(series.movie_id);
+        session.hall_id = Some(series.hall_id);
+        session.price_list_id = series.price_list_id;
+        session.start = start;
+        session.end = end;
+        if repositories.sessions.replace(&session).await? {
+            events.publish(DomainEvent::SessionUpdated {
+                id: session_id,
+                previous: Some(previous),
+            });
+            updated.push(session.into());
+        }
+    }
+
+    for (date, start) in planned {
+        let end = session_end(settings, &hall, &movie, start);
+        if let Some(reason) = occurrence_conflict(repositories, settings, &hall, start, end, None).await? {
+            conflicts.push(OccurrenceConflict {
+                date,
+                session_id: None,
+                reason,
+            });
+            continue;
+        }
+
+        let session = repositories
+            .sessions
+            .insert(Session {
+                id: None,
+                title: series.title.clone(),
+                movie_id: Some(series.movie_id),
+                hall_id: Some(series.hall_id),
+                price_list_id: series.price_list_id,
+                series_id: Some(series_id),
+                start,
+                end,
+            })
+            .await?;
+        if let Some(session_id) = session.id {
+            events.publish(DomainEvent::SessionAdded(session_id));
+        }
+        created.push(session.into());
+    }
+
+    conflicts.sort_by_key(|conflict| conflict.date);
+
+    Ok(SessionSeriesChanges {
+        series,
+        created,
+        updated,
+        cancelled,
+        conflicts,
+    })
+}
+
+pub async fn load_session_series(
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<Vec<SessionSeries>>, StatusCode> {
+    let series = repositories.session_series.list().await?;
+
+    Ok(Json(series))
+}
+
+pub async fn load_session_series_by_id(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<SessionSeriesDetail>, StatusCode> {
+    let series_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let series = repositories
+        .session_series
+        .find(series_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let sessions = repositories.sessions.list_for_series(series_id).await?;
+
+    Ok(Json(SessionSeriesDetail {
+        series,
+        sessions: sessions.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Creates the series and schedules its occurrences. Occurrences whose hall is taken, closed
+/// or not open are skipped and listed under `conflicts`.
+pub async fn add_session_series(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(events): Extension<EventBus>,
+    Json(mut series): Json<SessionSeries>,
+) -> Result<(StatusCode, Json<SessionSeriesChanges>), StatusCode> {
+    validate_series(&repositories, &series).await?;
+
+    series.id = None;
+    let series = repositories.session_series.insert(series).await?;
+    let changes = sync_series_sessions(&repositories, &settings, &events, series).await?;
+
+    Ok((StatusCode::CREATED, Json(changes)))
+}
+
+/// Replaces the series definition and applies it to the occurrences that have not started yet.
+/// The series can change halls, but not venues.
+pub async fn replace_session_series(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(events): Extension<EventBus>,
+    Json(mut series): Json<SessionSeries>,
+) -> Result<Json<SessionSeriesChanges>, StatusCode> {
+    let series_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    validate_series(&repositories, &series).await?;
+
+    let current = repositories
+        .session_series
+        .find(series_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if current.hall_id != series.hall_id {
+        let current_hall = repositories.halls.find(current.hall_id).await?;
+        let hall = repositories.halls.find(series.hall_id).await?;
+        if let (Some(current_hall), Some(hall)) = (current_hall, hall) {
+            if current_hall.venue_id != hall.venue_id {
+                eprintln!("Session series {} cannot move to a hall at another venue", series_id);
+                return Err(StatusCode::CONFLICT);
+            }
+        }
+    }
+
+    // The series is only saved once its sessions could follow it.
+    series.id = Some(series_id);
+    let changes = sync_series_sessions(&repositories, &settings, &events, series).await?;
+    if !repositories.session_series.replace(&changes.series).await? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(changes))
+}
+
+/// Cancels the occurrences that have not started yet and removes the series. Past sessions
+/// are kept as standalone sessions.
+pub async fn delete_session_series(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(events): Extension<EventBus>,
+) -> Result<Json<SessionSeriesChanges>, StatusCode> {
+    let series_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let series = repositories
+        .session_series
+        .find(series_id)
+        .await?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let time_zone = match repositories.halls.find(series.hall_id).await? {
+        Some(hall) => hall_time_zone(&repositories, &settings, &hall).await?,
+        None => settings.time_zone,
+    };
+    let now = DateTime::now();
+    let mut cancelled: Vec<SessionResponse> = Vec::new();
+    let mut conflicts = Vec::new();
+    for session in repositories.sessions.list_for_series(series_id).await? {
+        let Some(session_id) = session.id.filter(|_| session.start > now) else {
+            continue;
+        };
+        if cancel_occurrence(&repositories, &events, &session).await? {
+            cancelled.push(session.into());
+        } else {
+            conflicts.push(OccurrenceConflict {
+                date: local_time_in(time_zone, session.start).date(),
+                session_id: Some(session_id),
+                reason: OccurrenceConflictReason::HasCheckedInTickets,
+            });
+        }
+    }
+
+    repositories.session_series.delete(series_id).await?;
+
+    Ok(Json(SessionSeriesChanges {
+        series,
+        created: Vec::new(),
+        updated: Vec::new(),
+        cancelled,
+        conflicts,
+    }))
+}
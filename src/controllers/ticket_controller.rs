@@ -0,0 +1,164 @@
+use std::io::Cursor;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use image::{ImageFormat, Luma};
+use mongodb::bson::DateTime;
+use qrcode::{render::svg, QrCode};
+use serde::Deserialize;
+
+use crate::{
+    auth::Caller,
+    controllers::booking_controller::{ensure_booking_access, load_booking},
+    models::booking_model::{BookingStatus, CheckInRejection, CheckInRequest, CheckInResponse},
+    repositories::Repositories,
+    settings::Settings,
+    ticket_signing::TicketSigner,
+};
+
+const QR_MIN_SIZE: u32 = 256;
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+#[derive(Deserialize)]
+pub struct QrQuery {
+    #[serde(default)]
+    pub format: QrFormat,
+}
+
+pub async fn ticket_qr(
+    Path((booking_id, ticket_id)): Path<(String, String)>,
+    Query(query): Query<QrQuery>,
+    caller: Caller,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Response, StatusCode> {
+    let booking = load_booking(&repositories, &booking_id).await?;
+    ensure_booking_access(&caller, &booking)?;
+    if matches!(booking.status, BookingStatus::Cancelled | BookingStatus::Refunded) {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let ticket = booking
+        .tickets
+        .iter()
+        .find(|ticket| ticket.id.to_hex() == ticket_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let code = QrCode::new(ticket.token.as_bytes()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match query.format {
+        QrFormat::Svg => {
+            let image = code
+                .render::<svg::Color>()
+                .min_dimensions(QR_MIN_SIZE, QR_MIN_SIZE)
+                .build();
+            Ok(([(header::CONTENT_TYPE, "image/svg+xml")], image).into_response())
+        }
+        QrFormat::Png => {
+            let image = code
+                .render::<Luma<u8>>()
+                .min_dimensions(QR_MIN_SIZE, QR_MIN_SIZE)
+                .build();
+            let mut png = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|e| {
+                    eprintln!("Failed to encode QR code: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+        }
+    }
+}
+
+fn reject(reason: CheckInRejection) -> (StatusCode, Json<CheckInResponse>) {
+    let status = match reason {
+        CheckInRejection::InvalidToken => StatusCode::BAD_REQUEST,
+        CheckInRejection::UnknownTicket | CheckInRejection::SessionNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::CONFLICT,
+    };
+
+    (
+        status,
+        Json(CheckInResponse::Rejected {
+            reason,
+            message: reason.message(),
+        }),
+    )
+}
+
+/// Validates a scanned ticket and marks it as used. Every refusal carries its own reason
+/// so door staff can tell a wrong hall from a reused ticket.
+pub async fn check_in(
+    Extension(repositories): Extension<Repositories>,
+    Extension(settings): Extension<Settings>,
+    Extension(signer): Extension<TicketSigner>,
+    Json(request): Json<CheckInRequest>,
+) -> Result<(StatusCode, Json<CheckInResponse>), StatusCode> {
+    let Some((booking_id, ticket_id)) = signer.verify(&request.token) else {
+        return Ok(reject(CheckInRejection::InvalidToken));
+    };
+
+    let Some(booking) = repositories.bookings.find(booking_id).await? else {
+        return Ok(reject(CheckInRejection::UnknownTicket));
+    };
+    let Some(ticket) = booking.tickets.iter().find(|ticket| ticket.id == ticket_id) else {
+        return Ok(reject(CheckInRejection::UnknownTicket));
+    };
+
+    match booking.status {
+        BookingStatus::Pending => return Ok(reject(CheckInRejection::BookingNotConfirmed)),
+        BookingStatus::Cancelled | BookingStatus::Refunded => {
+            return Ok(reject(CheckInRejection::BookingVoided))
+        }
+        BookingStatus::Confirmed | BookingStatus::CheckedIn => {}
+    }
+
+    let Some(session) = repositories.sessions.find(booking.session_id).await? else {
+        return Ok(reject(CheckInRejection::SessionNotFound));
+    };
+    if session.hall_id != Some(request.hall_id) {
+        return Ok(reject(CheckInRejection::WrongHall));
+    }
+
+    let now = DateTime::now();
+    let opens_at = session.start.timestamp_millis() - settings.checkin_opens_minutes * 60 * 1000;
+    if now.timestamp_millis() < opens_at {
+        return Ok(reject(CheckInRejection::TooEarly));
+    }
+    if now >= session.end {
+        return Ok(reject(CheckInRejection::TooLate));
+    }
+
+    if ticket.checked_in_at.is_some()
+        || !repositories
+            .bookings
+            .check_in_ticket(booking_id, ticket_id, now)
+            .await?
+    {
+        return Ok(reject(CheckInRejection::AlreadyUsed));
+    }
+
+    if booking.status == BookingStatus::Confirmed {
+        repositories
+            .bookings
+            .update_status(booking_id, BookingStatus::Confirmed, BookingStatus::CheckedIn, now)
+            .await?;
+    }
+
+    let mut ticket = ticket.clone();
+    ticket.checked_in_at = Some(now);
+
+    Ok((
+        StatusCode::OK,
+        Json(CheckInResponse::Accepted { booking_id, ticket }),
+    ))
+}
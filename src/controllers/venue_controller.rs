@@ -0,0 +1,119 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use chrono_tz::Tz;
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    models::venue_model::{Venue, VenueDetail, VenueFilter, VenueUpdate},
+    repositories::Repositories,
+};
+
+/// Venue asked for with `?venue_id=`, checked to exist.
+pub async fn venue_filter(repositories: &Repositories, filter: &VenueFilter) -> Result<Option<ObjectId>, StatusCode> {
+    let Some(id_str) = &filter.venue_id else {
+        return Ok(None);
+    };
+    let venue_id = match ObjectId::parse_str(id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.venues.find(venue_id).await?.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Some(venue_id))
+}
+
+pub async fn ensure_venue_exists(repositories: &Repositories, venue_id: Option<ObjectId>) -> Result<(), StatusCode> {
+    if let Some(venue_id) = venue_id {
+        if repositories.venues.find(venue_id).await?.is_none() {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn is_valid_time_zone(time_zone: &str) -> bool {
+    time_zone.parse::<Tz>().is_ok()
+}
+
+pub async fn load_venues(Extension(repositories): Extension<Repositories>) -> Result<Json<Vec<Venue>>, StatusCode> {
+    let venues = repositories.venues.list().await?;
+
+    Ok(Json(venues))
+}
+
+pub async fn load_venue(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<VenueDetail>, StatusCode> {
+    let venue_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let venue = repositories.venues.find(venue_id).await?.ok_or(StatusCode::NOT_FOUND)?;
+    let halls = repositories.halls.list_for_venue(venue_id).await?;
+
+    Ok(Json(VenueDetail { venue, halls }))
+}
+
+pub async fn add_venue(
+    Extension(repositories): Extension<Repositories>,
+    Json(mut venue): Json<Venue>,
+) -> Result<(StatusCode, Json<Venue>), StatusCode> {
+    if !is_valid_time_zone(&venue.time_zone) {
+        eprintln!("Unknown time zone: {}", venue.time_zone);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    venue.id = None;
+    let venue = repositories.venues.insert(venue).await?;
+
+    Ok((StatusCode::CREATED, Json(venue)))
+}
+
+pub async fn update_venue(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+    Json(update_data): Json<VenueUpdate>,
+) -> Result<Json<VenueUpdate>, StatusCode> {
+    let venue_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if let Some(time_zone) = &update_data.time_zone {
+        if !is_valid_time_zone(time_zone) {
+            eprintln!("Unknown time zone: {}", time_zone);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    if repositories.venues.update(venue_id, &update_data).await? {
+        Ok(Json(update_data))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+pub async fn delete_venue(
+    Path(id_str): Path<String>,
+    Extension(repositories): Extension<Repositories>,
+) -> Result<Json<String>, StatusCode> {
+    let venue_id = match ObjectId::parse_str(&id_str) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    if repositories.venues.delete(venue_id).await? {
+        Ok(Json("Venue deleted and its halls detached successfully".to_string()))
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
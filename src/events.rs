@@ -0,0 +1,142 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use futures::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_document, Document},
+    change_stream::event::{ChangeStreamEvent, OperationType},
+    Database,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::models::session_model::Session;
+
+/// Collections whose changes are pushed to WebSocket clients.
+const WATCHED_COLLECTIONS: [&str; 3] = ["sessions", "movies", "halls"];
+
+/// Change to a session, movie or hall, whoever made it. Events only carry ids; the
+/// current document is looked up when the event is delivered.
+#[derive(Clone, Debug)]
+pub enum DomainEvent {
+    SessionAdded(ObjectId),
+    /// `previous` is the session before the change. Change streams do not know it.
+    SessionUpdated { id: ObjectId, previous: Option<Session> },
+    SessionDeleted { id: ObjectId, previous: Option<Session> },
+    MovieAdded(ObjectId),
+    MovieUpdated(ObjectId),
+    MovieDeleted(ObjectId),
+    HallAdded(ObjectId),
+    HallUpdated(ObjectId),
+    HallDeleted(ObjectId),
+}
+
+/// Domain event on its way to the WebSocket hub.
+pub struct Change {
+    pub event: DomainEvent,
+    /// Id of the change stream event it came from. Instances watching the same database see
+    /// the same id.
+    pub change_id: Option<String>,
+}
+
+/// Domain events of a tenant. They come from a MongoDB change stream when the deployment
+/// supports one, so edits made outside the API are seen too, and from the handlers that
+/// make the changes otherwise.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: UnboundedSender<Change>,
+    /// Set while the change stream is open. What handlers publish would then be reported twice.
+    watching: Arc<AtomicBool>,
+}
+
+impl EventBus {
+    pub fn new() -> (Self, UnboundedReceiver<Change>) {
+        let (sender, receiver) = unbounded_channel();
+        let bus = EventBus {
+            sender,
+            watching: Arc::new(AtomicBool::new(false)),
+        };
+
+        (bus, receiver)
+    }
+
+    /// Reports a change made by a handler, unless the change stream will report it.
+    pub fn publish(&self, event: DomainEvent) {
+        if !self.watching.load(Ordering::Relaxed) {
+            self.send(Change { event, change_id: None });
+        }
+    }
+
+    fn send(&self, change: Change) {
+        if let Err(e) = self.sender.send(change) {
+            eprintln!("Failed to publish domain event: {}", e);
+        }
+    }
+}
+
+fn change_id(change: &ChangeStreamEvent<Document>) -> Option<String> {
+    let token = to_document(&change.id).ok()?;
+    token.get_str("_data").ok().map(str::to_string)
+}
+
+fn domain_event(change: &ChangeStreamEvent<Document>) -> Option<DomainEvent> {
+    let collection = change.ns.as_ref()?.coll.as_deref()?;
+    let id = change.document_key.as_ref()?.get_object_id("_id").ok()?;
+
+    match (collection, &change.operation_type) {
+        ("sessions", OperationType::Insert) => Some(DomainEvent::SessionAdded(id)),
+        ("sessions", OperationType::Update | OperationType::Replace) => {
+            Some(DomainEvent::SessionUpdated { id, previous: None })
+        }
+        ("sessions", OperationType::Delete) => Some(DomainEvent::SessionDeleted { id, previous: None }),
+        ("movies", OperationType::Insert) => Some(DomainEvent::MovieAdded(id)),
+        ("movies", OperationType::Update | OperationType::Replace) => Some(DomainEvent::MovieUpdated(id)),
+        ("movies", OperationType::Delete) => Some(DomainEvent::MovieDeleted(id)),
+        ("halls", OperationType::Insert) => Some(DomainEvent::HallAdded(id)),
+        ("halls", OperationType::Update | OperationType::Replace) => Some(DomainEvent::HallUpdated(id)),
+        ("halls", OperationType::Delete) => Some(DomainEvent::HallDeleted(id)),
+        _ => None,
+    }
+}
+
+/// Tails the change stream of the tenant's database in the background. Change streams need
+/// a replica set; when one cannot be opened, or breaks later, the bus goes back to the
+/// events published by the handlers.
+pub async fn watch_changes(database: Database, bus: EventBus) {
+    let pipeline = [doc! { "$match": { "ns.coll": { "$in": WATCHED_COLLECTIONS.to_vec() } } }];
+    let mut stream = match database.watch(pipeline, None).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            println!(
+                "Change streams are not available for {}, using the internal event bus: {}",
+                database.name(),
+                e
+            );
+            return;
+        }
+    };
+    bus.watching.store(true, Ordering::Relaxed);
+
+    tokio::spawn(async move {
+        while let Some(change) = stream.next().await {
+            match change {
+                Ok(change) => {
+                    if let Some(event) = domain_event(&change) {
+                        bus.send(Change {
+                            event,
+                            change_id: change_id(&change),
+                        });
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Change stream of {} failed: {}", database.name(), e);
+                    break;
+                }
+            }
+        }
+
+        println!("Change stream of {} closed, using the internal event bus", database.name());
+        bus.watching.store(false, Ordering::Relaxed);
+    });
+}
@@ -1,94 +1,224 @@
 use anyhow::anyhow;
 use axum::{
-    extract::{Extension, WebSocketUpgrade},
-    http::{header, HeaderValue, Method},
-    routing::{delete, get, patch, post},
+    extract::Extension,
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use mongodb::{bson::doc, options::ClientOptions, Client};
-use tokio::sync::Mutex;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 
+mod auth;
+mod broadcast;
 mod controllers;
+mod events;
 pub mod models;
+mod repositories;
+mod settings;
+mod tenancy;
+mod ticket_signing;
 mod utils;
 use controllers::{
-    hall_controller::*, home_controller, movie_controller::*, session_controller::*,
+    api_key_controller::*, auth_controller::*, booking_controller::*, event_stream_controller::*, hall_controller::*, hall_schedule_controller::*, home_controller, movie_controller::*,
+    price_list_controller::*, promotion_controller::*, reservation_controller::*, schedule_controller::*,
+    session_controller::*, session_series_controller::*, ticket_controller::*, venue_controller::*,
 };
 
 mod websockets;
 use shuttle_runtime::{SecretStore, Secrets};
 
-use crate::websockets::{websocket_handler, SharedState};
+use crate::{
+    auth::{authorize, ensure_admin, Access, JwtSigner},
+    models::{api_key_model::ApiScope, user_model::Role},
+    repositories::mongo_repository::DATABASE_NAME,
+    settings::Settings,
+    tenancy::{resolve_tenant, TenantRegistry, TenantSettings, API_KEY_HEADER, TENANT_HEADER},
+    ticket_signing::TicketSigner,
+    websockets::{websocket_handler, websocket_metrics, websocket_schema},
+};
 
 #[shuttle_runtime::main]
 async fn main(#[Secrets] secret_store: SecretStore) -> shuttle_axum::ShuttleAxum {
-    // get secret defined in `Secrets.toml` file.
-    let database_url = if let Some(secret) = secret_store.get("MONGODB_URI") {
+    let app_url = if let Some(secret) = secret_store.get("APP_URL") {
         secret
     } else {
         return Err(anyhow!("secret was not found").into());
     };
 
-    let app_url = if let Some(secret) = secret_store.get("APP_URL") {
-        secret
+    let ticket_signer = if let Some(secret) = secret_store.get("TICKET_SIGNING_KEY") {
+        TicketSigner::new(secret.as_bytes())
     } else {
         return Err(anyhow!("secret was not found").into());
     };
 
-    // For different deployment than shuttle use dotenv
-    // dotenv().ok(); // Load environment variables
-    // env::set_var("RUST_LOG", "debug");
-    // let database_url = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
+    let jwt_signer = if let Some(secret) = secret_store.get("JWT_SIGNING_KEY") {
+        JwtSigner::new(secret.as_bytes())
+    } else {
+        return Err(anyhow!("secret was not found").into());
+    };
 
-    let client_options = ClientOptions::parse(&database_url)
-        .await
-        .expect("Failed to connect to MongoDB");
-    let client = Client::with_options(client_options).expect("Failed to initialize MongoDB client");
+    let settings = Settings::from_secrets(&secret_store)?;
 
-    // Ping the server to see if you can connect to the cluster
-    client
-        .database("cinema-axum")
-        .run_command(doc! {"ping": 1}, None)
-        .await
-        .unwrap();
-    println!("Pinged your deployment. You successfully connected to MongoDB!");
+    let tenant_settings = TenantSettings::from_secrets(&secret_store)?;
 
-    let shared_client = Arc::new(client.clone());
+    let registry = if secret_store.get("STORAGE_BACKEND").as_deref() == Some("memory") {
+        println!("Using in-memory storage. Data will be lost on restart.");
+        TenantRegistry::in_memory(settings.clone())
+    } else {
+        // get secret defined in `Secrets.toml` file.
+        let database_url = if let Some(secret) = secret_store.get("MONGODB_URI") {
+            secret
+        } else {
+            return Err(anyhow!("secret was not found").into());
+        };
+
+        // For different deployment than shuttle use dotenv
+        // dotenv().ok(); // Load environment variables
+        // env::set_var("RUST_LOG", "debug");
+        // let database_url = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
+
+        let client_options = ClientOptions::parse(&database_url)
+            .await
+            .expect("Failed to connect to MongoDB");
+        let client = Client::with_options(client_options).expect("Failed to initialize MongoDB client");
+
+        // Ping the server to see if you can connect to the cluster
+        client
+            .database(DATABASE_NAME)
+            .run_command(doc! {"ping": 1}, None)
+            .await
+            .unwrap();
+        println!("Pinged your deployment. You successfully connected to MongoDB!");
+
+        TenantRegistry::mongo(client, settings.clone())
+    };
 
-    let shared_state = Arc::new(Mutex::new(SharedState::new()));
+    // Set up every known tenant now, so their indexes exist, they have an admin to log in
+    // as and expired holds get released before the first request comes in.
+    let admin_account = secret_store.get("ADMIN_EMAIL").zip(secret_store.get("ADMIN_PASSWORD"));
+    for tenant in tenant_settings.tenants() {
+        let context = registry
+            .context(&tenant)
+            .await
+            .map_err(|e| anyhow!("failed to set up tenant {}: {}", tenant, e))?;
+        if let Some((email, password)) = &admin_account {
+            ensure_admin(&context.repositories, email, password)
+                .await
+                .map_err(|e| anyhow!("failed to create the admin of tenant {}: {}", tenant, e))?;
+        }
+    }
+    let registry = Arc::new(registry);
 
-    let shared_cliented = Arc::new(client.clone());
+    // Who can call each route. Routes without a layer are open to anyone and ignore
+    // partner API keys.
+    let admin = middleware::from_fn_with_state(Access::role(Role::Admin), authorize);
+    let staff = middleware::from_fn_with_state(Access::role(Role::Staff), authorize);
+    let customer = middleware::from_fn_with_state(Access::role(Role::Customer), authorize);
+    let booking = middleware::from_fn_with_state(
+        Access::role(Role::Customer).or_scope(ApiScope::BookingsWrite),
+        authorize,
+    );
+    let catalog = middleware::from_fn_with_state(Access::public(ApiScope::CatalogRead), authorize);
+    let schedule = middleware::from_fn_with_state(Access::public(ApiScope::SessionsRead), authorize);
 
     let app = Router::new()
         .route("/", get(home_controller::index))
-        .route("/ws", get(move |ws: WebSocketUpgrade| websocket_handler(ws, Extension(shared_cliented), shared_state.clone())))
-        .route("/sessions", get(load_sessions_with_details))
-        .route("/sessions/{id}", get(fetch_session_by_id))
-        .route("/movies", get(load_movies_with_details))
-        .route("/movies", post(add_movie))
-        .route("/movies/{id}", get(load_movie_with_details))
-        .route("/movies/{id}", patch(update_movie))
-        .route("/movies/{id}", delete(delete_movie))
-        .route("/halls", get(load_halls_with_details))
-        .route("/halls", post(add_hall))
-        .route("/halls/{id}", get(load_hall_with_details))
-        .route("/halls/{id}", patch(update_hall))
-        .route("/halls/{id}", delete(delete_hall))
+        .route("/ws", get(websocket_handler).route_layer(schedule.clone()))
+        .route("/ws/schema", get(websocket_schema))
+        .route("/ws/metrics", get(websocket_metrics).route_layer(admin.clone()))
+        .route("/events", get(event_stream).route_layer(schedule.clone()))
+        .route("/auth/login", post(login))
+        .route("/auth/register", post(register))
+        .route("/auth/me", get(current_user).route_layer(customer.clone()))
+        .route("/users", post(add_user).route_layer(admin.clone()))
+        .route("/api-keys", get(load_api_keys).route_layer(admin.clone()))
+        .route("/api-keys", post(add_api_key).route_layer(admin.clone()))
+        .route("/api-keys/{id}/rotate", post(rotate_api_key).route_layer(admin.clone()))
+        .route("/api-keys/{id}/revoke", post(revoke_api_key).route_layer(admin.clone()))
+        .route("/sessions", get(load_sessions_with_details).route_layer(schedule.clone()))
+        .route("/sessions/{id}", get(fetch_session_by_id).route_layer(schedule.clone()))
+        .route("/sessions/{id}/seats", get(get_session_seats).route_layer(schedule.clone()))
+        .route("/sessions/{id}/quote", post(quote_session).route_layer(schedule.clone()))
+        .route("/sessions/{id}/holds", post(hold_seats).route_layer(booking.clone()))
+        .route("/holds/{id}", delete(release_hold).route_layer(booking.clone()))
+        .route("/sessions/{id}/bookings", get(list_session_bookings).route_layer(staff.clone()))
+        .route("/sessions/{id}/bookings", post(create_booking).route_layer(booking.clone()))
+        .route("/bookings/{id}", get(get_booking).route_layer(booking.clone()))
+        .route("/bookings/{id}/confirm", post(confirm_booking).route_layer(booking.clone()))
+        .route("/bookings/{id}/cancel", post(cancel_booking).route_layer(booking.clone()))
+        .route("/bookings/{id}/refund", post(refund_booking).route_layer(staff.clone()))
+        .route("/bookings/{id}/tickets/{ticket_id}/qr", get(ticket_qr).route_layer(booking.clone()))
+        .route("/checkin", post(check_in).route_layer(staff.clone()))
+        .route("/movies", get(load_movies_with_details).route_layer(catalog.clone()))
+        .route("/movies", post(add_movie).route_layer(admin.clone()))
+        .route("/movies/{id}", get(load_movie_with_details).route_layer(catalog.clone()))
+        .route("/movies/{id}", patch(update_movie).route_layer(admin.clone()))
+        .route("/movies/{id}", delete(delete_movie).route_layer(admin.clone()))
+        .route("/venues", get(load_venues).route_layer(catalog.clone()))
+        .route("/venues", post(add_venue).route_layer(admin.clone()))
+        .route("/venues/{id}", get(load_venue).route_layer(catalog.clone()))
+        .route("/venues/{id}", patch(update_venue).route_layer(admin.clone()))
+        .route("/venues/{id}", delete(delete_venue).route_layer(admin.clone()))
+        .route("/halls", get(load_halls_with_details).route_layer(catalog.clone()))
+        .route("/halls", post(add_hall).route_layer(admin.clone()))
+        .route("/halls/{id}", get(load_hall_with_details).route_layer(catalog.clone()))
+        .route("/halls/{id}", patch(update_hall).route_layer(admin.clone()))
+        .route("/halls/{id}", delete(delete_hall).route_layer(admin.clone()))
+        .route("/halls/{id}/layout", get(get_hall_layout).route_layer(catalog.clone()))
+        .route("/halls/{id}/layout", post(add_hall_layout).route_layer(admin.clone()))
+        .route("/halls/{id}/layout", put(replace_hall_layout).route_layer(admin.clone()))
+        .route("/halls/{id}/layout", delete(delete_hall_layout).route_layer(admin.clone()))
+        .route("/halls/{id}/opening-hours", get(load_hall_opening_hours).route_layer(catalog.clone()))
+        .route("/halls/{id}/opening-hours", post(add_hall_opening_hours).route_layer(admin.clone()))
+        .route("/halls/{id}/opening-hours/{hours_id}", delete(delete_hall_opening_hours).route_layer(admin.clone()))
+        .route("/halls/{id}/closures", get(load_hall_closures).route_layer(staff.clone()))
+        .route("/halls/{id}/closures", post(add_hall_closure).route_layer(admin.clone()))
+        .route("/halls/{id}/closures/{closure_id}", delete(delete_hall_closure).route_layer(admin.clone()))
+        .route("/halls/{id}/calendar", get(hall_calendar).route_layer(staff.clone()))
+        .route("/price-lists", get(load_price_lists).route_layer(catalog.clone()))
+        .route("/price-lists", post(add_price_list).route_layer(admin.clone()))
+        .route("/price-lists/{id}", get(load_price_list).route_layer(catalog.clone()))
+        .route("/price-lists/{id}", put(replace_price_list).route_layer(admin.clone()))
+        .route("/price-lists/{id}", delete(delete_price_list).route_layer(admin.clone()))
+        .route("/schedule/plan", post(plan_schedule).route_layer(staff.clone()))
+        .route("/session-series", get(load_session_series).route_layer(staff.clone()))
+        .route("/session-series", post(add_session_series).route_layer(staff.clone()))
+        .route("/session-series/{id}", get(load_session_series_by_id).route_layer(staff.clone()))
+        .route("/session-series/{id}", put(replace_session_series).route_layer(staff.clone()))
+        .route("/session-series/{id}", delete(delete_session_series).route_layer(staff.clone()))
+        .route("/promotions", get(load_promotions).route_layer(staff.clone()))
+        .route("/promotions", post(add_promotion).route_layer(admin.clone()))
+        .route("/promotions/{id}", get(load_promotion).route_layer(staff.clone()))
+        .route("/promotions/{id}", put(replace_promotion).route_layer(admin.clone()))
+        .route("/promotions/{id}", delete(delete_promotion).route_layer(admin.clone()))
         .layer(
             CorsLayer::new()
                 .allow_methods([
                     Method::GET,
                     Method::POST,
                     Method::PATCH,
+                    Method::PUT,
                     Method::DELETE,
                     Method::OPTIONS,
                 ])
                 .allow_origin(app_url.parse::<HeaderValue>().unwrap())
-                .allow_headers([header::CONTENT_TYPE]),
+                .allow_headers([
+                    header::AUTHORIZATION,
+                    header::CONTENT_TYPE,
+                    HeaderName::from_static(TENANT_HEADER),
+                    HeaderName::from_static(API_KEY_HEADER),
+                    HeaderName::from_static(LAST_EVENT_ID_HEADER),
+                ]),
         )
-        .layer(Extension(shared_client.clone()));
+        // Repositories and the WebSocket hub are per tenant and added by `resolve_tenant`.
+        .layer(middleware::from_fn(resolve_tenant))
+        .layer(Extension(tenant_settings))
+        .layer(Extension(registry))
+        .layer(Extension(settings))
+        .layer(Extension(ticket_signer))
+        .layer(Extension(jwt_signer));
 
     // run our app with hyper, listening globally on port 4000 with Tokio - no shuttle deployment
     // let listener = tokio::net::TcpListener::bind("0.0.0.0:4000").await.unwrap();
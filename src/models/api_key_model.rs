@@ -0,0 +1,105 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_datetime, serialize_object_id, serialize_optional_datetime};
+
+/// What a partner key can be used for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Movies, venues, halls and price lists.
+    #[serde(rename = "catalog:read")]
+    CatalogRead,
+    /// Sessions, their seat maps and the WebSocket feed.
+    #[serde(rename = "sessions:read")]
+    SessionsRead,
+    /// Holding seats and making bookings, which the key can then manage.
+    #[serde(rename = "bookings:write")]
+    BookingsWrite,
+}
+
+/// Key a third-party site uses to call the API. Only a hash of the key is stored; the
+/// key itself is shown once, when it is created or rotated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKey {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    /// Who the key was issued to, e.g. the partner's name.
+    pub name: String,
+    /// Start of the key, so it can be recognised without storing it.
+    pub prefix: String,
+    /// SHA-256 of the key, base64url encoded.
+    pub key_hash: String,
+    pub scopes: Vec<ApiScope>,
+    /// Requests made with the key.
+    #[serde(default)]
+    pub uses: i64,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime"
+    )]
+    pub last_used_at: Option<DateTime>,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created_at: DateTime,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime"
+    )]
+    pub rotated_at: Option<DateTime>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime"
+    )]
+    pub revoked_at: Option<DateTime>,
+}
+
+/// A key as shown to admins, without its hash.
+#[derive(Serialize, Debug, Clone)]
+pub struct ApiKeyResponse {
+    #[serde(rename = "_id", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub uses: i64,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_optional_datetime")]
+    pub last_used_at: Option<DateTime>,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_optional_datetime")]
+    pub rotated_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_optional_datetime")]
+    pub revoked_at: Option<DateTime>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        ApiKeyResponse {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes: api_key.scopes,
+            uses: api_key.uses,
+            last_used_at: api_key.last_used_at,
+            created_at: api_key.created_at,
+            rotated_at: api_key.rotated_at,
+            revoked_at: api_key.revoked_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// A key right after it was created or rotated. `key` is not stored and cannot be shown again.
+#[derive(Serialize, Debug)]
+pub struct IssuedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
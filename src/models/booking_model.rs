@@ -0,0 +1,172 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    serialize_datetime, serialize_object_id, serialize_optional_datetime,
+    serialize_required_object_id,
+};
+
+use super::{
+    price_list_model::{QuoteSeat, TicketType},
+    promotion_model::AppliedPromotion,
+    seat_layout_model::SeatCategory,
+};
+
+/// Lifecycle of a booking:
+///
+/// ```text
+/// pending ──> confirmed ──> checked_in
+///    │            │
+///    │            ├──> refunded
+///    v            v
+/// cancelled <─────┘
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BookingStatus {
+    Pending,
+    Confirmed,
+    Cancelled,
+    Refunded,
+    CheckedIn,
+}
+
+impl BookingStatus {
+    pub fn can_transition_to(self, next: BookingStatus) -> bool {
+        use BookingStatus::*;
+
+        matches!(
+            (self, next),
+            (Pending, Confirmed)
+                | (Pending, Cancelled)
+                | (Confirmed, Cancelled)
+                | (Confirmed, Refunded)
+                | (Confirmed, CheckedIn)
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuyerContact {
+    pub name: String,
+    pub email: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Ticket {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub id: ObjectId,
+    pub row: String,
+    pub number: u32,
+    pub category: SeatCategory,
+    #[serde(default)]
+    pub ticket_type: TicketType,
+    /// Price paid in minor units of the booking currency, after any promo discount.
+    pub price: i64,
+    /// Signed token shown as the ticket's QR code at the door.
+    #[serde(default)]
+    pub token: String,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime"
+    )]
+    pub checked_in_at: Option<DateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Booking {
+    #[serde(
+        rename = "_id",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id"
+    )]
+    pub id: Option<ObjectId>,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub session_id: ObjectId,
+    /// Seat hold the tickets were taken from.
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hold_id: ObjectId,
+    pub buyer: BuyerContact,
+    pub tickets: Vec<Ticket>,
+    pub total: i64,
+    pub currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promotion: Option<AppliedPromotion>,
+    /// Partner key the booking was made with. Only that key can manage it.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id"
+    )]
+    pub api_key_id: Option<ObjectId>,
+    pub status: BookingStatus,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created_at: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookingRequest {
+    pub hold_id: ObjectId,
+    pub buyer: BuyerContact,
+    /// Ticket type per held seat. Seats left out are sold as adult tickets.
+    #[serde(default)]
+    pub seats: Vec<QuoteSeat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promo_code: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckInRequest {
+    pub token: String,
+    /// Hall the scanner is placed at.
+    pub hall_id: ObjectId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckInRejection {
+    InvalidToken,
+    UnknownTicket,
+    BookingNotConfirmed,
+    BookingVoided,
+    SessionNotFound,
+    WrongHall,
+    TooEarly,
+    TooLate,
+    AlreadyUsed,
+}
+
+impl CheckInRejection {
+    pub fn message(self) -> &'static str {
+        match self {
+            CheckInRejection::InvalidToken => "Ticket code is not valid",
+            CheckInRejection::UnknownTicket => "Ticket does not exist",
+            CheckInRejection::BookingNotConfirmed => "Booking has not been confirmed",
+            CheckInRejection::BookingVoided => "Booking was cancelled or refunded",
+            CheckInRejection::SessionNotFound => "Session no longer exists",
+            CheckInRejection::WrongHall => "Ticket is for a different hall",
+            CheckInRejection::TooEarly => "Check-in for this session has not opened yet",
+            CheckInRejection::TooLate => "Session has already ended",
+            CheckInRejection::AlreadyUsed => "Ticket has already been used",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CheckInResponse {
+    Accepted {
+        #[serde(serialize_with = "serialize_required_object_id")]
+        booking_id: ObjectId,
+        ticket: Ticket,
+    },
+    Rejected {
+        reason: CheckInRejection,
+        message: &'static str,
+    },
+}
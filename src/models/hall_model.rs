@@ -1,33 +1,65 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
+use schemars::JsonSchema;
 
 use crate::utils::serialize_object_id;
 
-use super::{movie_model::Movie, session_model::SessionResponse};
+use super::{movie_model::Movie, seat_layout_model::SeatLayout, session_model::SessionResponse};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Hall {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    #[schemars(with = "Option<String>")]
     pub id: Option<ObjectId>,
+    /// Venue the hall is in.
+    #[serde(default, skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    #[schemars(with = "Option<String>")]
+    pub venue_id: Option<ObjectId>,
     pub name: String,
     pub description: String,
     pub capacity: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<SeatLayout>,
+    /// Minutes of ads and trailers before the film. Falls back to `PRE_SHOW_MINUTES`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_show_minutes: Option<i64>,
+    /// Minutes needed to clean the hall after a session. Falls back to `CLEANING_MINUTES`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleaning_minutes: Option<i64>,
+    /// IANA time zone of the hall. Falls back to the venue's, then to `TIME_ZONE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HallDetail {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
     pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub venue_id: Option<ObjectId>,
     pub name: String,
     pub capacity: u32,
     pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<SeatLayout>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_show_minutes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleaning_minutes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
     pub movies: Vec<Movie>,
     pub sessions: Vec<SessionResponse>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct HallUpdate {
+    #[serde(serialize_with = "serialize_object_id")]
+    pub venue_id: Option<ObjectId>,
     pub name: Option<String>,
     pub capacity: Option<u32>,
     pub description: Option<String>,
+    pub pre_show_minutes: Option<i64>,
+    pub cleaning_minutes: Option<i64>,
+    pub time_zone: Option<String>,
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use chrono::{DateTime as ChronoDateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_datetime, serialize_object_id, serialize_required_object_id};
+
+/// Local hours a hall is open on the given weekdays. A window that closes at or before
+/// it opens runs past midnight into the next day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OpeningHours {
+    /// Days the window opens on. Empty means every day.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    pub opens: NaiveTime,
+    pub closes: NaiveTime,
+}
+
+impl OpeningHours {
+    /// Local start and end of the window opening on `date`, if it opens that day.
+    pub fn window(&self, date: NaiveDate) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if !self.weekdays.is_empty() && !self.weekdays.contains(&date.weekday()) {
+            return None;
+        }
+
+        let opens = date.and_time(self.opens);
+        let closes = if self.closes > self.opens {
+            date.and_time(self.closes)
+        } else {
+            (date + Duration::days(1)).and_time(self.closes)
+        };
+        Some((opens, closes))
+    }
+
+    /// Whether `[start, end]` local time fits inside one window, including one that opened
+    /// the day before and runs past midnight.
+    pub fn contains(&self, start: NaiveDateTime, end: NaiveDateTime) -> bool {
+        let date = start.date();
+        [date.pred_opt(), Some(date)]
+            .into_iter()
+            .flatten()
+            .filter_map(|date| self.window(date))
+            .any(|(opens, closes)| opens <= start && end <= closes)
+    }
+}
+
+/// Weekly opening hours of a hall. A hall without any is open around the clock.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HallOpeningHours {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hall_id: ObjectId,
+    #[serde(flatten)]
+    pub hours: OpeningHours,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosureKind {
+    Holiday,
+    PrivateEvent,
+    Maintenance,
+    Other,
+}
+
+/// One-off period the hall cannot be used for sessions.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HallClosure {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hall_id: ObjectId,
+    pub kind: ClosureKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub start: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub end: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HallClosureRequest {
+    pub kind: ClosureKind,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub start: ChronoDateTime<Utc>,
+    pub end: ChronoDateTime<Utc>,
+}
+
+/// Why a hall cannot take a session at the requested time.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum HallUnavailability {
+    OutsideOpeningHours,
+    Closed {
+        #[serde(serialize_with = "serialize_object_id")]
+        closure_id: Option<ObjectId>,
+        kind: ClosureKind,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        #[serde(serialize_with = "serialize_datetime")]
+        start: DateTime,
+        #[serde(serialize_with = "serialize_datetime")]
+        end: DateTime,
+    },
+}
+
+impl HallUnavailability {
+    pub fn closed(closure: HallClosure) -> Self {
+        HallUnavailability::Closed {
+            closure_id: closure.id,
+            kind: closure.kind,
+            note: closure.note,
+            start: closure.start,
+            end: closure.end,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            HallUnavailability::OutsideOpeningHours => "Session falls outside the hall's opening hours".to_string(),
+            HallUnavailability::Closed { kind, note, start, end, .. } => {
+                let what = match kind {
+                    ClosureKind::Holiday => "closed for a holiday",
+                    ClosureKind::PrivateEvent => "booked for a private event",
+                    ClosureKind::Maintenance => "closed for maintenance",
+                    ClosureKind::Other => "closed",
+                };
+                let mut message = format!(
+                    "Hall is {} from {} to {}",
+                    what,
+                    start.to_chrono().to_rfc3339(),
+                    end.to_chrono().to_rfc3339()
+                );
+                if let Some(note) = note {
+                    message.push_str(&format!(" ({})", note));
+                }
+                message
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockedReason {
+    OutsideOpeningHours,
+    Holiday,
+    PrivateEvent,
+    Maintenance,
+    Other,
+    Session,
+}
+
+impl From<ClosureKind> for BlockedReason {
+    fn from(kind: ClosureKind) -> Self {
+        match kind {
+            ClosureKind::Holiday => BlockedReason::Holiday,
+            ClosureKind::PrivateEvent => BlockedReason::PrivateEvent,
+            ClosureKind::Maintenance => BlockedReason::Maintenance,
+            ClosureKind::Other => BlockedReason::Other,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TimeRange {
+    #[serde(serialize_with = "serialize_datetime")]
+    pub start: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub end: DateTime,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BlockedTime {
+    #[serde(serialize_with = "serialize_datetime")]
+    pub start: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub end: DateTime,
+    pub reason: BlockedReason,
+    /// Closure or session taking the time.
+    #[serde(serialize_with = "serialize_object_id", skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HallCalendar {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hall_id: ObjectId,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub from: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub to: DateTime,
+    pub free: Vec<TimeRange>,
+    pub blocked: Vec<BlockedTime>,
+}
@@ -0,0 +1,194 @@
+use chrono::{Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use mongodb::bson::DateTime;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How to read a wall-clock time that happens twice, when clocks go back.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AmbiguousTime {
+    #[default]
+    Reject,
+    /// The first occurrence, still on summer time.
+    Earlier,
+    /// The second occurrence, after the clocks went back.
+    Later,
+}
+
+/// How to read a wall-clock time that never happens, when clocks go forward.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NonexistentTime {
+    #[default]
+    Reject,
+    /// Moves it forward by the length of the gap, so 02:30 becomes 03:30 when clocks jump
+    /// from 02:00 to 03:00.
+    ShiftForward,
+}
+
+/// Wall-clock time that could not be turned into an instant.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum LocalTimeError {
+    /// The time happens twice. Both readings are given so the client can pick one.
+    Ambiguous {
+        local: NaiveDateTime,
+        time_zone: String,
+        earlier: String,
+        later: String,
+    },
+    Nonexistent { local: NaiveDateTime, time_zone: String },
+}
+
+impl LocalTimeError {
+    pub fn message(&self) -> String {
+        match self {
+            LocalTimeError::Ambiguous { local, time_zone, earlier, later } => format!(
+                "{} happens twice in {} ({} and {}); choose one with ambiguous_time",
+                local, time_zone, earlier, later
+            ),
+            LocalTimeError::Nonexistent { local, time_zone } => format!(
+                "{} does not exist in {} because the clocks skip it; send another time or set nonexistent_time",
+                local, time_zone
+            ),
+        }
+    }
+}
+
+/// Instant of a wall-clock time in `time_zone`, resolving daylight saving changes as asked.
+pub fn resolve_local_time(
+    time_zone: Tz,
+    local: NaiveDateTime,
+    ambiguous: AmbiguousTime,
+    nonexistent: NonexistentTime,
+) -> Result<DateTime, LocalTimeError> {
+    match time_zone.from_local_datetime(&local) {
+        LocalResult::Single(at) => Ok(DateTime::from_chrono(at)),
+        LocalResult::Ambiguous(earlier, later) => match ambiguous {
+            AmbiguousTime::Earlier => Ok(DateTime::from_chrono(earlier)),
+            AmbiguousTime::Later => Ok(DateTime::from_chrono(later)),
+            AmbiguousTime::Reject => Err(LocalTimeError::Ambiguous {
+                local,
+                time_zone: time_zone.name().to_string(),
+                earlier: earlier.to_rfc3339(),
+                later: later.to_rfc3339(),
+            }),
+        },
+        LocalResult::None => {
+            let error = LocalTimeError::Nonexistent {
+                local,
+                time_zone: time_zone.name().to_string(),
+            };
+            if nonexistent == NonexistentTime::Reject {
+                return Err(error);
+            }
+            // Reading the time with the offset from before the jump lands the same
+            // distance past it.
+            let before = time_zone
+                .from_local_datetime(&(local - Duration::days(1)))
+                .earliest()
+                .ok_or(error)?;
+            let offset = Duration::seconds(before.offset().fix().local_minus_utc() as i64);
+            Ok(DateTime::from_chrono((local - offset).and_utc()))
+        }
+    }
+}
+
+/// Start and end of a session on the wall clock of its hall, as RFC 3339 with the offset
+/// in effect at each, so times around a daylight saving change stay unambiguous.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct LocalTimes {
+    pub time_zone: String,
+    pub start: String,
+    pub end: String,
+}
+
+impl LocalTimes {
+    pub fn new(time_zone: Tz, start: DateTime, end: DateTime) -> Self {
+        LocalTimes {
+            time_zone: time_zone.name().to_string(),
+            start: start.to_chrono().with_timezone(&time_zone).to_rfc3339(),
+            end: end.to_chrono().with_timezone(&time_zone).to_rfc3339(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use chrono_tz::Europe::Warsaw;
+    use mongodb::bson::DateTime;
+
+    use super::{resolve_local_time, AmbiguousTime, LocalTimeError, NonexistentTime};
+
+    fn local(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime {
+        DateTime::from_chrono(Utc.with_ymd_and_hms(2024, month, day, hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn ordinary_times_ignore_the_policies() {
+        for (ambiguous, nonexistent) in [
+            (AmbiguousTime::Reject, NonexistentTime::Reject),
+            (AmbiguousTime::Later, NonexistentTime::ShiftForward),
+        ] {
+            let at = resolve_local_time(Warsaw, local(7, 1, 20, 0), ambiguous, nonexistent).unwrap();
+            assert_eq!(at, utc(7, 1, 18, 0));
+        }
+    }
+
+    #[test]
+    fn times_skipped_when_clocks_go_forward_are_rejected_or_shifted() {
+        // Clocks in Warsaw jump from 02:00 to 03:00 on 2024-03-31.
+        let skipped = local(3, 31, 2, 30);
+
+        for ambiguous in [AmbiguousTime::Reject, AmbiguousTime::Earlier, AmbiguousTime::Later] {
+            match resolve_local_time(Warsaw, skipped, ambiguous, NonexistentTime::Reject) {
+                Err(LocalTimeError::Nonexistent { local, time_zone }) => {
+                    assert_eq!(local, skipped);
+                    assert_eq!(time_zone, "Europe/Warsaw");
+                }
+                other => panic!("expected a nonexistent time, got {:?}", other),
+            }
+        }
+
+        let shifted = resolve_local_time(Warsaw, skipped, AmbiguousTime::Reject, NonexistentTime::ShiftForward).unwrap();
+        assert_eq!(shifted, utc(3, 31, 1, 30));
+        assert_eq!(shifted.to_chrono().with_timezone(&Warsaw).naive_local(), local(3, 31, 3, 30));
+    }
+
+    #[test]
+    fn times_repeated_when_clocks_go_back_follow_the_policy() {
+        // Clocks in Warsaw go back from 03:00 to 02:00 on 2024-10-27.
+        let repeated = local(10, 27, 2, 30);
+
+        for nonexistent in [NonexistentTime::Reject, NonexistentTime::ShiftForward] {
+            match resolve_local_time(Warsaw, repeated, AmbiguousTime::Reject, nonexistent) {
+                Err(LocalTimeError::Ambiguous {
+                    local,
+                    time_zone,
+                    earlier,
+                    later,
+                }) => {
+                    assert_eq!(local, repeated);
+                    assert_eq!(time_zone, "Europe/Warsaw");
+                    assert_eq!(earlier, "2024-10-27T02:30:00+02:00");
+                    assert_eq!(later, "2024-10-27T02:30:00+01:00");
+                }
+                other => panic!("expected an ambiguous time, got {:?}", other),
+            }
+        }
+
+        let earlier = resolve_local_time(Warsaw, repeated, AmbiguousTime::Earlier, NonexistentTime::Reject).unwrap();
+        assert_eq!(earlier, utc(10, 27, 0, 30));
+        let later = resolve_local_time(Warsaw, repeated, AmbiguousTime::Later, NonexistentTime::Reject).unwrap();
+        assert_eq!(later, utc(10, 27, 1, 30));
+    }
+}
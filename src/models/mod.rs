@@ -1,3 +1,16 @@
+pub mod api_key_model;
+pub mod booking_model;
 pub mod movie_model;
 pub mod session_model;
-pub mod hall_model;
\ No newline at end of file
+pub mod session_series_model;
+pub mod hall_model;
+pub mod hall_schedule_model;
+pub mod local_time_model;
+pub mod price_list_model;
+pub mod promotion_model;
+pub mod reservation_model;
+pub mod schedule_model;
+pub mod seat_layout_model;
+pub mod user_model;
+pub mod venue_model;
+pub mod ws_message_model;
\ No newline at end of file
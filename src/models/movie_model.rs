@@ -1,13 +1,15 @@
 use serde::{Deserialize, Serialize };
 use mongodb::bson::oid::ObjectId;
+use schemars::JsonSchema;
 
 use crate::utils::serialize_object_id;
 
 use super::{hall_model::Hall, session_model::SessionResponse};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct Movie {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    #[schemars(with = "Option<String>")]
     pub id: Option<ObjectId>,
     pub title: String,
     pub duration: i32,
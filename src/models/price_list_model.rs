@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_object_id, serialize_required_object_id};
+
+use super::{promotion_model::PromotionOutcome, seat_layout_model::SeatCategory};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketType {
+    #[default]
+    Adult,
+    Child,
+    Senior,
+    Student,
+}
+
+/// Base price of one seat category for one ticket type, in minor units of the list currency.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PriceEntry {
+    pub category: SeatCategory,
+    pub ticket_type: TicketType,
+    pub amount: i64,
+}
+
+/// Change a rule makes to the price: `percent` is in whole percent, `amount` in minor units.
+/// Negative values are discounts, positive ones surcharges.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum PriceAdjustment {
+    Percent(i64),
+    Amount(i64),
+}
+
+impl PriceAdjustment {
+    fn apply(self, amount: i64) -> i64 {
+        let adjusted = match self {
+            // Rounded to the nearest minor unit.
+            PriceAdjustment::Percent(percent) => (amount * (100 + percent) + 50).div_euclid(100),
+            PriceAdjustment::Amount(value) => amount + value,
+        };
+        adjusted.max(0)
+    }
+}
+
+/// Adjustment applied to sessions starting on the given weekdays and between `from` and
+/// `until` local time, e.g. a matinee discount or a weekend surcharge. Empty filters match
+/// everything. A window with `from` after `until` runs past midnight, e.g. 22:00-02:00,
+/// and its weekdays are the ones the window opens on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PriceRule {
+    pub name: String,
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveTime>,
+    #[serde(default)]
+    pub categories: Vec<SeatCategory>,
+    #[serde(default)]
+    pub ticket_types: Vec<TicketType>,
+    pub adjustment: PriceAdjustment,
+}
+
+impl PriceRule {
+    fn matches(&self, category: SeatCategory, ticket_type: TicketType, start: NaiveDateTime) -> bool {
+        let time = start.time();
+        let (in_window, weekday) = match (self.from, self.until) {
+            // Past midnight the session belongs to the window opened the evening before.
+            (Some(from), Some(until)) if from > until && time < until => (true, start.weekday().pred()),
+            (Some(from), Some(until)) if from > until => (time >= from, start.weekday()),
+            (from, until) => (
+                from.is_none_or(|from| time >= from) && until.is_none_or(|until| time < until),
+                start.weekday(),
+            ),
+        };
+
+        (self.weekdays.is_empty() || self.weekdays.contains(&weekday))
+            && in_window
+            && (self.categories.is_empty() || self.categories.contains(&category))
+            && (self.ticket_types.is_empty() || self.ticket_types.contains(&ticket_type))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PriceList {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    /// ISO 4217 code every amount in the list is given in.
+    pub currency: String,
+    pub prices: Vec<PriceEntry>,
+    /// Applied in order, each one on top of the previous result.
+    #[serde(default)]
+    pub rules: Vec<PriceRule>,
+}
+
+/// Price of a single seat once the rules have been applied.
+#[derive(Debug, Clone)]
+pub struct SeatPrice {
+    pub base_amount: i64,
+    pub amount: i64,
+    pub applied_rules: Vec<String>,
+}
+
+impl PriceList {
+    /// Checks that the currency looks like an ISO 4217 code, that amounts are not negative
+    /// and that every category and ticket type pair is priced at most once.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.currency.len() != 3 || !self.currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("invalid currency code: {}", self.currency));
+        }
+
+        let mut priced = HashSet::new();
+        for entry in &self.prices {
+            if entry.amount < 0 {
+                return Err(format!("negative price for {:?}/{:?}", entry.category, entry.ticket_type));
+            }
+            if !priced.insert((entry.category, entry.ticket_type)) {
+                return Err(format!("duplicate price for {:?}/{:?}", entry.category, entry.ticket_type));
+            }
+        }
+
+        for rule in &self.rules {
+            if let (Some(from), Some(until)) = (rule.from, rule.until) {
+                if from == until {
+                    return Err(format!("rule {} starts and ends at the same time", rule.name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prices a seat for a session starting at `start` local time. Returns `None` when the
+    /// list has no price for the category and ticket type.
+    pub fn price(&self, category: SeatCategory, ticket_type: TicketType, start: NaiveDateTime) -> Option<SeatPrice> {
+        let base_amount = self
+            .prices
+            .iter()
+            .find(|entry| entry.category == category && entry.ticket_type == ticket_type)?
+            .amount;
+
+        let mut amount = base_amount;
+        let mut applied_rules = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(category, ticket_type, start)) {
+            amount = rule.adjustment.apply(amount);
+            applied_rules.push(rule.name.clone());
+        }
+
+        Some(SeatPrice {
+            base_amount,
+            amount,
+            applied_rules,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuoteSeat {
+    pub row: String,
+    pub number: u32,
+    #[serde(default)]
+    pub ticket_type: TicketType,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct QuoteRequest {
+    pub seats: Vec<QuoteSeat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promo_code: Option<String>,
+    /// Buyer email, lets the quote check the per-customer limit of the promo code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuoteLine {
+    pub row: String,
+    pub number: u32,
+    pub category: SeatCategory,
+    pub ticket_type: TicketType,
+    pub base_amount: i64,
+    pub amount: i64,
+    pub applied_rules: Vec<String>,
+    /// Taken off `amount` by the promo code.
+    #[serde(default)]
+    pub discount: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Quote {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub session_id: ObjectId,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub price_list_id: ObjectId,
+    pub currency: String,
+    pub lines: Vec<QuoteLine>,
+    pub subtotal: i64,
+    pub discount: i64,
+    pub total: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub promotion: Option<PromotionOutcome>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+    use super::{PriceAdjustment, PriceEntry, PriceList, PriceRule, TicketType};
+    use crate::models::seat_layout_model::SeatCategory;
+
+    fn at(date: (i32, u32, u32), hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    fn rule(name: &str, adjustment: PriceAdjustment) -> PriceRule {
+        PriceRule {
+            name: name.to_string(),
+            weekdays: Vec::new(),
+            from: None,
+            until: None,
+            categories: Vec::new(),
+            ticket_types: Vec::new(),
+            adjustment,
+        }
+    }
+
+    fn price_list(amount: i64, rules: Vec<PriceRule>) -> PriceList {
+        PriceList {
+            id: None,
+            name: "Standard".to_string(),
+            currency: "PLN".to_string(),
+            prices: vec![PriceEntry {
+                category: SeatCategory::Standard,
+                ticket_type: TicketType::Adult,
+                amount,
+            }],
+            rules,
+        }
+    }
+
+    fn amount(list: &PriceList, start: NaiveDateTime) -> i64 {
+        list.price(SeatCategory::Standard, TicketType::Adult, start).unwrap().amount
+    }
+
+    // 2024-03-15 is a Friday.
+    const FRIDAY: (i32, u32, u32) = (2024, 3, 15);
+    const SATURDAY: (i32, u32, u32) = (2024, 3, 16);
+
+    #[test]
+    fn percent_adjustments_round_to_the_nearest_minor_unit_and_fixed_ones_add_up() {
+        assert_eq!(PriceAdjustment::Percent(-25).apply(1000), 750);
+        assert_eq!(PriceAdjustment::Percent(10).apply(995), 1095);
+        assert_eq!(PriceAdjustment::Percent(-33).apply(1001), 671);
+        assert_eq!(PriceAdjustment::Amount(-150).apply(1000), 850);
+        assert_eq!(PriceAdjustment::Amount(200).apply(1000), 1200);
+    }
+
+    #[test]
+    fn adjustments_never_go_below_zero() {
+        assert_eq!(PriceAdjustment::Amount(-1500).apply(1000), 0);
+        assert_eq!(PriceAdjustment::Percent(-150).apply(1000), 0);
+
+        let list = price_list(
+            1000,
+            vec![rule("free", PriceAdjustment::Amount(-2000)), rule("fee", PriceAdjustment::Amount(100))],
+        );
+        assert_eq!(amount(&list, at(FRIDAY, 18, 0)), 100);
+    }
+
+    #[test]
+    fn rules_apply_in_order_on_top_of_each_other() {
+        let percent_first = price_list(
+            1000,
+            vec![rule("matinee", PriceAdjustment::Percent(-50)), rule("3d", PriceAdjustment::Amount(300))],
+        );
+        let amount_first = price_list(
+            1000,
+            vec![rule("3d", PriceAdjustment::Amount(300)), rule("matinee", PriceAdjustment::Percent(-50))],
+        );
+
+        let price = percent_first.price(SeatCategory::Standard, TicketType::Adult, at(FRIDAY, 12, 0)).unwrap();
+        assert_eq!(price.base_amount, 1000);
+        assert_eq!(price.amount, 800);
+        assert_eq!(price.applied_rules, ["matinee", "3d"]);
+        assert_eq!(amount(&amount_first, at(FRIDAY, 12, 0)), 650);
+    }
+
+    #[test]
+    fn only_matching_rules_apply() {
+        let mut matinee = rule("matinee", PriceAdjustment::Percent(-20));
+        matinee.until = time(14, 0);
+        let mut weekend = rule("weekend", PriceAdjustment::Amount(200));
+        weekend.weekdays = vec![Weekday::Sat, Weekday::Sun];
+        let mut premium = rule("premium", PriceAdjustment::Amount(500));
+        premium.categories = vec![SeatCategory::Premium];
+        let list = price_list(1000, vec![matinee, weekend, premium]);
+
+        assert_eq!(amount(&list, at(FRIDAY, 13, 59)), 800);
+        assert_eq!(amount(&list, at(FRIDAY, 14, 0)), 1000);
+        assert_eq!(amount(&list, at(SATURDAY, 12, 0)), 1000);
+        assert_eq!(amount(&list, at(SATURDAY, 18, 0)), 1200);
+    }
+
+    #[test]
+    fn windows_past_midnight_belong_to_the_evening_they_start() {
+        let mut late = rule("late night", PriceAdjustment::Percent(-30));
+        late.weekdays = vec![Weekday::Fri];
+        late.from = time(22, 0);
+        late.until = time(2, 0);
+        let list = price_list(1000, vec![late]);
+        assert!(list.validate().is_ok());
+
+        assert_eq!(amount(&list, at(FRIDAY, 21, 59)), 1000);
+        assert_eq!(amount(&list, at(FRIDAY, 22, 0)), 700);
+        assert_eq!(amount(&list, at(FRIDAY, 23, 30)), 700);
+        assert_eq!(amount(&list, at(SATURDAY, 1, 30)), 700);
+        assert_eq!(amount(&list, at(SATURDAY, 2, 0)), 1000);
+        assert_eq!(amount(&list, at(SATURDAY, 23, 0)), 1000);
+        assert_eq!(amount(&list, at(FRIDAY, 1, 30)), 1000);
+    }
+
+    #[test]
+    fn empty_windows_are_rejected() {
+        let mut never = rule("never", PriceAdjustment::Amount(-100));
+        never.from = time(10, 0);
+        never.until = time(10, 0);
+
+        assert!(price_list(1000, vec![never]).validate().is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use chrono::{DateTime as ChronoDateTime, Datelike, NaiveDateTime, Utc, Weekday};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_object_id, serialize_object_ids, serialize_required_object_id};
+
+use super::session_model::Session;
+
+/// What a code takes off the order: `percent` is in whole percent of every ticket,
+/// `amount` in minor units of the promotion currency, spread over the tickets.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum PromotionDiscount {
+    Percent(i64),
+    Amount(i64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Promotion {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    /// Code customers type at checkout. Stored upper case and matched case-insensitively.
+    pub code: String,
+    pub name: String,
+    pub discount: PromotionDiscount,
+    /// ISO 4217 code of a fixed-amount discount. Only orders in the same currency qualify.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<ChronoDateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<ChronoDateTime<Utc>>,
+    /// Total number of bookings the code can be used on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses: Option<u32>,
+    /// Number of bookings a single buyer email can use the code on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_uses_per_customer: Option<u32>,
+    /// Smallest order subtotal the code can be used on, in minor units of the order currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_spend: Option<i64>,
+    /// Bookings currently using the code. Kept by the server, cancelled and refunded
+    /// bookings give their use back.
+    #[serde(default)]
+    pub uses: u32,
+    /// Restrictions on the session. Empty lists match everything.
+    #[serde(default, serialize_with = "serialize_object_ids")]
+    pub movie_ids: Vec<ObjectId>,
+    #[serde(default, serialize_with = "serialize_object_ids")]
+    pub hall_ids: Vec<ObjectId>,
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionRejection {
+    UnknownCode,
+    NotYetValid,
+    Expired,
+    UsageLimitReached,
+    CustomerLimitReached,
+    MovieNotEligible,
+    HallNotEligible,
+    WeekdayNotEligible,
+    CurrencyMismatch,
+    BelowMinimumSpend,
+}
+
+impl PromotionRejection {
+    pub fn message(self) -> &'static str {
+        match self {
+            PromotionRejection::UnknownCode => "Promo code does not exist",
+            PromotionRejection::NotYetValid => "Promo code is not valid yet",
+            PromotionRejection::Expired => "Promo code has expired",
+            PromotionRejection::UsageLimitReached => "Promo code has been used up",
+            PromotionRejection::CustomerLimitReached => "Promo code was already used the maximum number of times by this customer",
+            PromotionRejection::MovieNotEligible => "Promo code is not valid for this movie",
+            PromotionRejection::HallNotEligible => "Promo code is not valid in this hall",
+            PromotionRejection::WeekdayNotEligible => "Promo code is not valid on this day",
+            PromotionRejection::CurrencyMismatch => "Promo code is not valid for this currency",
+            PromotionRejection::BelowMinimumSpend => "Order total is below the minimum spend of the promo code",
+        }
+    }
+}
+
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+/// Buyers are told apart by their email address.
+pub fn normalize_customer(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+impl Promotion {
+    /// Checks the code format, the discount and that the validity window and limits make sense.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.code.is_empty()
+            || !self
+                .code
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(format!("invalid promo code: {}", self.code));
+        }
+
+        match self.discount {
+            PromotionDiscount::Percent(percent) if !(1..=100).contains(&percent) => {
+                return Err(format!("percent discount must be between 1 and 100, got {}", percent));
+            }
+            PromotionDiscount::Amount(amount) if amount <= 0 => {
+                return Err(format!("fixed discount must be positive, got {}", amount));
+            }
+            PromotionDiscount::Amount(_) if self.currency.is_none() => {
+                return Err("fixed discount needs a currency".to_string());
+            }
+            _ => {}
+        }
+        if let Some(currency) = &self.currency {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(format!("invalid currency code: {}", currency));
+            }
+        }
+
+        if let (Some(from), Some(until)) = (self.valid_from, self.valid_until) {
+            if from >= until {
+                return Err("promotion ends before it starts".to_string());
+            }
+        }
+        if self.max_uses == Some(0) || self.max_uses_per_customer == Some(0) {
+            return Err("usage limits must be at least 1".to_string());
+        }
+        if self.min_spend.is_some_and(|min_spend| min_spend < 0) {
+            return Err("minimum spend cannot be negative".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether the code can be used on an order of `subtotal` for the session. The
+    /// per-customer limit needs the redemption counters and is checked by the repository.
+    /// `local_start` is the session start in cinema time.
+    pub fn check(
+        &self,
+        session: &Session,
+        local_start: NaiveDateTime,
+        currency: &str,
+        subtotal: i64,
+        now: ChronoDateTime<Utc>,
+    ) -> Result<(), PromotionRejection> {
+        if self.valid_from.is_some_and(|from| now < from) {
+            return Err(PromotionRejection::NotYetValid);
+        }
+        if self.valid_until.is_some_and(|until| now >= until) {
+            return Err(PromotionRejection::Expired);
+        }
+        if !self.movie_ids.is_empty() && !session.movie_id.is_some_and(|id| self.movie_ids.contains(&id)) {
+            return Err(PromotionRejection::MovieNotEligible);
+        }
+        if !self.hall_ids.is_empty() && !session.hall_id.is_some_and(|id| self.hall_ids.contains(&id)) {
+            return Err(PromotionRejection::HallNotEligible);
+        }
+        if !self.weekdays.is_empty() && !self.weekdays.contains(&local_start.weekday()) {
+            return Err(PromotionRejection::WeekdayNotEligible);
+        }
+        if self.currency.as_deref().is_some_and(|own| own != currency) {
+            return Err(PromotionRejection::CurrencyMismatch);
+        }
+        if self.min_spend.is_some_and(|min_spend| subtotal < min_spend) {
+            return Err(PromotionRejection::BelowMinimumSpend);
+        }
+        if self.max_uses.is_some_and(|max_uses| self.uses >= max_uses) {
+            return Err(PromotionRejection::UsageLimitReached);
+        }
+
+        Ok(())
+    }
+
+    /// Splits the discount over the ticket prices. A fixed amount is spread in proportion
+    /// to the prices and never makes a ticket cost less than nothing.
+    pub fn discounts(&self, amounts: &[i64]) -> Vec<i64> {
+        match self.discount {
+            // Rounded to the nearest minor unit.
+            PromotionDiscount::Percent(percent) => amounts
+                .iter()
+                .map(|amount| (amount * percent + 50).div_euclid(100))
+                .collect(),
+            PromotionDiscount::Amount(value) => {
+                let subtotal: i64 = amounts.iter().sum();
+                if subtotal == 0 {
+                    return vec![0; amounts.len()];
+                }
+                let value = value.min(subtotal);
+
+                let mut discounts: Vec<i64> = amounts.iter().map(|amount| amount * value / subtotal).collect();
+                let mut remainder = value - discounts.iter().sum::<i64>();
+                for (discount, amount) in discounts.iter_mut().zip(amounts) {
+                    let extra = remainder.min(amount - *discount);
+                    *discount += extra;
+                    remainder -= extra;
+                }
+                discounts
+            }
+        }
+    }
+}
+
+/// Times one buyer has used one code, for the per-customer limit.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PromotionRedemption {
+    pub promotion_id: ObjectId,
+    pub customer: String,
+    pub uses: u32,
+}
+
+/// Promotion a booking was sold with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppliedPromotion {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub promotion_id: ObjectId,
+    pub code: String,
+    /// Amount taken off the booking, in minor units of its currency.
+    pub discount: i64,
+}
+
+/// What happened to the promo code sent with a quote or booking.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PromotionOutcome {
+    Applied(AppliedPromotion),
+    Rejected {
+        code: String,
+        reason: PromotionRejection,
+        message: &'static str,
+    },
+}
+
+impl PromotionOutcome {
+    pub fn rejected(code: String, reason: PromotionRejection) -> Self {
+        PromotionOutcome::Rejected {
+            code,
+            reason,
+            message: reason.message(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
+    use mongodb::bson::{oid::ObjectId, DateTime};
+
+    use super::{Promotion, PromotionDiscount, PromotionRejection};
+    use crate::models::session_model::Session;
+
+    fn promotion(discount: PromotionDiscount) -> Promotion {
+        Promotion {
+            id: None,
+            code: "SUMMER".to_string(),
+            name: "Summer".to_string(),
+            discount,
+            currency: None,
+            valid_from: None,
+            valid_until: None,
+            max_uses: None,
+            max_uses_per_customer: None,
+            min_spend: None,
+            uses: 0,
+            movie_ids: Vec::new(),
+            hall_ids: Vec::new(),
+            weekdays: Vec::new(),
+        }
+    }
+
+    fn session() -> Session {
+        Session {
+            id: None,
+            title: None,
+            movie_id: Some(ObjectId::new()),
+            hall_id: Some(ObjectId::new()),
+            price_list_id: None,
+            series_id: None,
+            start: DateTime::from_millis(0),
+            end: DateTime::from_millis(2 * 60 * 60 * 1000),
+        }
+    }
+
+    fn local_start() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 7, 5).unwrap().and_hms_opt(18, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn codes_are_only_valid_within_their_window() {
+        let mut summer = promotion(PromotionDiscount::Percent(10));
+        summer.valid_from = Some(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+        summer.valid_until = Some(Utc.with_ymd_and_hms(2024, 9, 1, 0, 0, 0).unwrap());
+        let check = |now| summer.check(&session(), local_start(), "PLN", 1000, now);
+
+        assert_eq!(
+            check(Utc.with_ymd_and_hms(2024, 5, 31, 23, 59, 59).unwrap()),
+            Err(PromotionRejection::NotYetValid)
+        );
+        assert_eq!(check(Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap()), Ok(()));
+        assert_eq!(check(Utc.with_ymd_and_hms(2024, 8, 31, 23, 59, 59).unwrap()), Ok(()));
+        assert_eq!(
+            check(Utc.with_ymd_and_hms(2024, 9, 1, 0, 0, 0).unwrap()),
+            Err(PromotionRejection::Expired)
+        );
+    }
+
+    #[test]
+    fn codes_stop_working_once_used_up() {
+        let mut limited = promotion(PromotionDiscount::Percent(10));
+        limited.max_uses = Some(2);
+        limited.uses = 1;
+        assert_eq!(limited.check(&session(), local_start(), "PLN", 1000, Utc::now()), Ok(()));
+
+        limited.uses = 2;
+        assert_eq!(
+            limited.check(&session(), local_start(), "PLN", 1000, Utc::now()),
+            Err(PromotionRejection::UsageLimitReached)
+        );
+
+        limited.max_uses = Some(0);
+        assert!(limited.validate().is_err());
+    }
+
+    #[test]
+    fn orders_below_the_minimum_spend_are_rejected() {
+        let mut minimum = promotion(PromotionDiscount::Amount(500));
+        minimum.currency = Some("PLN".to_string());
+        minimum.min_spend = Some(3000);
+        assert!(minimum.validate().is_ok());
+
+        assert_eq!(
+            minimum.check(&session(), local_start(), "PLN", 2999, Utc::now()),
+            Err(PromotionRejection::BelowMinimumSpend)
+        );
+        assert_eq!(minimum.check(&session(), local_start(), "PLN", 3000, Utc::now()), Ok(()));
+        assert_eq!(
+            minimum.check(&session(), local_start(), "EUR", 3000, Utc::now()),
+            Err(PromotionRejection::CurrencyMismatch)
+        );
+
+        minimum.min_spend = Some(-1);
+        assert!(minimum.validate().is_err());
+    }
+
+    #[test]
+    fn percent_discounts_round_each_ticket() {
+        let discounts = promotion(PromotionDiscount::Percent(15)).discounts(&[999, 1000, 0]);
+
+        assert_eq!(discounts, [150, 150, 0]);
+    }
+
+    #[test]
+    fn fixed_discounts_are_spread_over_the_tickets() {
+        let discounts = promotion(PromotionDiscount::Amount(100)).discounts(&[333, 333, 334]);
+
+        assert_eq!(discounts, [34, 33, 33]);
+    }
+
+    #[test]
+    fn discounts_larger_than_the_total_make_the_order_free() {
+        let amounts = [1000, 500];
+
+        assert_eq!(promotion(PromotionDiscount::Amount(5000)).discounts(&amounts), amounts);
+        assert_eq!(promotion(PromotionDiscount::Percent(100)).discounts(&amounts), amounts);
+        assert_eq!(promotion(PromotionDiscount::Amount(500)).discounts(&[0, 0]), [0, 0]);
+    }
+}
@@ -0,0 +1,119 @@
+use mongodb::bson::serde_helpers::serialize_bson_datetime_as_rfc3339_string;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_object_id, serialize_required_object_id};
+
+use super::seat_layout_model::SeatCategory;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeatRef {
+    pub row: String,
+    pub number: u32,
+}
+
+/// One seat taken for a session. Seats held together share a `hold_id`.
+/// A hold is temporary while `expires_at` is set and becomes permanent once confirmed.
+/// Only whoever placed the hold can release it or book its seats.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeatReservation {
+    #[serde(
+        rename = "_id",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id"
+    )]
+    pub id: Option<ObjectId>,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub session_id: ObjectId,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hold_id: ObjectId,
+    pub row: String,
+    pub number: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime>,
+    /// User who placed the hold.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id"
+    )]
+    pub user_id: Option<ObjectId>,
+    /// Partner key the hold was placed with.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_object_id"
+    )]
+    pub api_key_id: Option<ObjectId>,
+}
+
+impl SeatReservation {
+    pub fn seat(&self) -> SeatRef {
+        SeatRef {
+            row: self.row.clone(),
+            number: self.number,
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime) -> bool {
+        self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+
+    pub fn status(&self) -> SeatStatus {
+        if self.expires_at.is_some() {
+            SeatStatus::Held
+        } else {
+            SeatStatus::Booked
+        }
+    }
+}
+
+/// Outcome of storing a hold.
+#[derive(Debug)]
+pub struct HoldInsertion {
+    /// `false` when a seat is taken by an active reservation. Nothing of the hold is stored then.
+    pub placed: bool,
+    /// Expired holds removed to free the requested seats. Their bookings still have to be
+    /// cancelled, as the sweeper would have done.
+    pub displaced: Vec<SeatReservation>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatStatus {
+    Available,
+    Held,
+    Booked,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeatHoldRequest {
+    pub seats: Vec<SeatRef>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SeatHoldResponse {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hold_id: ObjectId,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub session_id: ObjectId,
+    pub seats: Vec<SeatRef>,
+    #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
+    pub expires_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SeatAvailability {
+    pub row: String,
+    pub number: u32,
+    pub category: SeatCategory,
+    pub status: SeatStatus,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct SeatStatusChange {
+    pub row: String,
+    pub number: u32,
+    pub status: SeatStatus,
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_datetime, serialize_required_object_id};
+
+use super::{hall_schedule_model::OpeningHours, session_model::SessionResponse};
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlanMovie {
+    pub movie_id: ObjectId,
+    /// Screenings wanted over the week. Without it the movie fills whatever time is left.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenings: Option<u32>,
+    /// Relative priority. A movie with twice the weight gets about twice the screenings
+    /// and the first pick of the larger halls.
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlanHall {
+    pub hall_id: ObjectId,
+    /// Hours to plan within. Without them the hall's stored opening hours are used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opening_hours: Vec<OpeningHours>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanMode {
+    /// Only returns the proposed sessions.
+    #[default]
+    Preview,
+    /// Also stores them.
+    Commit,
+}
+
+fn default_slot_minutes() -> u32 {
+    5
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SchedulePlanRequest {
+    /// Local date the planned week starts on. Seven days are planned from it.
+    pub week_start: NaiveDate,
+    pub movies: Vec<PlanMovie>,
+    pub halls: Vec<PlanHall>,
+    /// Price list given to every planned session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_list_id: Option<ObjectId>,
+    #[serde(default)]
+    pub mode: PlanMode,
+    /// Sessions start on multiples of this many minutes after the hall opens.
+    #[serde(default = "default_slot_minutes")]
+    pub slot_minutes: u32,
+}
+
+impl SchedulePlanRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.movies.is_empty() || self.halls.is_empty() {
+            return Err("plan needs at least one movie and one hall".to_string());
+        }
+        if !(1..=60).contains(&self.slot_minutes) {
+            return Err(format!("slot must be between 1 and 60 minutes, got {}", self.slot_minutes));
+        }
+
+        let mut movie_ids = HashSet::new();
+        for movie in &self.movies {
+            if !movie_ids.insert(movie.movie_id) {
+                return Err(format!("movie {} is listed twice", movie.movie_id));
+            }
+            if !(movie.weight > 0.0 && movie.weight.is_finite()) {
+                return Err(format!("weight of movie {} must be positive", movie.movie_id));
+            }
+        }
+
+        let mut hall_ids = HashSet::new();
+        for hall in &self.halls {
+            if !hall_ids.insert(hall.hall_id) {
+                return Err(format!("hall {} is listed twice", hall.hall_id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlannedSession {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub movie_id: ObjectId,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hall_id: ObjectId,
+    pub title: String,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub start: DateTime,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub end: DateTime,
+}
+
+/// Movie that got fewer screenings than asked for.
+#[derive(Serialize, Debug, Clone)]
+pub struct PlanShortfall {
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub movie_id: ObjectId,
+    pub requested: u32,
+    pub scheduled: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SchedulePlan {
+    pub mode: PlanMode,
+    pub sessions: Vec<PlannedSession>,
+    pub shortfalls: Vec<PlanShortfall>,
+    /// Sessions stored in commit mode.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub created: Vec<SessionResponse>,
+    /// Planned sessions whose slot was taken before they could be stored.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<PlannedSession>,
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatCategory {
+    Standard,
+    Premium,
+    Wheelchair,
+    Companion,
+}
+
+/// One position in a row, read from the left wall to the right wall.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LayoutSlot {
+    Seat { number: u32, category: SeatCategory },
+    /// Empty position, e.g. a missing seat or a pillar.
+    Gap,
+    Aisle,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SeatRow {
+    pub label: String,
+    pub slots: Vec<LayoutSlot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Seat {
+    pub row: String,
+    pub number: u32,
+    pub category: SeatCategory,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SeatLayout {
+    pub rows: Vec<SeatRow>,
+}
+
+impl SeatLayout {
+    pub fn seats(&self) -> impl Iterator<Item = Seat> + '_ {
+        self.rows.iter().flat_map(|row| {
+            row.slots.iter().filter_map(move |slot| match slot {
+                LayoutSlot::Seat { number, category } => Some(Seat {
+                    row: row.label.clone(),
+                    number: *number,
+                    category: *category,
+                }),
+                _ => None,
+            })
+        })
+    }
+
+    pub fn seat(&self, row: &str, number: u32) -> Option<Seat> {
+        self.seats().find(|seat| seat.row == row && seat.number == number)
+    }
+
+    pub fn seat_count(&self) -> u32 {
+        self.seats().count() as u32
+    }
+
+    /// Checks that rows and seats are uniquely labelled and that the layout
+    /// holds exactly `capacity` seats.
+    pub fn validate(&self, capacity: u32) -> Result<(), String> {
+        let mut row_labels = HashSet::new();
+        for row in &self.rows {
+            if row.label.trim().is_empty() {
+                return Err("row label must not be empty".to_string());
+            }
+            if !row_labels.insert(row.label.as_str()) {
+                return Err(format!("duplicate row label: {}", row.label));
+            }
+
+            let mut numbers = HashSet::new();
+            for slot in &row.slots {
+                if let LayoutSlot::Seat { number, .. } = slot {
+                    if !numbers.insert(*number) {
+                        return Err(format!("duplicate seat {}{}", row.label, number));
+                    }
+                }
+            }
+        }
+
+        let seat_count = self.seat_count();
+        if seat_count != capacity {
+            return Err(format!(
+                "layout has {} seats but hall capacity is {}",
+                seat_count, capacity
+            ));
+        }
+
+        Ok(())
+    }
+}
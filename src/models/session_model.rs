@@ -1,11 +1,17 @@
-use ::chrono::{DateTime as ChronoDateTime, Utc};
+use ::chrono::{DateTime as ChronoDateTime, NaiveDateTime, Utc};
 use mongodb::bson::serde_helpers::serialize_bson_datetime_as_rfc3339_string;
 use mongodb::bson::{oid::ObjectId, DateTime};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::utils::serialize_object_id;
 
-use super::{hall_model::Hall, movie_model::Movie};
+use super::{
+    hall_model::Hall,
+    local_time_model::{AmbiguousTime, LocalTimes, NonexistentTime},
+    movie_model::Movie,
+    venue_model::VenueFilter,
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Session {
@@ -26,33 +32,67 @@ pub struct Session {
         skip_serializing_if = "Option::is_none"
     )]
     pub hall_id: Option<ObjectId>,
+    #[serde(
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub price_list_id: Option<ObjectId>,
+    /// Series the session was generated from.
+    #[serde(
+        default,
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub series_id: Option<ObjectId>,
     pub start: DateTime,
     pub end: DateTime,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct SessionResponse {
     #[serde(
         rename = "_id",
         skip_serializing_if = "Option::is_none",
         serialize_with = "serialize_object_id"
     )]
+    #[schemars(with = "Option<String>")]
     pub id: Option<ObjectId>,
     pub title: Option<String>,
     #[serde(
         serialize_with = "serialize_object_id",
         skip_serializing_if = "Option::is_none"
     )]
+    #[schemars(with = "Option<String>")]
     pub movie_id: Option<ObjectId>,
     #[serde(
         serialize_with = "serialize_object_id",
         skip_serializing_if = "Option::is_none"
     )]
+    #[schemars(with = "Option<String>")]
     pub hall_id: Option<ObjectId>,
+    #[serde(
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub price_list_id: Option<ObjectId>,
+    /// Series the session was generated from.
+    #[serde(
+        default,
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub series_id: Option<ObjectId>,
     #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
+    #[schemars(with = "String")]
     pub start: DateTime,
     #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
+    #[schemars(with = "String")]
     pub end: DateTime,
+    /// Start and end on the hall's wall clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<LocalTimes>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -74,28 +114,82 @@ pub struct SessionDetail {
         skip_serializing_if = "Option::is_none"
     )]
     pub hall_id: Option<ObjectId>,
+    #[serde(
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub price_list_id: Option<ObjectId>,
+    /// Series the session was generated from.
+    #[serde(
+        default,
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub series_id: Option<ObjectId>,
     #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
     pub start: DateTime,
     #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
     pub end: DateTime,
+    /// Start and end on the hall's wall clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<LocalTimes>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub movie: Option<Movie>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hall: Option<Hall>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct SessionUpdate {
+    #[schemars(with = "Option<String>")]
     pub movie_id: Option<ObjectId>,
+    #[schemars(with = "Option<String>")]
     pub hall_id: Option<ObjectId>,
+    #[schemars(with = "Option<String>")]
+    pub price_list_id: Option<ObjectId>,
     pub title: Option<String>,
     pub start: Option<ChronoDateTime<Utc>>,
     pub end: Option<ChronoDateTime<Utc>>,
+    /// Start on the hall's wall clock, instead of `start`.
+    #[serde(default)]
+    pub local_start: Option<NaiveDateTime>,
+    /// End on the hall's wall clock, instead of `end`.
+    #[serde(default)]
+    pub local_end: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub ambiguous_time: AmbiguousTime,
+    #[serde(default)]
+    pub nonexistent_time: NonexistentTime,
     pub poster: Option<String>,
 }
 
+/// Filters accepted by the session listing.
+#[derive(Deserialize, Debug, Default)]
+pub struct SessionListQuery {
+    #[serde(flatten)]
+    pub venue: VenueFilter,
+    /// `today` or a `YYYY-MM-DD` date, matched against each session's local start.
+    pub date: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SessionDeleteResponse {
     pub message: String,
     pub id: String,
 }
+
+impl From<Session> for SessionResponse {
+    fn from(session: Session) -> Self {
+        SessionResponse {
+            id: session.id,
+            title: session.title,
+            movie_id: session.movie_id,
+            hall_id: session.hall_id,
+            price_list_id: session.price_list_id,
+            series_id: session.series_id,
+            start: session.start,
+            end: session.end,
+            local: None,
+        }
+    }
+}
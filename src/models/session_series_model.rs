@@ -0,0 +1,288 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_object_id, serialize_required_object_id};
+
+use super::session_model::SessionResponse;
+
+/// Most occurrences a single series can expand into.
+pub const MAX_OCCURRENCES: u32 = 366;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+/// Subset of an iCalendar RRULE: `FREQ`, `INTERVAL`, `BYDAY`, `UNTIL` and `COUNT`, plus
+/// `EXDATE`-like exceptions. At least one of `until` and `count` is required.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    /// Every how many days or weeks the series repeats.
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// Weekdays the series runs on. A weekly rule without them repeats on the weekday of
+    /// the first date, a daily rule without them runs every day.
+    #[serde(default)]
+    pub by_day: Vec<Weekday>,
+    /// Last local date an occurrence may fall on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// Local dates the series skips, e.g. a holiday. As with `EXDATE`, they still count
+    /// towards `count`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<NaiveDate>,
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+impl RecurrenceRule {
+    pub fn validate(&self, first: NaiveDate) -> Result<(), String> {
+        if self.interval == 0 {
+            return Err("interval must be at least 1".to_string());
+        }
+        match (self.until, self.count) {
+            (None, None) => return Err("rule needs an until date or a count".to_string()),
+            (Some(until), _) if until < first => {
+                return Err("rule ends before the first occurrence".to_string());
+            }
+            (_, Some(count)) if count == 0 || count > MAX_OCCURRENCES => {
+                return Err(format!("count must be between 1 and {}", MAX_OCCURRENCES));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn matches(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        let interval = self.interval as i64;
+        match self.frequency {
+            Frequency::Daily => {
+                (date - first).num_days() % interval == 0
+                    && (self.by_day.is_empty() || self.by_day.contains(&date.weekday()))
+            }
+            Frequency::Weekly => {
+                let weeks = (week_start(date) - week_start(first)).num_days() / 7;
+                let weekday_matches = if self.by_day.is_empty() {
+                    date.weekday() == first.weekday()
+                } else {
+                    self.by_day.contains(&date.weekday())
+                };
+                weeks % interval == 0 && weekday_matches
+            }
+        }
+    }
+
+    /// Local dates of the occurrences, in order, starting at `first`.
+    pub fn dates(&self, first: NaiveDate) -> Vec<NaiveDate> {
+        let limit = self.count.unwrap_or(MAX_OCCURRENCES).min(MAX_OCCURRENCES) as usize;
+
+        let mut dates = Vec::new();
+        let mut date = first;
+        while dates.len() < limit && self.until.is_none_or(|until| date <= until) {
+            if self.matches(first, date) {
+                dates.push(date);
+            }
+            // A rule without `until` still stops once a year's worth of weeks has been
+            // looked at without reaching the count.
+            if (date - first).num_days() > MAX_OCCURRENCES as i64 * 7 * self.interval as i64 {
+                break;
+            }
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        dates.retain(|date| !self.exceptions.contains(date));
+        dates
+    }
+}
+
+/// A movie played in the same hall at the same local time on a recurring set of days.
+/// Each occurrence is stored as an ordinary session pointing back at the series.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionSeries {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub movie_id: ObjectId,
+    #[serde(serialize_with = "serialize_required_object_id")]
+    pub hall_id: ObjectId,
+    #[serde(
+        default,
+        serialize_with = "serialize_object_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub price_list_id: Option<ObjectId>,
+    /// Local date of the first occurrence.
+    pub start_date: NaiveDate,
+    /// Local time every occurrence starts at.
+    pub start_time: NaiveTime,
+    pub rule: RecurrenceRule,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OccurrenceConflictReason {
+    HallUnavailable,
+    OutsideOpeningHours,
+    /// A holiday, private event or maintenance closure of the hall.
+    HallClosed,
+    /// The start time does not exist on that date, e.g. during a daylight saving jump.
+    InvalidLocalTime,
+    HasCheckedInTickets,
+}
+
+/// Occurrence that could not be scheduled, moved or cancelled.
+#[derive(Serialize, Debug, Clone)]
+pub struct OccurrenceConflict {
+    pub date: NaiveDate,
+    #[serde(serialize_with = "serialize_object_id", skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<ObjectId>,
+    pub reason: OccurrenceConflictReason,
+}
+
+/// What creating or editing a series did to its future sessions.
+#[derive(Serialize, Debug)]
+pub struct SessionSeriesChanges {
+    pub series: SessionSeries,
+    pub created: Vec<SessionResponse>,
+    pub updated: Vec<SessionResponse>,
+    pub cancelled: Vec<SessionResponse>,
+    pub conflicts: Vec<OccurrenceConflict>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionSeriesDetail {
+    #[serde(flatten)]
+    pub series: SessionSeries,
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime, Timelike, Weekday};
+    use chrono_tz::Europe::Warsaw;
+
+    use super::{Frequency, RecurrenceRule, MAX_OCCURRENCES};
+    use crate::settings::instant_in;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn rule(frequency: Frequency) -> RecurrenceRule {
+        RecurrenceRule {
+            frequency,
+            interval: 1,
+            by_day: Vec::new(),
+            until: None,
+            count: None,
+            exceptions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn occurrences_stop_at_the_until_date() {
+        let mut daily = rule(Frequency::Daily);
+        daily.until = Some(date(2024, 5, 4));
+
+        assert_eq!(
+            daily.dates(date(2024, 5, 1)),
+            [date(2024, 5, 1), date(2024, 5, 2), date(2024, 5, 3), date(2024, 5, 4)]
+        );
+
+        daily.count = Some(2);
+        assert_eq!(daily.dates(date(2024, 5, 1)), [date(2024, 5, 1), date(2024, 5, 2)]);
+    }
+
+    #[test]
+    fn rules_without_an_end_are_capped() {
+        let mut daily = rule(Frequency::Daily);
+        assert!(daily.validate(date(2024, 5, 1)).is_err());
+
+        daily.count = Some(MAX_OCCURRENCES + 1);
+        assert!(daily.validate(date(2024, 5, 1)).is_err());
+        daily.count = None;
+        assert_eq!(daily.dates(date(2024, 5, 1)).len(), MAX_OCCURRENCES as usize);
+    }
+
+    #[test]
+    fn weekdays_pick_the_days_of_every_interval_week() {
+        // 2024-05-01 is a Wednesday.
+        let mut weekly = rule(Frequency::Weekly);
+        weekly.interval = 2;
+        weekly.by_day = vec![Weekday::Mon, Weekday::Fri];
+        weekly.until = Some(date(2024, 5, 31));
+
+        assert_eq!(
+            weekly.dates(date(2024, 5, 1)),
+            [date(2024, 5, 3), date(2024, 5, 13), date(2024, 5, 17), date(2024, 5, 27), date(2024, 5, 31)]
+        );
+
+        let mut weekly = rule(Frequency::Weekly);
+        weekly.count = Some(3);
+        assert_eq!(
+            weekly.dates(date(2024, 5, 1)),
+            [date(2024, 5, 1), date(2024, 5, 8), date(2024, 5, 15)]
+        );
+
+        let mut weekdays = rule(Frequency::Daily);
+        weekdays.by_day = vec![Weekday::Sat, Weekday::Sun];
+        weekdays.count = Some(3);
+        assert_eq!(
+            weekdays.dates(date(2024, 5, 1)),
+            [date(2024, 5, 4), date(2024, 5, 5), date(2024, 5, 11)]
+        );
+    }
+
+    #[test]
+    fn exceptions_are_left_out_of_the_occurrences() {
+        let mut daily = rule(Frequency::Daily);
+        daily.until = Some(date(2024, 5, 5));
+        daily.exceptions = vec![date(2024, 5, 2), date(2024, 5, 4), date(2024, 6, 1)];
+        assert_eq!(
+            daily.dates(date(2024, 5, 1)),
+            [date(2024, 5, 1), date(2024, 5, 3), date(2024, 5, 5)]
+        );
+
+        daily.until = None;
+        daily.count = Some(3);
+        assert_eq!(daily.dates(date(2024, 5, 1)), [date(2024, 5, 1), date(2024, 5, 3)]);
+    }
+
+    #[test]
+    fn occurrences_keep_their_local_time_over_a_daylight_saving_change() {
+        // Clocks in Warsaw go forward at 02:00 on 2024-03-31.
+        let mut daily = rule(Frequency::Daily);
+        daily.until = Some(date(2024, 4, 2));
+        let dates = daily.dates(date(2024, 3, 29));
+        assert_eq!(dates.len(), 5);
+
+        let evening = NaiveTime::from_hms_opt(20, 0, 0).unwrap();
+        let utc_hours: Vec<u32> = dates
+            .iter()
+            .map(|date| instant_in(Warsaw, date.and_time(evening)).unwrap().to_chrono().hour())
+            .collect();
+        assert_eq!(utc_hours, [19, 19, 18, 18, 18]);
+
+        let skipped = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        assert!(instant_in(Warsaw, date(2024, 3, 31).and_time(skipped)).is_none());
+        assert!(instant_in(Warsaw, date(2024, 4, 1).and_time(skipped)).is_some());
+    }
+}
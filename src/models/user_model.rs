@@ -0,0 +1,100 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{serialize_datetime, serialize_object_id};
+
+/// What a user is allowed to do. Each role can do everything the ones below it can:
+/// admins manage the catalog and halls, staff run sessions and check-in, customers read
+/// and book.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Customer,
+    Staff,
+    Admin,
+}
+
+impl Role {
+    fn rank(self) -> u8 {
+        match self {
+            Role::Customer => 0,
+            Role::Staff => 1,
+            Role::Admin => 2,
+        }
+    }
+
+    pub fn allows(self, required: Role) -> bool {
+        self.rank() >= required.rank()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct User {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    /// Login name. Stored trimmed and lower case, unique per tenant.
+    pub email: String,
+    pub name: String,
+    pub role: Role,
+    /// Argon2 hash in PHC string format.
+    pub password_hash: String,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created_at: DateTime,
+}
+
+/// A user as shown to clients, without the password hash.
+#[derive(Serialize, Debug, Clone)]
+pub struct UserResponse {
+    #[serde(rename = "_id", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    pub email: String,
+    pub name: String,
+    pub role: Role,
+    #[serde(serialize_with = "serialize_datetime")]
+    pub created_at: DateTime,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        UserResponse {
+            id: user.id,
+            email: user.email,
+            name: user.name,
+            role: user.role,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: String,
+}
+
+/// Sign-up of a customer account.
+#[derive(Deserialize, Debug)]
+pub struct RegisterRequest {
+    pub email: String,
+    pub name: String,
+    pub password: String,
+}
+
+/// Account created by an admin, e.g. for box office staff.
+#[derive(Deserialize, Debug)]
+pub struct UserRequest {
+    pub email: String,
+    pub name: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Seconds until the token expires.
+    pub expires_in: i64,
+    pub user: UserResponse,
+}
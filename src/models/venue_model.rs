@@ -0,0 +1,66 @@
+use chrono_tz::Tz;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::serialize_object_id;
+
+use super::hall_model::Hall;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+    pub country: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Contact {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+}
+
+/// Cinema location. Halls belong to at most one venue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Venue {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none", serialize_with = "serialize_object_id")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub address: Address,
+    /// IANA time zone the venue runs in, e.g. `Europe/Warsaw`.
+    pub time_zone: String,
+    #[serde(default)]
+    pub contact: Contact,
+}
+
+impl Venue {
+    pub fn tz(&self) -> Option<Tz> {
+        self.time_zone.parse().ok()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VenueUpdate {
+    pub name: Option<String>,
+    pub address: Option<Address>,
+    pub time_zone: Option<String>,
+    pub contact: Option<Contact>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VenueDetail {
+    #[serde(flatten)]
+    pub venue: Venue,
+    pub halls: Vec<Hall>,
+}
+
+/// `?venue_id=` filter accepted by the listing endpoints.
+#[derive(Deserialize, Debug, Default)]
+pub struct VenueFilter {
+    pub venue_id: Option<String>,
+}
@@ -0,0 +1,201 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    hall_model::Hall,
+    movie_model::Movie,
+    reservation_model::SeatStatusChange,
+    session_model::{SessionDetail, SessionResponse, SessionUpdate},
+    user_model::Role,
+};
+
+/// Versions of the WebSocket protocol the server speaks, oldest first.
+pub const PROTOCOL_VERSIONS: &[u32] = &[1];
+
+/// Id a client gives a request, echoed in the reply to it.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    Text(String),
+}
+
+/// Message sent by a client.
+#[derive(Deserialize, JsonSchema, Debug)]
+pub struct ClientMessage {
+    pub id: RequestId,
+    #[serde(flatten)]
+    pub request: ClientRequest,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientRequest {
+    /// Agrees on a protocol version, the highest one both sides speak. Only allowed as the
+    /// first message; without it, version 1 is used.
+    Hello { versions: Vec<u32> },
+    /// Identifies an anonymous connection with an access token. Only allowed before any
+    /// other request but `hello`.
+    Authenticate { token: String },
+    /// Starts receiving events for the topics, e.g. `hall:<id>` or `session:<id>:seats`.
+    /// A client resuming after a reconnect passes the `seq` of the last event it saw, and
+    /// the events on these topics it missed are sent before the reply.
+    Subscribe {
+        topics: Vec<String>,
+        #[serde(default)]
+        last_seq: Option<u64>,
+    },
+    Unsubscribe { topics: Vec<String> },
+    GetSessions,
+    AddSession { data: SessionUpdate },
+    UpdateSession { session_id: String, data: SessionUpdate },
+    DeleteSession { session_id: String },
+    /// Any action this version of the protocol does not know.
+    #[serde(other)]
+    #[schemars(skip)]
+    Unsupported,
+}
+
+/// Message sent by the server.
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Answer to a request. `id` is missing only when the request had none.
+    Reply {
+        id: Option<RequestId>,
+        #[serde(flatten)]
+        result: ReplyResult,
+    },
+    /// Change delivered to the clients subscribed to one of its topics. `seq` increases with
+    /// every event of the tenant.
+    Event {
+        seq: u64,
+        /// The serialized `Event`, which is how events are kept for replay and passed
+        /// between instances.
+        #[serde(flatten)]
+        #[schemars(with = "Event")]
+        event: Value,
+    },
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReplyResult {
+    Success(Reply),
+    Error { action_type: String, error: WsError },
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "action_type", content = "data", rename_all = "snake_case")]
+pub enum Reply {
+    Hello { version: u32 },
+    Authenticate { role: Role },
+    /// `seq` is the sequence number of the latest event. `resync_required` is set when the
+    /// events missed since `last_seq` are no longer kept; the client then has to reload
+    /// what it shows.
+    Subscribe {
+        topics: Vec<String>,
+        seq: u64,
+        resync_required: bool,
+    },
+    Unsubscribe { topics: Vec<String> },
+    /// Sessions with their movie and hall. The schema only describes the session fields.
+    GetSessions(#[schemars(with = "Vec<SessionResponse>")] Vec<SessionDetail>),
+    AddSession(SessionResponse),
+    UpdateSession(SessionResponse),
+    DeleteSession(DeletedSession),
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+#[serde(tag = "action_type", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    AddSession(SessionResponse),
+    UpdateSession(SessionResponse),
+    DeleteSession(DeletedSession),
+    /// Seats of a session that were held, booked or released.
+    SeatAvailability { session_id: String, seats: Vec<SeatStatusChange> },
+    AddMovie(Movie),
+    UpdateMovie(Movie),
+    DeleteMovie(DeletedRecord),
+    AddHall(Hall),
+    UpdateHall(Hall),
+    DeleteHall(DeletedRecord),
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct DeletedSession {
+    pub message: String,
+    #[serde(rename = "_id")]
+    pub id: String,
+}
+
+impl DeletedSession {
+    pub fn new(id: String) -> Self {
+        DeletedSession {
+            message: "Session deleted successfully".to_string(),
+            id,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct DeletedRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+}
+
+/// Machine-readable reason a request failed.
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The message is not JSON or does not match the schema. The message says what is wrong.
+    InvalidRequest,
+    UnsupportedAction,
+    /// None of the versions offered in `hello` is spoken by the server.
+    UnsupportedVersion,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    /// The hall is not open or is closed at the requested time.
+    HallUnavailable,
+    /// The session was asked to move to a hall at another venue.
+    CrossVenueMove,
+    /// A wall-clock time falls into a daylight saving change.
+    InvalidLocalTime,
+    Internal,
+}
+
+/// Error in the reply to a failed request.
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct WsError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Why a time was rejected: `unavailability` for hall closures and `local_time` for
+    /// daylight saving changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl WsError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        WsError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+}
+
+/// State of a tenant's WebSocket hub.
+#[derive(Serialize, Debug)]
+pub struct WsMetrics {
+    pub connected_clients: usize,
+    /// Messages dropped from the queues of clients that read too slowly.
+    pub dropped_messages: u64,
+    /// Clients disconnected because their queue was full, with the `disconnect` overflow policy.
+    pub slow_client_disconnects: u64,
+    /// Clients disconnected because they stopped answering pings.
+    pub timed_out_clients: u64,
+}
@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::api_key_model::ApiKey;
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<ApiKey>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<ApiKey>>;
+
+    async fn find_by_hash(&self, key_hash: &str) -> RepositoryResult<Option<ApiKey>>;
+
+    /// Stores the key and returns it with its generated id.
+    async fn insert(&self, api_key: ApiKey) -> RepositoryResult<ApiKey>;
+
+    /// Swaps the key of a key that is not revoked, so the old one stops working.
+    /// Returns `false` if no such key matched.
+    async fn rotate(&self, id: ObjectId, prefix: &str, key_hash: &str, now: DateTime) -> RepositoryResult<bool>;
+
+    /// Marks a key revoked. Returns `false` if no key that is not revoked yet matched.
+    async fn revoke(&self, id: ObjectId, now: DateTime) -> RepositoryResult<bool>;
+
+    /// Counts one request made with the key.
+    async fn record_use(&self, id: ObjectId, now: DateTime) -> RepositoryResult<()>;
+}
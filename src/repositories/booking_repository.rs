@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::booking_model::{Booking, BookingStatus};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait BookingRepository: Send + Sync {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Booking>>;
+
+    async fn list_for_session(&self, session_id: ObjectId) -> RepositoryResult<Vec<Booking>>;
+
+    /// Booking made from the hold, if any. A hold can only be booked once.
+    async fn find_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Option<Booking>>;
+
+    /// Stores the booking and returns it with its generated id, or `None` when
+    /// a booking for the same hold already exists.
+    async fn insert(&self, booking: Booking) -> RepositoryResult<Option<Booking>>;
+
+    /// Moves the booking from `from` to `to`. Returns `false` if the booking is
+    /// unknown or no longer in the `from` state.
+    async fn update_status(
+        &self,
+        id: ObjectId,
+        from: BookingStatus,
+        to: BookingStatus,
+        now: DateTime,
+    ) -> RepositoryResult<bool>;
+
+    /// Marks the ticket as used. Returns `false` if it is unknown or was already used,
+    /// so a ticket can only ever be checked in once.
+    async fn check_in_ticket(
+        &self,
+        booking_id: ObjectId,
+        ticket_id: ObjectId,
+        now: DateTime,
+    ) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::{
+    hall_model::{Hall, HallDetail, HallUpdate},
+    seat_layout_model::SeatLayout,
+};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait HallRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<Hall>>;
+
+    async fn list_for_venue(&self, venue_id: ObjectId) -> RepositoryResult<Vec<Hall>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Hall>>;
+
+    /// Hall joined with its sessions and the movies played in them.
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<HallDetail>>;
+
+    /// Stores the hall and returns it with its generated id.
+    async fn insert(&self, hall: Hall) -> RepositoryResult<Hall>;
+
+    /// Applies the non-empty fields of `update`. Returns `false` if no hall matched.
+    async fn update(&self, id: ObjectId, update: &HallUpdate) -> RepositoryResult<bool>;
+
+    /// Stores `layout` on the hall, or removes it when `None`. Returns `false` if no hall matched.
+    async fn set_layout(&self, id: ObjectId, layout: Option<&SeatLayout>) -> RepositoryResult<bool>;
+
+    /// Removes the hall with its opening hours and closures, and clears `hall_id` on its sessions.
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
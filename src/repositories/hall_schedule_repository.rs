@@ -0,0 +1,26 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::hall_schedule_model::{HallClosure, HallOpeningHours};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait HallScheduleRepository: Send + Sync {
+    async fn list_opening_hours(&self, hall_id: ObjectId) -> RepositoryResult<Vec<HallOpeningHours>>;
+
+    /// Stores the opening hours and returns them with their generated id.
+    async fn insert_opening_hours(&self, hours: HallOpeningHours) -> RepositoryResult<HallOpeningHours>;
+
+    /// Removes the opening hours if they belong to the hall.
+    async fn delete_opening_hours(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool>;
+
+    /// Closures of the hall overlapping `[start, end)`, ordered by start.
+    async fn list_closures(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<HallClosure>>;
+
+    /// Stores the closure and returns it with its generated id.
+    async fn insert_closure(&self, closure: HallClosure) -> RepositoryResult<HallClosure>;
+
+    /// Removes the closure if it belongs to the hall.
+    async fn delete_closure(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool>;
+}
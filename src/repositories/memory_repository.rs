@@ -0,0 +1,1208 @@
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::{
+    api_key_model::ApiKey,
+    booking_model::{Booking, BookingStatus},
+    hall_model::{Hall, HallDetail, HallUpdate},
+    hall_schedule_model::{HallClosure, HallOpeningHours},
+    movie_model::{Movie, MovieDetail, MovieUpdate},
+    price_list_model::PriceList,
+    promotion_model::{Promotion, PromotionRedemption, PromotionRejection},
+    reservation_model::{HoldInsertion, SeatReservation},
+    seat_layout_model::SeatLayout,
+    session_model::{Session, SessionDetail},
+    session_series_model::SessionSeries,
+    user_model::User,
+    venue_model::{Venue, VenueUpdate},
+};
+
+use super::{
+    ApiKeyRepository, BookingRepository, HallRepository, HallScheduleRepository, MovieRepository, PriceListRepository, PromotionRepository, RepositoryResult,
+    ReservationRepository, SessionRepository, SessionSeriesRepository, UserRepository, VenueRepository,
+};
+
+#[derive(Default)]
+struct Collections {
+    movies: Vec<Movie>,
+    halls: Vec<Hall>,
+    hall_opening_hours: Vec<HallOpeningHours>,
+    hall_closures: Vec<HallClosure>,
+    sessions: Vec<Session>,
+    reservations: Vec<SeatReservation>,
+    bookings: Vec<Booking>,
+    price_lists: Vec<PriceList>,
+    promotions: Vec<Promotion>,
+    promotion_redemptions: Vec<PromotionRedemption>,
+    session_series: Vec<SessionSeries>,
+    venues: Vec<Venue>,
+    users: Vec<User>,
+    api_keys: Vec<ApiKey>,
+}
+
+impl Collections {
+    fn movie(&self, id: Option<ObjectId>) -> Option<&Movie> {
+        id.and_then(|id| self.movies.iter().find(|movie| movie.id == Some(id)))
+    }
+
+    fn hall(&self, id: Option<ObjectId>) -> Option<&Hall> {
+        id.and_then(|id| self.halls.iter().find(|hall| hall.id == Some(id)))
+    }
+
+    fn session_detail(&self, session: &Session) -> SessionDetail {
+        SessionDetail {
+            id: session.id,
+            title: session.title.clone(),
+            movie_id: session.movie_id,
+            hall_id: session.hall_id,
+            price_list_id: session.price_list_id,
+            series_id: session.series_id,
+            start: session.start,
+            end: session.end,
+            local: None,
+            movie: self.movie(session.movie_id).cloned(),
+            hall: self.hall(session.hall_id).cloned(),
+        }
+    }
+}
+
+/// Keeps every collection in process memory. Joins that MongoDB does with `$lookup`
+/// are done by hand so the API behaves the same without a database.
+#[derive(Clone, Default)]
+pub struct InMemoryRepository {
+    data: Arc<RwLock<Collections>>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl MovieRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Movie>> {
+        Ok(self.data.read().unwrap().movies.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Movie>> {
+        Ok(self.data.read().unwrap().movie(Some(id)).cloned())
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<MovieDetail>> {
+        let data = self.data.read().unwrap();
+        let Some(movie) = data.movie(Some(id)) else {
+            return Ok(None);
+        };
+
+        let sessions: Vec<&Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.movie_id == Some(id))
+            .collect();
+        let halls = data
+            .halls
+            .iter()
+            .filter(|hall| sessions.iter().any(|session| session.hall_id == hall.id))
+            .cloned()
+            .collect();
+
+        Ok(Some(MovieDetail {
+            id: movie.id,
+            title: movie.title.clone(),
+            duration: movie.duration,
+            description: movie.description.clone(),
+            poster: movie.poster.clone(),
+            halls,
+            sessions: sessions.into_iter().cloned().map(Into::into).collect(),
+        }))
+    }
+
+    async fn insert(&self, mut movie: Movie) -> RepositoryResult<Movie> {
+        movie.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().movies.push(movie.clone());
+        Ok(movie)
+    }
+
+    async fn update(&self, id: ObjectId, update: &MovieUpdate) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(movie) = data.movies.iter_mut().find(|movie| movie.id == Some(id)) else {
+            return Ok(false);
+        };
+
+        if let Some(title) = &update.title {
+            movie.title = title.clone();
+        }
+        if let Some(duration) = update.duration {
+            movie.duration = duration;
+        }
+        if update.description.is_some() {
+            movie.description = update.description.clone();
+        }
+        if update.poster.is_some() {
+            movie.poster = update.poster.clone();
+        }
+
+        Ok(true)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.movies.len();
+        data.movies.retain(|movie| movie.id != Some(id));
+        Ok(data.movies.len() < count)
+    }
+}
+
+#[async_trait]
+impl HallRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Hall>> {
+        Ok(self.data.read().unwrap().halls.clone())
+    }
+
+    async fn list_for_venue(&self, venue_id: ObjectId) -> RepositoryResult<Vec<Hall>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .halls
+            .iter()
+            .filter(|hall| hall.venue_id == Some(venue_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Hall>> {
+        Ok(self.data.read().unwrap().hall(Some(id)).cloned())
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<HallDetail>> {
+        let data = self.data.read().unwrap();
+        let Some(hall) = data.hall(Some(id)) else {
+            return Ok(None);
+        };
+
+        let sessions: Vec<&Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.hall_id == Some(id))
+            .collect();
+        let movies = data
+            .movies
+            .iter()
+            .filter(|movie| sessions.iter().any(|session| session.movie_id == movie.id))
+            .cloned()
+            .collect();
+
+        Ok(Some(HallDetail {
+            id: hall.id,
+            venue_id: hall.venue_id,
+            name: hall.name.clone(),
+            capacity: hall.capacity,
+            description: hall.description.clone(),
+            layout: hall.layout.clone(),
+            pre_show_minutes: hall.pre_show_minutes,
+            cleaning_minutes: hall.cleaning_minutes,
+            time_zone: hall.time_zone.clone(),
+            movies,
+            sessions: sessions.into_iter().cloned().map(Into::into).collect(),
+        }))
+    }
+
+    async fn insert(&self, mut hall: Hall) -> RepositoryResult<Hall> {
+        hall.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().halls.push(hall.clone());
+        Ok(hall)
+    }
+
+    async fn update(&self, id: ObjectId, update: &HallUpdate) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(hall) = data.halls.iter_mut().find(|hall| hall.id == Some(id)) else {
+            return Ok(false);
+        };
+
+        if update.venue_id.is_some() {
+            hall.venue_id = update.venue_id;
+        }
+        if let Some(name) = &update.name {
+            hall.name = name.clone();
+        }
+        if let Some(capacity) = update.capacity {
+            hall.capacity = capacity;
+        }
+        if let Some(description) = &update.description {
+            hall.description = description.clone();
+        }
+        if update.pre_show_minutes.is_some() {
+            hall.pre_show_minutes = update.pre_show_minutes;
+        }
+        if update.cleaning_minutes.is_some() {
+            hall.cleaning_minutes = update.cleaning_minutes;
+        }
+        if update.time_zone.is_some() {
+            hall.time_zone = update.time_zone.clone();
+        }
+
+        Ok(true)
+    }
+
+    async fn set_layout(&self, id: ObjectId, layout: Option<&SeatLayout>) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(hall) = data.halls.iter_mut().find(|hall| hall.id == Some(id)) else {
+            return Ok(false);
+        };
+
+        hall.layout = layout.cloned();
+        Ok(true)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.halls.len();
+        data.halls.retain(|hall| hall.id != Some(id));
+        if data.halls.len() == count {
+            return Ok(false);
+        }
+
+        for session in data.sessions.iter_mut().filter(|session| session.hall_id == Some(id)) {
+            session.hall_id = None;
+        }
+        data.hall_opening_hours.retain(|hours| hours.hall_id != id);
+        data.hall_closures.retain(|closure| closure.hall_id != id);
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl HallScheduleRepository for InMemoryRepository {
+    async fn list_opening_hours(&self, hall_id: ObjectId) -> RepositoryResult<Vec<HallOpeningHours>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .hall_opening_hours
+            .iter()
+            .filter(|hours| hours.hall_id == hall_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn insert_opening_hours(&self, mut hours: HallOpeningHours) -> RepositoryResult<HallOpeningHours> {
+        hours.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().hall_opening_hours.push(hours.clone());
+        Ok(hours)
+    }
+
+    async fn delete_opening_hours(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.hall_opening_hours.len();
+        data.hall_opening_hours
+            .retain(|hours| !(hours.id == Some(id) && hours.hall_id == hall_id));
+        Ok(data.hall_opening_hours.len() < count)
+    }
+
+    async fn list_closures(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<HallClosure>> {
+        let data = self.data.read().unwrap();
+        let mut closures: Vec<HallClosure> = data
+            .hall_closures
+            .iter()
+            .filter(|closure| closure.hall_id == hall_id)
+            .filter(|closure| closure.start < end && closure.end > start)
+            .cloned()
+            .collect();
+        closures.sort_by_key(|closure| closure.start);
+        Ok(closures)
+    }
+
+    async fn insert_closure(&self, mut closure: HallClosure) -> RepositoryResult<HallClosure> {
+        closure.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().hall_closures.push(closure.clone());
+        Ok(closure)
+    }
+
+    async fn delete_closure(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.hall_closures.len();
+        data.hall_closures
+            .retain(|closure| !(closure.id == Some(id) && closure.hall_id == hall_id));
+        Ok(data.hall_closures.len() < count)
+    }
+}
+
+#[async_trait]
+impl SessionRepository for InMemoryRepository {
+    async fn list_with_details(&self) -> RepositoryResult<Vec<SessionDetail>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .sessions
+            .iter()
+            .map(|session| {
+                let mut detail = data.session_detail(session);
+                if let Some(movie) = detail.movie.as_mut() {
+                    movie.description = None;
+                    movie.poster = None;
+                }
+                detail
+            })
+            .collect())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Session>> {
+        let data = self.data.read().unwrap();
+        Ok(data.sessions.iter().find(|session| session.id == Some(id)).cloned())
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<SessionDetail>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .sessions
+            .iter()
+            .find(|session| session.id == Some(id))
+            .map(|session| data.session_detail(session)))
+    }
+
+    async fn list_for_series(&self, series_id: ObjectId) -> RepositoryResult<Vec<Session>> {
+        let data = self.data.read().unwrap();
+        let mut sessions: Vec<Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.series_id == Some(series_id))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.start);
+        Ok(sessions)
+    }
+
+    async fn list_for_hall(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<Session>> {
+        let data = self.data.read().unwrap();
+        let mut sessions: Vec<Session> = data
+            .sessions
+            .iter()
+            .filter(|session| session.hall_id == Some(hall_id))
+            .filter(|session| session.start < end && session.end > start)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.start);
+        Ok(sessions)
+    }
+
+    async fn count_overlapping(
+        &self,
+        hall_id: ObjectId,
+        start: DateTime,
+        end: DateTime,
+        exclude_id: Option<ObjectId>,
+    ) -> RepositoryResult<u64> {
+        let data = self.data.read().unwrap();
+        let count = data
+            .sessions
+            .iter()
+            .filter(|session| session.hall_id == Some(hall_id))
+            .filter(|session| exclude_id.is_none() || session.id != exclude_id)
+            .filter(|session| session.start < end && session.end > start)
+            .count();
+        Ok(count as u64)
+    }
+
+    async fn insert(&self, mut session: Session) -> RepositoryResult<Session> {
+        session.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().sessions.push(session.clone());
+        Ok(session)
+    }
+
+    async fn insert_many(&self, mut sessions: Vec<Session>) -> RepositoryResult<Vec<Session>> {
+        for session in &mut sessions {
+            session.id.get_or_insert_with(ObjectId::new);
+        }
+        self.data.write().unwrap().sessions.extend(sessions.iter().cloned());
+        Ok(sessions)
+    }
+
+    async fn replace(&self, session: &Session) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        match data.sessions.iter_mut().find(|stored| stored.id.is_some() && stored.id == session.id) {
+            Some(stored) => {
+                *stored = session.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.sessions.len();
+        data.sessions.retain(|session| session.id != Some(id));
+        Ok(data.sessions.len() < count)
+    }
+}
+
+#[async_trait]
+impl ReservationRepository for InMemoryRepository {
+    async fn list_for_session(&self, session_id: ObjectId, now: DateTime) -> RepositoryResult<Vec<SeatReservation>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.session_id == session_id && reservation.is_active(now))
+            .cloned()
+            .collect())
+    }
+
+    async fn list_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Vec<SeatReservation>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.hold_id == hold_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn insert_hold(&self, reservations: Vec<SeatReservation>, now: DateTime) -> RepositoryResult<HoldInsertion> {
+        let mut data = self.data.write().unwrap();
+        let taken = reservations.iter().any(|requested| {
+            data.reservations.iter().any(|existing| {
+                existing.session_id == requested.session_id
+                    && existing.row == requested.row
+                    && existing.number == requested.number
+                    && existing.is_active(now)
+            })
+        });
+        if taken {
+            return Ok(HoldInsertion {
+                placed: false,
+                displaced: Vec::new(),
+            });
+        }
+
+        let (displaced, kept) = std::mem::take(&mut data.reservations)
+            .into_iter()
+            .partition(|existing| {
+                !existing.is_active(now)
+                    && reservations.iter().any(|requested| {
+                        existing.session_id == requested.session_id
+                            && existing.row == requested.row
+                            && existing.number == requested.number
+                    })
+            });
+        data.reservations = kept;
+        data.reservations.extend(reservations.into_iter().map(|mut reservation| {
+            reservation.id.get_or_insert_with(ObjectId::new);
+            reservation
+        }));
+
+        Ok(HoldInsertion {
+            placed: true,
+            displaced,
+        })
+    }
+
+    async fn confirm_hold(&self, hold_id: ObjectId, now: DateTime) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let mut confirmed = false;
+        for reservation in data.reservations.iter_mut().filter(|reservation| reservation.hold_id == hold_id) {
+            if reservation.expires_at.is_some_and(|expires_at| expires_at > now) {
+                reservation.expires_at = None;
+                confirmed = true;
+            }
+        }
+        Ok(confirmed)
+    }
+
+    async fn release_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.reservations.len();
+        data.reservations
+            .retain(|reservation| reservation.hold_id != hold_id || reservation.expires_at.is_none());
+        Ok(data.reservations.len() < count)
+    }
+
+    async fn delete_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.reservations.len();
+        data.reservations.retain(|reservation| reservation.hold_id != hold_id);
+        Ok(data.reservations.len() < count)
+    }
+
+    async fn delete_for_session(&self, session_id: ObjectId) -> RepositoryResult<u64> {
+        let mut data = self.data.write().unwrap();
+        let count = data.reservations.len();
+        data.reservations.retain(|reservation| reservation.session_id != session_id);
+        Ok((count - data.reservations.len()) as u64)
+    }
+
+    async fn purge_expired(&self, now: DateTime) -> RepositoryResult<Vec<SeatReservation>> {
+        let mut data = self.data.write().unwrap();
+        let (active, expired) = std::mem::take(&mut data.reservations)
+            .into_iter()
+            .partition(|reservation| reservation.is_active(now));
+        data.reservations = active;
+        Ok(expired)
+    }
+}
+
+#[async_trait]
+impl BookingRepository for InMemoryRepository {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Booking>> {
+        let data = self.data.read().unwrap();
+        Ok(data.bookings.iter().find(|booking| booking.id == Some(id)).cloned())
+    }
+
+    async fn list_for_session(&self, session_id: ObjectId) -> RepositoryResult<Vec<Booking>> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .bookings
+            .iter()
+            .filter(|booking| booking.session_id == session_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Option<Booking>> {
+        let data = self.data.read().unwrap();
+        Ok(data.bookings.iter().find(|booking| booking.hold_id == hold_id).cloned())
+    }
+
+    async fn insert(&self, mut booking: Booking) -> RepositoryResult<Option<Booking>> {
+        let mut data = self.data.write().unwrap();
+        if data.bookings.iter().any(|existing| existing.hold_id == booking.hold_id) {
+            return Ok(None);
+        }
+
+        booking.id.get_or_insert_with(ObjectId::new);
+        data.bookings.push(booking.clone());
+        Ok(Some(booking))
+    }
+
+    async fn update_status(
+        &self,
+        id: ObjectId,
+        from: BookingStatus,
+        to: BookingStatus,
+        now: DateTime,
+    ) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        match data
+            .bookings
+            .iter_mut()
+            .find(|booking| booking.id == Some(id) && booking.status == from)
+        {
+            Some(booking) => {
+                booking.status = to;
+                booking.updated_at = now;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn check_in_ticket(
+        &self,
+        booking_id: ObjectId,
+        ticket_id: ObjectId,
+        now: DateTime,
+    ) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(booking) = data.bookings.iter_mut().find(|booking| booking.id == Some(booking_id)) else {
+            return Ok(false);
+        };
+        match booking
+            .tickets
+            .iter_mut()
+            .find(|ticket| ticket.id == ticket_id && ticket.checked_in_at.is_none())
+        {
+            Some(ticket) => {
+                ticket.checked_in_at = Some(now);
+                booking.updated_at = now;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceListRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<PriceList>> {
+        Ok(self.data.read().unwrap().price_lists.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<PriceList>> {
+        let data = self.data.read().unwrap();
+        Ok(data.price_lists.iter().find(|price_list| price_list.id == Some(id)).cloned())
+    }
+
+    async fn insert(&self, mut price_list: PriceList) -> RepositoryResult<PriceList> {
+        price_list.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().price_lists.push(price_list.clone());
+        Ok(price_list)
+    }
+
+    async fn replace(&self, price_list: &PriceList) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        match data
+            .price_lists
+            .iter_mut()
+            .find(|stored| stored.id.is_some() && stored.id == price_list.id)
+        {
+            Some(stored) => {
+                *stored = price_list.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.price_lists.len();
+        data.price_lists.retain(|price_list| price_list.id != Some(id));
+        if data.price_lists.len() == count {
+            return Ok(false);
+        }
+
+        for session in data.sessions.iter_mut().filter(|session| session.price_list_id == Some(id)) {
+            session.price_list_id = None;
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl PromotionRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Promotion>> {
+        Ok(self.data.read().unwrap().promotions.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Promotion>> {
+        let data = self.data.read().unwrap();
+        Ok(data.promotions.iter().find(|promotion| promotion.id == Some(id)).cloned())
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Promotion>> {
+        let data = self.data.read().unwrap();
+        Ok(data.promotions.iter().find(|promotion| promotion.code == code).cloned())
+    }
+
+    async fn insert(&self, mut promotion: Promotion) -> RepositoryResult<Option<Promotion>> {
+        let mut data = self.data.write().unwrap();
+        if data.promotions.iter().any(|existing| existing.code == promotion.code) {
+            return Ok(None);
+        }
+
+        promotion.id.get_or_insert_with(ObjectId::new);
+        data.promotions.push(promotion.clone());
+        Ok(Some(promotion))
+    }
+
+    async fn replace(&self, promotion: &Promotion) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        match data
+            .promotions
+            .iter_mut()
+            .find(|stored| stored.id.is_some() && stored.id == promotion.id)
+        {
+            Some(stored) => {
+                let uses = stored.uses;
+                *stored = promotion.clone();
+                stored.uses = uses;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.promotions.len();
+        data.promotions.retain(|promotion| promotion.id != Some(id));
+        if data.promotions.len() == count {
+            return Ok(false);
+        }
+
+        data.promotion_redemptions.retain(|redemption| redemption.promotion_id != id);
+
+        Ok(true)
+    }
+
+    async fn customer_uses(&self, id: ObjectId, customer: &str) -> RepositoryResult<u32> {
+        let data = self.data.read().unwrap();
+        Ok(data
+            .promotion_redemptions
+            .iter()
+            .find(|redemption| redemption.promotion_id == id && redemption.customer == customer)
+            .map_or(0, |redemption| redemption.uses))
+    }
+
+    async fn redeem(&self, promotion: &Promotion, customer: &str) -> RepositoryResult<Option<PromotionRejection>> {
+        let Some(id) = promotion.id else {
+            return Ok(Some(PromotionRejection::UnknownCode));
+        };
+
+        let mut data = self.data.write().unwrap();
+        let Collections {
+            promotions,
+            promotion_redemptions,
+            ..
+        } = &mut *data;
+
+        let Some(stored) = promotions.iter_mut().find(|stored| stored.id == Some(id)) else {
+            return Ok(Some(PromotionRejection::UnknownCode));
+        };
+        if promotion.max_uses.is_some_and(|max_uses| stored.uses >= max_uses) {
+            return Ok(Some(PromotionRejection::UsageLimitReached));
+        }
+
+        let redemption = promotion_redemptions
+            .iter_mut()
+            .find(|redemption| redemption.promotion_id == id && redemption.customer == customer);
+        let customer_uses = redemption.as_ref().map_or(0, |redemption| redemption.uses);
+        if promotion
+            .max_uses_per_customer
+            .is_some_and(|max_uses| customer_uses >= max_uses)
+        {
+            return Ok(Some(PromotionRejection::CustomerLimitReached));
+        }
+
+        match redemption {
+            Some(redemption) => redemption.uses += 1,
+            None => promotion_redemptions.push(PromotionRedemption {
+                promotion_id: id,
+                customer: customer.to_string(),
+                uses: 1,
+            }),
+        }
+        stored.uses += 1;
+
+        Ok(None)
+    }
+
+    async fn release(&self, id: ObjectId, customer: &str) -> RepositoryResult<()> {
+        let mut data = self.data.write().unwrap();
+        if let Some(promotion) = data.promotions.iter_mut().find(|promotion| promotion.id == Some(id)) {
+            promotion.uses = promotion.uses.saturating_sub(1);
+        }
+        if let Some(redemption) = data
+            .promotion_redemptions
+            .iter_mut()
+            .find(|redemption| redemption.promotion_id == id && redemption.customer == customer)
+        {
+            redemption.uses = redemption.uses.saturating_sub(1);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionSeriesRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<SessionSeries>> {
+        Ok(self.data.read().unwrap().session_series.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<SessionSeries>> {
+        let data = self.data.read().unwrap();
+        Ok(data.session_series.iter().find(|series| series.id == Some(id)).cloned())
+    }
+
+    async fn insert(&self, mut series: SessionSeries) -> RepositoryResult<SessionSeries> {
+        series.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().session_series.push(series.clone());
+        Ok(series)
+    }
+
+    async fn replace(&self, series: &SessionSeries) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        match data
+            .session_series
+            .iter_mut()
+            .find(|stored| stored.id.is_some() && stored.id == series.id)
+        {
+            Some(stored) => {
+                *stored = series.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.session_series.len();
+        data.session_series.retain(|series| series.id != Some(id));
+        if data.session_series.len() == count {
+            return Ok(false);
+        }
+
+        for session in data.sessions.iter_mut().filter(|session| session.series_id == Some(id)) {
+            session.series_id = None;
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl VenueRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Venue>> {
+        Ok(self.data.read().unwrap().venues.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Venue>> {
+        let data = self.data.read().unwrap();
+        Ok(data.venues.iter().find(|venue| venue.id == Some(id)).cloned())
+    }
+
+    async fn insert(&self, mut venue: Venue) -> RepositoryResult<Venue> {
+        venue.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().venues.push(venue.clone());
+        Ok(venue)
+    }
+
+    async fn update(&self, id: ObjectId, update: &VenueUpdate) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(venue) = data.venues.iter_mut().find(|venue| venue.id == Some(id)) else {
+            return Ok(false);
+        };
+
+        if let Some(name) = &update.name {
+            venue.name = name.clone();
+        }
+        if let Some(address) = &update.address {
+            venue.address = address.clone();
+        }
+        if let Some(time_zone) = &update.time_zone {
+            venue.time_zone = time_zone.clone();
+        }
+        if let Some(contact) = &update.contact {
+            venue.contact = contact.clone();
+        }
+
+        Ok(true)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let count = data.venues.len();
+        data.venues.retain(|venue| venue.id != Some(id));
+        if data.venues.len() == count {
+            return Ok(false);
+        }
+
+        for hall in data.halls.iter_mut().filter(|hall| hall.venue_id == Some(id)) {
+            hall.venue_id = None;
+        }
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryRepository {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<User>> {
+        let data = self.data.read().unwrap();
+        Ok(data.users.iter().find(|user| user.id == Some(id)).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>> {
+        let data = self.data.read().unwrap();
+        Ok(data.users.iter().find(|user| user.email == email).cloned())
+    }
+
+    async fn insert(&self, mut user: User) -> RepositoryResult<Option<User>> {
+        let mut data = self.data.write().unwrap();
+        if data.users.iter().any(|existing| existing.email == user.email) {
+            return Ok(None);
+        }
+
+        user.id.get_or_insert_with(ObjectId::new);
+        data.users.push(user.clone());
+        Ok(Some(user))
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for InMemoryRepository {
+    async fn list(&self) -> RepositoryResult<Vec<ApiKey>> {
+        Ok(self.data.read().unwrap().api_keys.clone())
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<ApiKey>> {
+        let data = self.data.read().unwrap();
+        Ok(data.api_keys.iter().find(|api_key| api_key.id == Some(id)).cloned())
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> RepositoryResult<Option<ApiKey>> {
+        let data = self.data.read().unwrap();
+        Ok(data.api_keys.iter().find(|api_key| api_key.key_hash == key_hash).cloned())
+    }
+
+    async fn insert(&self, mut api_key: ApiKey) -> RepositoryResult<ApiKey> {
+        api_key.id.get_or_insert_with(ObjectId::new);
+        self.data.write().unwrap().api_keys.push(api_key.clone());
+        Ok(api_key)
+    }
+
+    async fn rotate(&self, id: ObjectId, prefix: &str, key_hash: &str, now: DateTime) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(api_key) = data
+            .api_keys
+            .iter_mut()
+            .find(|api_key| api_key.id == Some(id) && api_key.revoked_at.is_none())
+        else {
+            return Ok(false);
+        };
+
+        api_key.prefix = prefix.to_string();
+        api_key.key_hash = key_hash.to_string();
+        api_key.rotated_at = Some(now);
+        Ok(true)
+    }
+
+    async fn revoke(&self, id: ObjectId, now: DateTime) -> RepositoryResult<bool> {
+        let mut data = self.data.write().unwrap();
+        let Some(api_key) = data
+            .api_keys
+            .iter_mut()
+            .find(|api_key| api_key.id == Some(id) && api_key.revoked_at.is_none())
+        else {
+            return Ok(false);
+        };
+
+        api_key.revoked_at = Some(now);
+        Ok(true)
+    }
+
+    async fn record_use(&self, id: ObjectId, now: DateTime) -> RepositoryResult<()> {
+        let mut data = self.data.write().unwrap();
+        if let Some(api_key) = data.api_keys.iter_mut().find(|api_key| api_key.id == Some(id)) {
+            api_key.uses += 1;
+            api_key.last_used_at = Some(now);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        controllers::reservation_controller::place_hold,
+        models::{
+            booking_model::{Booking, BookingStatus, BuyerContact},
+            hall_model::{Hall, HallUpdate},
+            movie_model::{Movie, MovieUpdate},
+            promotion_model::{AppliedPromotion, Promotion, PromotionDiscount},
+            reservation_model::SeatReservation,
+            session_model::Session,
+        },
+        repositories::Repositories,
+    };
+    use mongodb::bson::{oid::ObjectId, DateTime};
+
+    fn movie(title: &str) -> Movie {
+        Movie {
+            id: None,
+            title: title.to_string(),
+            duration: 120,
+            description: None,
+            poster: None,
+        }
+    }
+
+    fn hall(name: &str) -> Hall {
+        Hall {
+            id: None,
+            venue_id: None,
+            name: name.to_string(),
+            description: String::new(),
+            capacity: 100,
+            layout: None,
+            pre_show_minutes: None,
+            cleaning_minutes: None,
+            time_zone: None,
+        }
+    }
+
+    fn session(movie_id: Option<ObjectId>, hall_id: Option<ObjectId>) -> Session {
+        Session {
+            id: None,
+            title: None,
+            movie_id,
+            hall_id,
+            price_list_id: None,
+            series_id: None,
+            start: DateTime::from_millis(0),
+            end: DateTime::from_millis(2 * 60 * 60 * 1000),
+        }
+    }
+
+    #[tokio::test]
+    async fn movies_can_be_inserted_found_updated_and_deleted() {
+        let repositories = Repositories::in_memory();
+
+        let movie = repositories.movies.insert(movie("Alien")).await.unwrap();
+        let id = movie.id.expect("insert assigns an id");
+        assert_eq!(repositories.movies.find(id).await.unwrap().unwrap().title, "Alien");
+        assert_eq!(repositories.movies.list().await.unwrap().len(), 1);
+
+        let update = MovieUpdate {
+            title: Some("Aliens".to_string()),
+            duration: None,
+            description: Some("Sequel".to_string()),
+            poster: None,
+        };
+        assert!(repositories.movies.update(id, &update).await.unwrap());
+        let updated = repositories.movies.find(id).await.unwrap().unwrap();
+        assert_eq!(updated.title, "Aliens");
+        assert_eq!(updated.duration, 120);
+        assert_eq!(updated.description.as_deref(), Some("Sequel"));
+
+        assert!(repositories.movies.delete(id).await.unwrap());
+        assert!(repositories.movies.find(id).await.unwrap().is_none());
+        assert!(!repositories.movies.delete(id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn unknown_ids_are_not_updated() {
+        let repositories = Repositories::in_memory();
+        let update = HallUpdate {
+            venue_id: None,
+            name: Some("Hall 2".to_string()),
+            capacity: None,
+            description: None,
+            pre_show_minutes: None,
+            cleaning_minutes: None,
+            time_zone: None,
+        };
+
+        assert!(!repositories.halls.update(ObjectId::new(), &update).await.unwrap());
+        assert!(repositories.halls.list().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn sessions_are_listed_with_their_movie_and_hall() {
+        let repositories = Repositories::in_memory();
+        let movie = repositories.movies.insert(movie("Alien")).await.unwrap();
+        let hall = repositories.halls.insert(hall("Hall 1")).await.unwrap();
+        let session = repositories.sessions.insert(session(movie.id, hall.id)).await.unwrap();
+
+        let detail = repositories
+            .sessions
+            .find_with_details(session.id.unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(detail.movie.map(|movie| movie.title).as_deref(), Some("Alien"));
+        assert_eq!(detail.hall.map(|hall| hall.name).as_deref(), Some("Hall 1"));
+    }
+
+    #[tokio::test]
+    async fn deleting_a_hall_detaches_its_sessions() {
+        let repositories = Repositories::in_memory();
+        let movie = repositories.movies.insert(movie("Alien")).await.unwrap();
+        let hall = repositories.halls.insert(hall("Hall 1")).await.unwrap();
+        let hall_id = hall.id.unwrap();
+        let session = repositories.sessions.insert(session(movie.id, hall.id)).await.unwrap();
+
+        assert!(repositories.halls.delete(hall_id).await.unwrap());
+
+        let session = repositories.sessions.find(session.id.unwrap()).await.unwrap().unwrap();
+        assert_eq!(session.hall_id, None);
+        assert_eq!(session.movie_id, movie.id);
+        assert!(repositories.halls.find(hall_id).await.unwrap().is_none());
+    }
+
+    fn reservation(session_id: ObjectId, hold_id: ObjectId, expires_at: DateTime) -> SeatReservation {
+        SeatReservation {
+            id: None,
+            session_id,
+            hold_id,
+            row: "A".to_string(),
+            number: 1,
+            expires_at: Some(expires_at),
+            user_id: None,
+            api_key_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn taking_the_seats_of_an_expired_hold_cancels_its_booking() {
+        let repositories = Repositories::in_memory();
+        let session_id = ObjectId::new();
+        let now = DateTime::from_millis(1_000_000);
+        let later = DateTime::from_millis(now.timestamp_millis() + 60_000);
+
+        let promotion = repositories
+            .promotions
+            .insert(Promotion {
+                id: None,
+                code: "SUMMER".to_string(),
+                name: "Summer".to_string(),
+                discount: PromotionDiscount::Percent(10),
+                currency: None,
+                valid_from: None,
+                valid_until: None,
+                max_uses: Some(1),
+                max_uses_per_customer: None,
+                min_spend: None,
+                uses: 0,
+                movie_ids: Vec::new(),
+                hall_ids: Vec::new(),
+                weekdays: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        let promotion_id = promotion.id.unwrap();
+        assert!(repositories.promotions.redeem(&promotion, "ann@example.com").await.unwrap().is_none());
+
+        let first_hold = ObjectId::new();
+        let expires_at = DateTime::from_millis(now.timestamp_millis() + 30_000);
+        assert!(place_hold(&repositories, vec![reservation(session_id, first_hold, expires_at)], now)
+            .await
+            .unwrap());
+        let booking = repositories
+            .bookings
+            .insert(Booking {
+                id: None,
+                session_id,
+                hold_id: first_hold,
+                buyer: BuyerContact {
+                    name: "Ann".to_string(),
+                    email: "ann@example.com".to_string(),
+                    phone: None,
+                },
+                tickets: Vec::new(),
+                total: 900,
+                currency: "PLN".to_string(),
+                promotion: Some(AppliedPromotion {
+                    promotion_id,
+                    code: "SUMMER".to_string(),
+                    discount: 100,
+                }),
+                api_key_id: None,
+                status: BookingStatus::Pending,
+                created_at: now,
+                updated_at: now,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        let second_hold = ObjectId::new();
+        let expires_at = DateTime::from_millis(later.timestamp_millis() + 30_000);
+        assert!(place_hold(&repositories, vec![reservation(session_id, second_hold, expires_at)], later)
+            .await
+            .unwrap());
+
+        let booking = repositories.bookings.find(booking.id.unwrap()).await.unwrap().unwrap();
+        assert_eq!(booking.status, BookingStatus::Cancelled);
+        let promotion = repositories.promotions.find(promotion_id).await.unwrap().unwrap();
+        assert_eq!(promotion.uses, 0);
+        assert_eq!(repositories.promotions.customer_uses(promotion_id, "ann@example.com").await.unwrap(), 0);
+        let seats = repositories.reservations.list_for_session(session_id, later).await.unwrap();
+        assert!(seats.iter().all(|seat| seat.hold_id == second_hold));
+        assert_eq!(seats.len(), 1);
+    }
+}
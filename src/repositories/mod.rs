@@ -0,0 +1,135 @@
+use std::{fmt, sync::Arc};
+
+use axum::http::StatusCode;
+use mongodb::Client;
+
+pub mod api_key_repository;
+pub mod booking_repository;
+pub mod hall_repository;
+pub mod hall_schedule_repository;
+pub mod memory_repository;
+pub mod mongo_repository;
+pub mod movie_repository;
+pub mod price_list_repository;
+pub mod promotion_repository;
+pub mod reservation_repository;
+pub mod session_repository;
+pub mod session_series_repository;
+pub mod user_repository;
+pub mod venue_repository;
+
+pub use api_key_repository::ApiKeyRepository;
+pub use booking_repository::BookingRepository;
+pub use hall_repository::HallRepository;
+pub use hall_schedule_repository::HallScheduleRepository;
+pub use memory_repository::InMemoryRepository;
+pub use mongo_repository::MongoRepository;
+pub use movie_repository::MovieRepository;
+pub use price_list_repository::PriceListRepository;
+pub use promotion_repository::PromotionRepository;
+pub use reservation_repository::ReservationRepository;
+pub use session_repository::SessionRepository;
+pub use session_series_repository::SessionSeriesRepository;
+pub use user_repository::UserRepository;
+pub use venue_repository::VenueRepository;
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    Mongo(mongodb::error::Error),
+    Serialize(mongodb::bson::ser::Error),
+    Deserialize(mongodb::bson::de::Error),
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepositoryError::Mongo(e) => write!(f, "MongoDB error: {}", e),
+            RepositoryError::Serialize(e) => write!(f, "failed to serialize document: {}", e),
+            RepositoryError::Deserialize(e) => write!(f, "failed to deserialize document: {}", e),
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for RepositoryError {
+    fn from(err: mongodb::error::Error) -> Self {
+        RepositoryError::Mongo(err)
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for RepositoryError {
+    fn from(err: mongodb::bson::ser::Error) -> Self {
+        RepositoryError::Serialize(err)
+    }
+}
+
+impl From<mongodb::bson::de::Error> for RepositoryError {
+    fn from(err: mongodb::bson::de::Error) -> Self {
+        RepositoryError::Deserialize(err)
+    }
+}
+
+impl From<RepositoryError> for StatusCode {
+    fn from(err: RepositoryError) -> StatusCode {
+        eprintln!("Repository error: {}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// Storage backends used by the handlers, shared through an `Extension` layer.
+#[derive(Clone)]
+pub struct Repositories {
+    pub movies: Arc<dyn MovieRepository>,
+    pub halls: Arc<dyn HallRepository>,
+    pub hall_schedules: Arc<dyn HallScheduleRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub reservations: Arc<dyn ReservationRepository>,
+    pub bookings: Arc<dyn BookingRepository>,
+    pub price_lists: Arc<dyn PriceListRepository>,
+    pub promotions: Arc<dyn PromotionRepository>,
+    pub session_series: Arc<dyn SessionSeriesRepository>,
+    pub venues: Arc<dyn VenueRepository>,
+    pub users: Arc<dyn UserRepository>,
+    pub api_keys: Arc<dyn ApiKeyRepository>,
+}
+
+impl Repositories {
+    pub async fn mongo(client: Client, database_name: &str) -> RepositoryResult<Self> {
+        let repository = Arc::new(MongoRepository::new(client, database_name));
+        repository.create_indexes().await?;
+
+        Ok(Repositories {
+            movies: repository.clone(),
+            halls: repository.clone(),
+            hall_schedules: repository.clone(),
+            sessions: repository.clone(),
+            reservations: repository.clone(),
+            bookings: repository.clone(),
+            price_lists: repository.clone(),
+            promotions: repository.clone(),
+            session_series: repository.clone(),
+            venues: repository.clone(),
+            users: repository.clone(),
+            api_keys: repository,
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        let repository = Arc::new(InMemoryRepository::new());
+        Repositories {
+            movies: repository.clone(),
+            halls: repository.clone(),
+            hall_schedules: repository.clone(),
+            sessions: repository.clone(),
+            reservations: repository.clone(),
+            bookings: repository.clone(),
+            price_lists: repository.clone(),
+            promotions: repository.clone(),
+            session_series: repository.clone(),
+            venues: repository.clone(),
+            users: repository.clone(),
+            api_keys: repository,
+        }
+    }
+}
@@ -0,0 +1,1081 @@
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, from_document, from_slice, oid::ObjectId, to_bson, to_vec, Bson, DateTime, Document},
+    error::{ErrorKind, WriteFailure},
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    Client, Collection, Database, IndexModel,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::models::{
+    api_key_model::ApiKey,
+    booking_model::{Booking, BookingStatus},
+    hall_model::{Hall, HallDetail, HallUpdate},
+    hall_schedule_model::{HallClosure, HallOpeningHours},
+    movie_model::{Movie, MovieDetail, MovieUpdate},
+    price_list_model::PriceList,
+    promotion_model::{Promotion, PromotionRedemption, PromotionRejection},
+    reservation_model::{HoldInsertion, SeatReservation},
+    seat_layout_model::SeatLayout,
+    session_model::{Session, SessionDetail},
+    session_series_model::SessionSeries,
+    user_model::User,
+    venue_model::{Venue, VenueUpdate},
+};
+
+use super::{
+    ApiKeyRepository, BookingRepository, HallRepository, HallScheduleRepository, MovieRepository, PriceListRepository, PromotionRepository, RepositoryResult,
+    ReservationRepository, SessionRepository, SessionSeriesRepository, UserRepository, VenueRepository,
+};
+
+/// Database of the default tenant. Other tenants get it suffixed with their id.
+pub const DATABASE_NAME: &str = "cinema-axum";
+
+pub struct MongoRepository {
+    db: Database,
+}
+
+impl MongoRepository {
+    pub fn new(client: Client, database_name: &str) -> Self {
+        MongoRepository {
+            db: client.database(database_name),
+        }
+    }
+
+    fn movies(&self) -> Collection<Movie> {
+        self.db.collection::<Movie>("movies")
+    }
+
+    fn venues(&self) -> Collection<Venue> {
+        self.db.collection::<Venue>("venues")
+    }
+
+    fn halls(&self) -> Collection<Hall> {
+        self.db.collection::<Hall>("halls")
+    }
+
+    fn hall_opening_hours(&self) -> Collection<HallOpeningHours> {
+        self.db.collection::<HallOpeningHours>("hall_opening_hours")
+    }
+
+    fn hall_closures(&self) -> Collection<HallClosure> {
+        self.db.collection::<HallClosure>("hall_closures")
+    }
+
+    fn sessions(&self) -> Collection<Session> {
+        self.db.collection::<Session>("sessions")
+    }
+
+    fn reservations(&self) -> Collection<SeatReservation> {
+        self.db.collection::<SeatReservation>("seat_reservations")
+    }
+
+    fn bookings(&self) -> Collection<Booking> {
+        self.db.collection::<Booking>("bookings")
+    }
+
+    fn price_lists(&self) -> Collection<PriceList> {
+        self.db.collection::<PriceList>("price_lists")
+    }
+
+    fn promotions(&self) -> Collection<Promotion> {
+        self.db.collection::<Promotion>("promotions")
+    }
+
+    fn promotion_redemptions(&self) -> Collection<PromotionRedemption> {
+        self.db.collection::<PromotionRedemption>("promotion_redemptions")
+    }
+
+    fn session_series(&self) -> Collection<SessionSeries> {
+        self.db.collection::<SessionSeries>("session_series")
+    }
+
+    fn users(&self) -> Collection<User> {
+        self.db.collection::<User>("users")
+    }
+
+    fn api_keys(&self) -> Collection<ApiKey> {
+        self.db.collection::<ApiKey>("api_keys")
+    }
+
+    pub async fn create_indexes(&self) -> RepositoryResult<()> {
+        // A seat can only be taken once per session, which is what keeps
+        // concurrent holds from handing out the same seat twice.
+        let seat_index = IndexModel::builder()
+            .keys(doc! { "session_id": 1, "row": 1, "number": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.reservations().create_index(seat_index, None).await?;
+
+        let hold_index = IndexModel::builder().keys(doc! { "hold_id": 1 }).build();
+        self.reservations().create_index(hold_index, None).await?;
+
+        let booking_hold_index = IndexModel::builder()
+            .keys(doc! { "hold_id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.bookings().create_index(booking_hold_index, None).await?;
+
+        let booking_session_index = IndexModel::builder().keys(doc! { "session_id": 1 }).build();
+        self.bookings().create_index(booking_session_index, None).await?;
+
+        let promotion_code_index = IndexModel::builder()
+            .keys(doc! { "code": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.promotions().create_index(promotion_code_index, None).await?;
+
+        // One counter per buyer, so a limited redemption can be an upsert that fails
+        // on the duplicate key once the limit is reached.
+        let redemption_index = IndexModel::builder()
+            .keys(doc! { "promotion_id": 1, "customer": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.promotion_redemptions().create_index(redemption_index, None).await?;
+
+        let session_series_index = IndexModel::builder().keys(doc! { "series_id": 1 }).build();
+        self.sessions().create_index(session_series_index, None).await?;
+
+        let hall_venue_index = IndexModel::builder().keys(doc! { "venue_id": 1 }).build();
+        self.halls().create_index(hall_venue_index, None).await?;
+
+        let opening_hours_index = IndexModel::builder().keys(doc! { "hall_id": 1 }).build();
+        self.hall_opening_hours().create_index(opening_hours_index, None).await?;
+
+        let closure_index = IndexModel::builder().keys(doc! { "hall_id": 1, "start": 1 }).build();
+        self.hall_closures().create_index(closure_index, None).await?;
+
+        let user_email_index = IndexModel::builder()
+            .keys(doc! { "email": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.users().create_index(user_email_index, None).await?;
+
+        let api_key_hash_index = IndexModel::builder()
+            .keys(doc! { "key_hash": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.api_keys().create_index(api_key_hash_index, None).await?;
+
+        Ok(())
+    }
+
+    /// Removes the expired holds matching `filter` and returns them. Each seat is removed only
+    /// if it is still expired, so a hold confirmed or released since it was read is neither
+    /// deleted nor reported.
+    async fn delete_expired_reservations(&self, filter: Document, now: DateTime) -> RepositoryResult<Vec<SeatReservation>> {
+        let expired: Vec<SeatReservation> = self
+            .reservations()
+            .find(filter, None)
+            .await?
+            .try_collect()
+            .await?;
+
+        let mut deleted = Vec::new();
+        for id in expired.into_iter().filter_map(|reservation| reservation.id) {
+            if let Some(reservation) = self
+                .reservations()
+                .find_one_and_delete(doc! { "_id": id, "expires_at": { "$lte": now } }, None)
+                .await?
+            {
+                deleted.push(reservation);
+            }
+        }
+
+        Ok(deleted)
+    }
+}
+
+pub(crate) fn is_duplicate_key_error(err: &mongodb::error::Error) -> bool {
+    const DUPLICATE_KEY: i32 = 11000;
+
+    match err.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == DUPLICATE_KEY,
+        ErrorKind::BulkWrite(failure) => failure
+            .write_errors
+            .iter()
+            .flatten()
+            .any(|e| e.code == DUPLICATE_KEY),
+        _ => false,
+    }
+}
+
+async fn aggregate<T, R>(
+    collection: &Collection<T>,
+    pipeline: Vec<Document>,
+) -> RepositoryResult<Vec<R>>
+where
+    R: DeserializeOwned,
+{
+    let mut cursor = collection.aggregate(pipeline, None).await?;
+
+    let mut result: Vec<R> = Vec::new();
+    while let Some(doc) = cursor.try_next().await? {
+        result.push(from_document(doc)?);
+    }
+
+    Ok(result)
+}
+
+/// Builds a `$set` body from the fields of `update` that are not null.
+fn set_document<T: Serialize>(update: &T) -> Document {
+    let json = serde_json::to_value(update).unwrap_or_else(|_| Value::Object(Default::default()));
+
+    let mut update_doc = Document::new();
+    if let Value::Object(obj) = json {
+        for (key, value) in obj {
+            if !value.is_null() {
+                let bson_value = match Bson::try_from(value) {
+                    Ok(bv) => bv,
+                    Err(_) => continue,
+                };
+                update_doc.insert(key, bson_value);
+            }
+        }
+    }
+
+    update_doc
+}
+
+fn session_details_pipeline() -> Vec<Document> {
+    vec![
+        doc! {
+            "$lookup": {
+                "from": "movies",
+                "localField": "movie_id",
+                "foreignField": "_id",
+                "as": "movie",
+            },
+        },
+        doc! {
+            "$lookup": {
+                "from": "halls",
+                "localField": "hall_id",
+                "foreignField": "_id",
+                "as": "hall"
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$movie",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
+        doc! {
+            "$unwind": {
+                "path": "$hall",
+                "preserveNullAndEmptyArrays": true
+            }
+        },
+    ]
+}
+
+#[async_trait]
+impl MovieRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Movie>> {
+        aggregate(&self.movies(), vec![]).await
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Movie>> {
+        Ok(self.movies().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<MovieDetail>> {
+        let pipeline = vec![
+            doc! {
+                "$match": { "_id": id }
+            },
+            doc! {
+                "$lookup": {
+                    "from": "sessions",
+                    "localField": "_id",
+                    "foreignField": "movie_id",
+                    "as": "sessions"
+                }
+            },
+            doc! {
+                "$lookup": {
+                    "from": "halls",
+                    "let": { "hall_id": "$sessions.hall_id" },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$in": [ "$_id", "$$hall_id" ] } } }
+                    ],
+                    "as": "halls"
+                }
+            },
+        ];
+
+        Ok(aggregate(&self.movies(), pipeline).await?.into_iter().next())
+    }
+
+    async fn insert(&self, mut movie: Movie) -> RepositoryResult<Movie> {
+        let insert_result = self.movies().insert_one(&movie, None).await?;
+        movie.id = insert_result.inserted_id.as_object_id();
+        Ok(movie)
+    }
+
+    async fn update(&self, id: ObjectId, update: &MovieUpdate) -> RepositoryResult<bool> {
+        let update = doc! {
+            "$set": set_document(update),
+        };
+
+        let update_result = self.movies().update_one(doc! {"_id": id}, update, None).await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.movies().delete_one(doc! {"_id": id}, None).await?;
+        Ok(delete_result.deleted_count == 1)
+    }
+}
+
+#[async_trait]
+impl HallRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Hall>> {
+        aggregate(&self.halls(), vec![]).await
+    }
+
+    async fn list_for_venue(&self, venue_id: ObjectId) -> RepositoryResult<Vec<Hall>> {
+        Ok(self
+            .halls()
+            .find(doc! {"venue_id": venue_id}, None)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Hall>> {
+        Ok(self.halls().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<HallDetail>> {
+        let pipeline = vec![
+            doc! {
+                "$match": { "_id": id }
+            },
+            doc! {
+                "$lookup": {
+                    "from": "sessions",
+                    "localField": "_id",
+                    "foreignField": "hall_id",
+                    "as": "sessions"
+                }
+            },
+            doc! {
+                "$lookup": {
+                    "from": "movies",
+                    "let": { "movie_id": "$sessions.movie_id" },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$in": [ "$_id", "$$movie_id" ] } } }
+                    ],
+                    "as": "movies"
+                }
+            },
+        ];
+
+        Ok(aggregate(&self.halls(), pipeline).await?.into_iter().next())
+    }
+
+    async fn insert(&self, mut hall: Hall) -> RepositoryResult<Hall> {
+        let insert_result = self.halls().insert_one(&hall, None).await?;
+        hall.id = insert_result.inserted_id.as_object_id();
+        Ok(hall)
+    }
+
+    async fn update(&self, id: ObjectId, update: &HallUpdate) -> RepositoryResult<bool> {
+        let mut fields = set_document(update);
+        // `set_document` goes through JSON, which would store the id as a hex string.
+        if let Some(venue_id) = update.venue_id {
+            fields.insert("venue_id", venue_id);
+        }
+        let update = doc! {
+            "$set": fields,
+        };
+
+        let update_result = self.halls().update_one(doc! {"_id": id}, update, None).await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn set_layout(&self, id: ObjectId, layout: Option<&SeatLayout>) -> RepositoryResult<bool> {
+        let update = match layout {
+            Some(layout) => doc! { "$set": { "layout": to_bson(layout)? } },
+            None => doc! { "$unset": { "layout": "" } },
+        };
+
+        let update_result = self.halls().update_one(doc! {"_id": id}, update, None).await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.halls().delete_one(doc! {"_id": id}, None).await?;
+        if delete_result.deleted_count != 1 {
+            return Ok(false);
+        }
+
+        let update_result = self
+            .sessions()
+            .update_many(
+                // Older sessions were stored with the hall id as a hex string.
+                doc! {"hall_id": {"$in": [id, id.to_hex()]}},
+                doc! {"$set": {"hall_id": null}},
+                None,
+            )
+            .await?;
+        println!("Detached {} session(s) from hall {}.", update_result.modified_count, id);
+
+        self.hall_opening_hours().delete_many(doc! {"hall_id": id}, None).await?;
+        self.hall_closures().delete_many(doc! {"hall_id": id}, None).await?;
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl HallScheduleRepository for MongoRepository {
+    async fn list_opening_hours(&self, hall_id: ObjectId) -> RepositoryResult<Vec<HallOpeningHours>> {
+        Ok(self
+            .hall_opening_hours()
+            .find(doc! {"hall_id": hall_id}, None)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert_opening_hours(&self, mut hours: HallOpeningHours) -> RepositoryResult<HallOpeningHours> {
+        let insert_result = self.hall_opening_hours().insert_one(&hours, None).await?;
+        hours.id = insert_result.inserted_id.as_object_id();
+        Ok(hours)
+    }
+
+    async fn delete_opening_hours(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self
+            .hall_opening_hours()
+            .delete_one(doc! {"_id": id, "hall_id": hall_id}, None)
+            .await?;
+        Ok(delete_result.deleted_count == 1)
+    }
+
+    async fn list_closures(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<HallClosure>> {
+        let options = FindOptions::builder().sort(doc! { "start": 1 }).build();
+        let query = doc! {
+            "hall_id": hall_id,
+            "start": { "$lt": end },
+            "end": { "$gt": start },
+        };
+        Ok(self.hall_closures().find(query, options).await?.try_collect().await?)
+    }
+
+    async fn insert_closure(&self, mut closure: HallClosure) -> RepositoryResult<HallClosure> {
+        let insert_result = self.hall_closures().insert_one(&closure, None).await?;
+        closure.id = insert_result.inserted_id.as_object_id();
+        Ok(closure)
+    }
+
+    async fn delete_closure(&self, hall_id: ObjectId, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self
+            .hall_closures()
+            .delete_one(doc! {"_id": id, "hall_id": hall_id}, None)
+            .await?;
+        Ok(delete_result.deleted_count == 1)
+    }
+}
+
+#[async_trait]
+impl SessionRepository for MongoRepository {
+    async fn list_with_details(&self) -> RepositoryResult<Vec<SessionDetail>> {
+        let mut pipeline = session_details_pipeline();
+        pipeline.push(doc! {
+            "$project": {
+                "movie.description": 0,
+                "movie.poster": 0,
+            }
+        });
+
+        aggregate(&self.sessions(), pipeline).await
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Session>> {
+        Ok(self.sessions().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<SessionDetail>> {
+        let mut pipeline = vec![doc! {
+            "$match": {
+                "_id": id,
+            }
+        }];
+        pipeline.extend(session_details_pipeline());
+
+        Ok(aggregate(&self.sessions(), pipeline).await?.into_iter().next())
+    }
+
+    async fn list_for_series(&self, series_id: ObjectId) -> RepositoryResult<Vec<Session>> {
+        let options = FindOptions::builder().sort(doc! { "start": 1 }).build();
+        Ok(self
+            .sessions()
+            .find(doc! { "series_id": series_id }, options)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn list_for_hall(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<Session>> {
+        let options = FindOptions::builder().sort(doc! { "start": 1 }).build();
+        let query = doc! {
+            "hall_id": hall_id,
+            "start": { "$lt": end },
+            "end": { "$gt": start },
+        };
+        Ok(self.sessions().find(query, options).await?.try_collect().await?)
+    }
+
+    async fn count_overlapping(
+        &self,
+        hall_id: ObjectId,
+        start: DateTime,
+        end: DateTime,
+        exclude_id: Option<ObjectId>,
+    ) -> RepositoryResult<u64> {
+        let mut query = doc! {
+            "hall_id": hall_id,
+            "$and": [
+                { "start": { "$lt": end } },
+                { "end": { "$gt": start } },
+            ],
+        };
+
+        if let Some(exclude_id) = exclude_id {
+            query.insert("_id", doc! { "$ne": exclude_id });
+        }
+
+        Ok(self.sessions().count_documents(query, None).await?)
+    }
+
+    async fn insert(&self, mut session: Session) -> RepositoryResult<Session> {
+        let insert_result = self.sessions().insert_one(&session, None).await?;
+        session.id = insert_result.inserted_id.as_object_id();
+        Ok(session)
+    }
+
+    async fn insert_many(&self, mut sessions: Vec<Session>) -> RepositoryResult<Vec<Session>> {
+        if sessions.is_empty() {
+            return Ok(sessions);
+        }
+
+        let insert_result = self.sessions().insert_many(&sessions, None).await?;
+        for (index, session) in sessions.iter_mut().enumerate() {
+            session.id = insert_result.inserted_ids.get(&index).and_then(|id| id.as_object_id());
+        }
+        Ok(sessions)
+    }
+
+    async fn replace(&self, session: &Session) -> RepositoryResult<bool> {
+        let update_result = self
+            .sessions()
+            .replace_one(doc! {"_id": session.id}, session, None)
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.sessions().delete_one(doc! {"_id": id}, None).await?;
+        Ok(delete_result.deleted_count == 1)
+    }
+}
+
+#[async_trait]
+impl ReservationRepository for MongoRepository {
+    async fn list_for_session(&self, session_id: ObjectId, now: DateTime) -> RepositoryResult<Vec<SeatReservation>> {
+        let filter = doc! {
+            "session_id": session_id,
+            "$or": [
+                { "expires_at": { "$exists": false } },
+                { "expires_at": { "$gt": now } },
+            ],
+        };
+
+        Ok(self.reservations().find(filter, None).await?.try_collect().await?)
+    }
+
+    async fn list_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Vec<SeatReservation>> {
+        Ok(self
+            .reservations()
+            .find(doc! { "hold_id": hold_id }, None)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn insert_hold(&self, reservations: Vec<SeatReservation>, now: DateTime) -> RepositoryResult<HoldInsertion> {
+        let Some(first) = reservations.first() else {
+            return Ok(HoldInsertion {
+                placed: true,
+                displaced: Vec::new(),
+            });
+        };
+        let (session_id, hold_id) = (first.session_id, first.hold_id);
+
+        // Expired holds still occupy the unique index until the sweeper removes them.
+        let seats: Vec<Document> = reservations
+            .iter()
+            .map(|reservation| doc! { "row": &reservation.row, "number": reservation.number })
+            .collect();
+        let displaced = self
+            .delete_expired_reservations(
+                doc! {
+                    "session_id": session_id,
+                    "expires_at": { "$lte": now },
+                    "$or": seats,
+                },
+                now,
+            )
+            .await?;
+
+        match self.reservations().insert_many(&reservations, None).await {
+            Ok(_) => Ok(HoldInsertion {
+                placed: true,
+                displaced,
+            }),
+            Err(e) if is_duplicate_key_error(&e) => {
+                self.reservations()
+                    .delete_many(doc! { "hold_id": hold_id }, None)
+                    .await?;
+                Ok(HoldInsertion {
+                    placed: false,
+                    displaced,
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn confirm_hold(&self, hold_id: ObjectId, now: DateTime) -> RepositoryResult<bool> {
+        let update_result = self
+            .reservations()
+            .update_many(
+                doc! { "hold_id": hold_id, "expires_at": { "$gt": now } },
+                doc! { "$unset": { "expires_at": "" } },
+                None,
+            )
+            .await?;
+        Ok(update_result.matched_count > 0)
+    }
+
+    async fn release_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self
+            .reservations()
+            .delete_many(doc! { "hold_id": hold_id, "expires_at": { "$exists": true } }, None)
+            .await?;
+        Ok(delete_result.deleted_count > 0)
+    }
+
+    async fn delete_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self
+            .reservations()
+            .delete_many(doc! { "hold_id": hold_id }, None)
+            .await?;
+        Ok(delete_result.deleted_count > 0)
+    }
+
+    async fn delete_for_session(&self, session_id: ObjectId) -> RepositoryResult<u64> {
+        let delete_result = self
+            .reservations()
+            .delete_many(doc! { "session_id": session_id }, None)
+            .await?;
+        Ok(delete_result.deleted_count)
+    }
+
+    async fn purge_expired(&self, now: DateTime) -> RepositoryResult<Vec<SeatReservation>> {
+        self.delete_expired_reservations(doc! { "expires_at": { "$lte": now } }, now)
+            .await
+    }
+}
+
+#[async_trait]
+impl BookingRepository for MongoRepository {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Booking>> {
+        Ok(self.bookings().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn list_for_session(&self, session_id: ObjectId) -> RepositoryResult<Vec<Booking>> {
+        Ok(self
+            .bookings()
+            .find(doc! { "session_id": session_id }, None)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    async fn find_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Option<Booking>> {
+        Ok(self.bookings().find_one(doc! { "hold_id": hold_id }, None).await?)
+    }
+
+    async fn insert(&self, mut booking: Booking) -> RepositoryResult<Option<Booking>> {
+        match self.bookings().insert_one(&booking, None).await {
+            Ok(insert_result) => {
+                booking.id = insert_result.inserted_id.as_object_id();
+                Ok(Some(booking))
+            }
+            Err(e) if is_duplicate_key_error(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn update_status(
+        &self,
+        id: ObjectId,
+        from: BookingStatus,
+        to: BookingStatus,
+        now: DateTime,
+    ) -> RepositoryResult<bool> {
+        let update_result = self
+            .bookings()
+            .update_one(
+                doc! { "_id": id, "status": to_bson(&from)? },
+                doc! { "$set": { "status": to_bson(&to)?, "updated_at": now } },
+                None,
+            )
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn check_in_ticket(
+        &self,
+        booking_id: ObjectId,
+        ticket_id: ObjectId,
+        now: DateTime,
+    ) -> RepositoryResult<bool> {
+        let update_result = self
+            .bookings()
+            .update_one(
+                doc! {
+                    "_id": booking_id,
+                    "tickets": { "$elemMatch": { "id": ticket_id, "checked_in_at": null } },
+                },
+                doc! { "$set": { "tickets.$.checked_in_at": now, "updated_at": now } },
+                None,
+            )
+            .await?;
+        Ok(update_result.modified_count == 1)
+    }
+}
+
+#[async_trait]
+impl PriceListRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<PriceList>> {
+        Ok(self.price_lists().find(None, None).await?.try_collect().await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<PriceList>> {
+        Ok(self.price_lists().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn insert(&self, mut price_list: PriceList) -> RepositoryResult<PriceList> {
+        let insert_result = self.price_lists().insert_one(&price_list, None).await?;
+        price_list.id = insert_result.inserted_id.as_object_id();
+        Ok(price_list)
+    }
+
+    async fn replace(&self, price_list: &PriceList) -> RepositoryResult<bool> {
+        let update_result = self
+            .price_lists()
+            .replace_one(doc! {"_id": price_list.id}, price_list, None)
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.price_lists().delete_one(doc! {"_id": id}, None).await?;
+        if delete_result.deleted_count != 1 {
+            return Ok(false);
+        }
+
+        self.sessions()
+            .update_many(doc! {"price_list_id": id}, doc! {"$unset": {"price_list_id": ""}}, None)
+            .await?;
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl PromotionRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Promotion>> {
+        Ok(self.promotions().find(None, None).await?.try_collect().await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Promotion>> {
+        Ok(self.promotions().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Promotion>> {
+        Ok(self.promotions().find_one(doc! {"code": code}, None).await?)
+    }
+
+    async fn insert(&self, mut promotion: Promotion) -> RepositoryResult<Option<Promotion>> {
+        match self.promotions().insert_one(&promotion, None).await {
+            Ok(insert_result) => {
+                promotion.id = insert_result.inserted_id.as_object_id();
+                Ok(Some(promotion))
+            }
+            Err(e) if is_duplicate_key_error(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn replace(&self, promotion: &Promotion) -> RepositoryResult<bool> {
+        // Serialized the way `insert_one` does, so ids stay ObjectIds instead of the hex
+        // strings the human-readable serializer writes for API responses.
+        let mut fields: Document = from_slice(&to_vec(promotion)?)?;
+        fields.remove("_id");
+        fields.remove("uses");
+
+        // A pipeline update so the stored counter survives while every other field is
+        // replaced. `$literal` keeps values starting with `$` from being read as paths.
+        let pipeline = vec![doc! {
+            "$replaceWith": {
+                "$mergeObjects": [
+                    { "$literal": fields },
+                    { "_id": "$_id", "uses": "$uses" },
+                ]
+            }
+        }];
+        let update_result = self
+            .promotions()
+            .update_one(doc! {"_id": promotion.id}, pipeline, None)
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.promotions().delete_one(doc! {"_id": id}, None).await?;
+        if delete_result.deleted_count != 1 {
+            return Ok(false);
+        }
+
+        self.promotion_redemptions()
+            .delete_many(doc! {"promotion_id": id}, None)
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn customer_uses(&self, id: ObjectId, customer: &str) -> RepositoryResult<u32> {
+        let redemption = self
+            .promotion_redemptions()
+            .find_one(doc! {"promotion_id": id, "customer": customer}, None)
+            .await?;
+        Ok(redemption.map_or(0, |redemption| redemption.uses))
+    }
+
+    async fn redeem(&self, promotion: &Promotion, customer: &str) -> RepositoryResult<Option<PromotionRejection>> {
+        let Some(id) = promotion.id else {
+            return Ok(Some(PromotionRejection::UnknownCode));
+        };
+
+        let mut filter = doc! {"_id": id};
+        if let Some(max_uses) = promotion.max_uses {
+            filter.insert("uses", doc! { "$lt": max_uses as i64 });
+        }
+        let update_result = self
+            .promotions()
+            .update_one(filter, doc! {"$inc": {"uses": 1}}, None)
+            .await?;
+        if update_result.matched_count == 0 {
+            return Ok(Some(PromotionRejection::UsageLimitReached));
+        }
+
+        let mut filter = doc! {"promotion_id": id, "customer": customer};
+        if let Some(max_uses) = promotion.max_uses_per_customer {
+            filter.insert("uses", doc! { "$lt": max_uses as i64 });
+        }
+        let counted = self
+            .promotion_redemptions()
+            .update_one(
+                filter,
+                doc! {"$inc": {"uses": 1}},
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await;
+        match counted {
+            Ok(_) => Ok(None),
+            Err(e) if is_duplicate_key_error(&e) => {
+                // The buyer is at their limit, so the total use taken above goes back.
+                self.promotions()
+                    .update_one(doc! {"_id": id}, doc! {"$inc": {"uses": -1}}, None)
+                    .await?;
+                Ok(Some(PromotionRejection::CustomerLimitReached))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn release(&self, id: ObjectId, customer: &str) -> RepositoryResult<()> {
+        self.promotions()
+            .update_one(
+                doc! {"_id": id, "uses": {"$gt": 0}},
+                doc! {"$inc": {"uses": -1}},
+                None,
+            )
+            .await?;
+        self.promotion_redemptions()
+            .update_one(
+                doc! {"promotion_id": id, "customer": customer, "uses": {"$gt": 0}},
+                doc! {"$inc": {"uses": -1}},
+                None,
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionSeriesRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<SessionSeries>> {
+        Ok(self.session_series().find(None, None).await?.try_collect().await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<SessionSeries>> {
+        Ok(self.session_series().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn insert(&self, mut series: SessionSeries) -> RepositoryResult<SessionSeries> {
+        let insert_result = self.session_series().insert_one(&series, None).await?;
+        series.id = insert_result.inserted_id.as_object_id();
+        Ok(series)
+    }
+
+    async fn replace(&self, series: &SessionSeries) -> RepositoryResult<bool> {
+        let update_result = self
+            .session_series()
+            .replace_one(doc! {"_id": series.id}, series, None)
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.session_series().delete_one(doc! {"_id": id}, None).await?;
+        if delete_result.deleted_count != 1 {
+            return Ok(false);
+        }
+
+        self.sessions()
+            .update_many(doc! {"series_id": id}, doc! {"$unset": {"series_id": ""}}, None)
+            .await?;
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl VenueRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<Venue>> {
+        Ok(self.venues().find(None, None).await?.try_collect().await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Venue>> {
+        Ok(self.venues().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn insert(&self, mut venue: Venue) -> RepositoryResult<Venue> {
+        let insert_result = self.venues().insert_one(&venue, None).await?;
+        venue.id = insert_result.inserted_id.as_object_id();
+        Ok(venue)
+    }
+
+    async fn update(&self, id: ObjectId, update: &VenueUpdate) -> RepositoryResult<bool> {
+        let update = doc! {
+            "$set": set_document(update),
+        };
+
+        let update_result = self.venues().update_one(doc! {"_id": id}, update, None).await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool> {
+        let delete_result = self.venues().delete_one(doc! {"_id": id}, None).await?;
+        if delete_result.deleted_count != 1 {
+            return Ok(false);
+        }
+
+        self.halls()
+            .update_many(doc! {"venue_id": id}, doc! {"$unset": {"venue_id": ""}}, None)
+            .await?;
+
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl UserRepository for MongoRepository {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<User>> {
+        Ok(self.users().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>> {
+        Ok(self.users().find_one(doc! {"email": email}, None).await?)
+    }
+
+    async fn insert(&self, mut user: User) -> RepositoryResult<Option<User>> {
+        match self.users().insert_one(&user, None).await {
+            Ok(insert_result) => {
+                user.id = insert_result.inserted_id.as_object_id();
+                Ok(Some(user))
+            }
+            Err(e) if is_duplicate_key_error(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for MongoRepository {
+    async fn list(&self) -> RepositoryResult<Vec<ApiKey>> {
+        let options = FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+        Ok(self.api_keys().find(None, options).await?.try_collect().await?)
+    }
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<ApiKey>> {
+        Ok(self.api_keys().find_one(doc! {"_id": id}, None).await?)
+    }
+
+    async fn find_by_hash(&self, key_hash: &str) -> RepositoryResult<Option<ApiKey>> {
+        Ok(self.api_keys().find_one(doc! {"key_hash": key_hash}, None).await?)
+    }
+
+    async fn insert(&self, mut api_key: ApiKey) -> RepositoryResult<ApiKey> {
+        let insert_result = self.api_keys().insert_one(&api_key, None).await?;
+        api_key.id = insert_result.inserted_id.as_object_id();
+        Ok(api_key)
+    }
+
+    async fn rotate(&self, id: ObjectId, prefix: &str, key_hash: &str, now: DateTime) -> RepositoryResult<bool> {
+        let update_result = self
+            .api_keys()
+            .update_one(
+                doc! {"_id": id, "revoked_at": null},
+                doc! {"$set": {"prefix": prefix, "key_hash": key_hash, "rotated_at": now}},
+                None,
+            )
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn revoke(&self, id: ObjectId, now: DateTime) -> RepositoryResult<bool> {
+        let update_result = self
+            .api_keys()
+            .update_one(doc! {"_id": id, "revoked_at": null}, doc! {"$set": {"revoked_at": now}}, None)
+            .await?;
+        Ok(update_result.matched_count == 1)
+    }
+
+    async fn record_use(&self, id: ObjectId, now: DateTime) -> RepositoryResult<()> {
+        self.api_keys()
+            .update_one(doc! {"_id": id}, doc! {"$inc": {"uses": 1}, "$set": {"last_used_at": now}}, None)
+            .await?;
+        Ok(())
+    }
+}
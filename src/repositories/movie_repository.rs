@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::movie_model::{Movie, MovieDetail, MovieUpdate};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait MovieRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<Movie>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Movie>>;
+
+    /// Movie joined with its sessions and the halls those sessions are played in.
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<MovieDetail>>;
+
+    /// Stores the movie and returns it with its generated id.
+    async fn insert(&self, movie: Movie) -> RepositoryResult<Movie>;
+
+    /// Applies the non-empty fields of `update`. Returns `false` if no movie matched.
+    async fn update(&self, id: ObjectId, update: &MovieUpdate) -> RepositoryResult<bool>;
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::price_list_model::PriceList;
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait PriceListRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<PriceList>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<PriceList>>;
+
+    /// Stores the price list and returns it with its generated id.
+    async fn insert(&self, price_list: PriceList) -> RepositoryResult<PriceList>;
+
+    /// Overwrites the stored price list with the same id. Returns `false` if none matched.
+    async fn replace(&self, price_list: &PriceList) -> RepositoryResult<bool>;
+
+    /// Removes the price list and clears `price_list_id` on its sessions.
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::promotion_model::{Promotion, PromotionRejection};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait PromotionRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<Promotion>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Promotion>>;
+
+    async fn find_by_code(&self, code: &str) -> RepositoryResult<Option<Promotion>>;
+
+    /// Stores the promotion and returns it with its generated id, or `None` when
+    /// the code is already taken.
+    async fn insert(&self, promotion: Promotion) -> RepositoryResult<Option<Promotion>>;
+
+    /// Overwrites the stored promotion with the same id, keeping its usage counter.
+    /// Returns `false` if none matched.
+    async fn replace(&self, promotion: &Promotion) -> RepositoryResult<bool>;
+
+    /// Removes the promotion and its redemption counters.
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+
+    /// How many bookings of `customer` currently use the promotion.
+    async fn customer_uses(&self, id: ObjectId, customer: &str) -> RepositoryResult<u32>;
+
+    /// Counts one use of the promotion by `customer` if neither the total nor the
+    /// per-customer limit is reached. Returns the limit that stopped it otherwise.
+    async fn redeem(&self, promotion: &Promotion, customer: &str) -> RepositoryResult<Option<PromotionRejection>>;
+
+    /// Gives back a use taken by `redeem`, e.g. when the booking is cancelled.
+    async fn release(&self, id: ObjectId, customer: &str) -> RepositoryResult<()>;
+}
@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::reservation_model::{HoldInsertion, SeatReservation};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait ReservationRepository: Send + Sync {
+    /// Active reservations of a session: confirmed seats and holds that have not expired yet.
+    async fn list_for_session(&self, session_id: ObjectId, now: DateTime) -> RepositoryResult<Vec<SeatReservation>>;
+
+    async fn list_for_hold(&self, hold_id: ObjectId) -> RepositoryResult<Vec<SeatReservation>>;
+
+    /// Stores all `reservations` or none of them, failing when any of the seats is already
+    /// taken by an active reservation. Expired holds on the seats are removed and reported.
+    async fn insert_hold(&self, reservations: Vec<SeatReservation>, now: DateTime) -> RepositoryResult<HoldInsertion>;
+
+    /// Makes an unexpired hold permanent. Returns `false` if the hold is unknown or expired.
+    async fn confirm_hold(&self, hold_id: ObjectId, now: DateTime) -> RepositoryResult<bool>;
+
+    /// Drops an unconfirmed hold. Returns `false` if there was nothing to release.
+    async fn release_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool>;
+
+    /// Removes every seat of the hold, confirmed or not. Returns `false` if there was nothing to remove.
+    async fn delete_hold(&self, hold_id: ObjectId) -> RepositoryResult<bool>;
+
+    /// Removes every seat taken for the session and returns how many there were.
+    async fn delete_for_session(&self, session_id: ObjectId) -> RepositoryResult<u64>;
+
+    /// Removes holds that expired at or before `now` and returns them.
+    async fn purge_expired(&self, now: DateTime) -> RepositoryResult<Vec<SeatReservation>>;
+}
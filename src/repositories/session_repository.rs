@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+
+use crate::models::session_model::{Session, SessionDetail};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    /// All sessions joined with their movie and hall. Movie description and poster are left out.
+    async fn list_with_details(&self) -> RepositoryResult<Vec<SessionDetail>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Session>>;
+
+    async fn find_with_details(&self, id: ObjectId) -> RepositoryResult<Option<SessionDetail>>;
+
+    /// Sessions generated from the series, ordered by start.
+    async fn list_for_series(&self, series_id: ObjectId) -> RepositoryResult<Vec<Session>>;
+
+    /// Sessions in the hall overlapping `[start, end)`, ordered by start.
+    async fn list_for_hall(&self, hall_id: ObjectId, start: DateTime, end: DateTime) -> RepositoryResult<Vec<Session>>;
+
+    /// Number of sessions in the hall overlapping `[start, end)`, ignoring `exclude_id`.
+    async fn count_overlapping(
+        &self,
+        hall_id: ObjectId,
+        start: DateTime,
+        end: DateTime,
+        exclude_id: Option<ObjectId>,
+    ) -> RepositoryResult<u64>;
+
+    /// Stores the session and returns it with its generated id.
+    async fn insert(&self, session: Session) -> RepositoryResult<Session>;
+
+    /// Stores the sessions in a single write and returns them in order with their generated ids.
+    async fn insert_many(&self, sessions: Vec<Session>) -> RepositoryResult<Vec<Session>>;
+
+    /// Overwrites the stored session with the same id. Returns `false` if none matched.
+    async fn replace(&self, session: &Session) -> RepositoryResult<bool>;
+
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::session_series_model::SessionSeries;
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait SessionSeriesRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<SessionSeries>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<SessionSeries>>;
+
+    /// Stores the series and returns it with its generated id.
+    async fn insert(&self, series: SessionSeries) -> RepositoryResult<SessionSeries>;
+
+    /// Overwrites the stored series with the same id. Returns `false` if none matched.
+    async fn replace(&self, series: &SessionSeries) -> RepositoryResult<bool>;
+
+    /// Removes the series and clears `series_id` on the sessions left from it.
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::user_model::User;
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<User>>;
+
+    /// Looks a user up by their normalized email.
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>>;
+
+    /// Stores the user and returns it with its generated id, or `None` when the email
+    /// is already taken.
+    async fn insert(&self, user: User) -> RepositoryResult<Option<User>>;
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+
+use crate::models::venue_model::{Venue, VenueUpdate};
+
+use super::RepositoryResult;
+
+#[async_trait]
+pub trait VenueRepository: Send + Sync {
+    async fn list(&self) -> RepositoryResult<Vec<Venue>>;
+
+    async fn find(&self, id: ObjectId) -> RepositoryResult<Option<Venue>>;
+
+    /// Stores the venue and returns it with its generated id.
+    async fn insert(&self, venue: Venue) -> RepositoryResult<Venue>;
+
+    /// Applies the non-empty fields of `update`. Returns `false` if no venue matched.
+    async fn update(&self, id: ObjectId, update: &VenueUpdate) -> RepositoryResult<bool>;
+
+    /// Removes the venue and clears `venue_id` on its halls.
+    async fn delete(&self, id: ObjectId) -> RepositoryResult<bool>;
+}
@@ -0,0 +1,136 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use chrono::{NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use mongodb::bson::DateTime;
+use shuttle_runtime::SecretStore;
+
+use crate::models::{hall_model::Hall, venue_model::Venue};
+
+/// Tunables read from `Secrets.toml`. Every value has a default so only overrides need to be set.
+#[derive(Clone, Debug)]
+pub struct Settings {
+    /// How long seats stay held before they are released again, in seconds.
+    pub seat_hold_ttl_seconds: i64,
+    /// IANA time zone of halls that neither set one nor belong to a venue that does.
+    pub time_zone: Tz,
+    /// How many minutes before a session starts tickets are let in.
+    pub checkin_opens_minutes: i64,
+    /// Minutes of ads and trailers before the film, for halls that do not set their own.
+    pub pre_show_minutes: i64,
+    /// Minutes a hall needs to be cleaned after a session, for halls that do not set their own.
+    pub cleaning_minutes: i64,
+    /// How long an access token issued at login stays valid, in minutes.
+    pub access_token_ttl_minutes: i64,
+    /// Messages that can wait to be sent to a WebSocket client before `ws_overflow_policy` applies.
+    pub ws_queue_len: usize,
+    pub ws_overflow_policy: OverflowPolicy,
+    /// How often WebSocket clients are pinged, in seconds.
+    pub ws_ping_interval_seconds: u64,
+    /// How long a WebSocket client can go without sending anything, pongs included, before
+    /// it is disconnected, in seconds.
+    pub ws_client_timeout_seconds: u64,
+    pub broadcast_backend: BroadcastBackend,
+}
+
+/// How WebSocket events reach the clients connected to other instances of the API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BroadcastBackend {
+    /// They do not. Enough when a single instance runs.
+    InProcess,
+    /// Through a capped collection in the tenant's database, which every instance tails.
+    Mongo,
+}
+
+impl FromStr for BroadcastBackend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, Self::Err> {
+        match backend {
+            "in_process" => Ok(BroadcastBackend::InProcess),
+            "mongodb" => Ok(BroadcastBackend::Mongo),
+            _ => Err(format!("unknown broadcast backend `{}`", backend)),
+        }
+    }
+}
+
+/// What happens when a WebSocket client does not read its messages as fast as they come.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The oldest queued message is dropped to make room.
+    DropOldest,
+    /// The client is disconnected and has to resume.
+    Disconnect,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "drop_oldest" => Ok(OverflowPolicy::DropOldest),
+            "disconnect" => Ok(OverflowPolicy::Disconnect),
+            _ => Err(format!("unknown overflow policy `{}`", policy)),
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_secrets(secret_store: &SecretStore) -> anyhow::Result<Self> {
+        Ok(Settings {
+            seat_hold_ttl_seconds: parse_secret(secret_store, "SEAT_HOLD_TTL_SECONDS", 600)?,
+            time_zone: parse_secret(secret_store, "TIME_ZONE", Tz::UTC)?,
+            checkin_opens_minutes: parse_secret(secret_store, "CHECKIN_OPENS_MINUTES", 30)?,
+            pre_show_minutes: parse_secret(secret_store, "PRE_SHOW_MINUTES", 15)?,
+            cleaning_minutes: parse_secret(secret_store, "CLEANING_MINUTES", 15)?,
+            access_token_ttl_minutes: parse_secret(secret_store, "ACCESS_TOKEN_TTL_MINUTES", 60)?,
+            ws_queue_len: parse_secret(secret_store, "WS_QUEUE_LEN", 256)?,
+            ws_overflow_policy: parse_secret(secret_store, "WS_OVERFLOW_POLICY", OverflowPolicy::DropOldest)?,
+            ws_ping_interval_seconds: parse_secret(secret_store, "WS_PING_INTERVAL_SECONDS", 20)?,
+            ws_client_timeout_seconds: parse_secret(secret_store, "WS_CLIENT_TIMEOUT_SECONDS", 60)?,
+            broadcast_backend: parse_secret(secret_store, "BROADCAST_BACKEND", BroadcastBackend::InProcess)?,
+        })
+    }
+
+    /// Time zone sessions in the hall run in: the hall's own, else its venue's, else `TIME_ZONE`.
+    pub fn hall_time_zone(&self, hall: &Hall, venue: Option<&Venue>) -> Tz {
+        hall.time_zone
+            .as_deref()
+            .and_then(|time_zone| time_zone.parse().ok())
+            .or_else(|| venue.and_then(Venue::tz))
+            .unwrap_or(self.time_zone)
+    }
+
+    pub fn pre_show_minutes(&self, hall: &Hall) -> i64 {
+        hall.pre_show_minutes.unwrap_or(self.pre_show_minutes)
+    }
+
+    pub fn cleaning_minutes(&self, hall: &Hall) -> i64 {
+        hall.cleaning_minutes.unwrap_or(self.cleaning_minutes)
+    }
+}
+
+/// Wall-clock time in `time_zone` for the given instant.
+pub fn local_time_in(time_zone: Tz, at: DateTime) -> NaiveDateTime {
+    at.to_chrono().with_timezone(&time_zone).naive_local()
+}
+
+/// Instant of a wall-clock time in `time_zone`. Returns `None` for times skipped by a
+/// daylight saving change and picks the earlier instant for repeated ones.
+pub fn instant_in(time_zone: Tz, local: NaiveDateTime) -> Option<DateTime> {
+    time_zone.from_local_datetime(&local).earliest().map(DateTime::from_chrono)
+}
+
+fn parse_secret<T>(secret_store: &SecretStore, key: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match secret_store.get(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|e| anyhow!("invalid value for {}: {}", key, e)),
+        None => Ok(default),
+    }
+}
@@ -0,0 +1,375 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Extension, Request},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use mongodb::Client;
+use shuttle_runtime::SecretStore;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::{
+    broadcast::{Broadcaster, InProcessBroadcaster, MongoBroadcaster},
+    controllers::reservation_controller::spawn_hold_sweeper,
+    events::{watch_changes, EventBus},
+    repositories::{mongo_repository::DATABASE_NAME, Repositories, RepositoryResult},
+    settings::{BroadcastBackend, Settings},
+    websockets::{spawn_event_dispatcher, SharedState},
+};
+
+/// Tenant used when the API runs for a single operator. Its data lives in the original database.
+pub const DEFAULT_TENANT: &str = "default";
+
+pub const TENANT_HEADER: &str = "x-tenant-id";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Cinema operator the API is hosted for. Ids are short lowercase slugs so they can
+/// double as subdomains and database name suffixes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tenant(String);
+
+impl Tenant {
+    pub fn parse(id: &str) -> Option<Self> {
+        let valid = (1..=32).contains(&id.len())
+            && !id.starts_with('-')
+            && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if valid {
+            Some(Tenant(id.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Every tenant gets a database of its own, so no query can reach another tenant's data.
+    pub fn database_name(&self) -> String {
+        if self.0 == DEFAULT_TENANT {
+            DATABASE_NAME.to_string()
+        } else {
+            format!("{}-{}", DATABASE_NAME, self.0)
+        }
+    }
+}
+
+impl fmt::Display for Tenant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// How requests are mapped to tenants, read from `Secrets.toml`.
+#[derive(Clone, Debug, Default)]
+pub struct TenantSettings {
+    /// Known tenants. Empty means the API serves the default tenant only.
+    tenants: Vec<Tenant>,
+    /// Domain tenant subdomains are under, e.g. `api.example.com` for `acme.api.example.com`.
+    base_domain: Option<String>,
+    api_keys: HashMap<String, Tenant>,
+}
+
+impl TenantSettings {
+    pub fn from_secrets(secret_store: &SecretStore) -> anyhow::Result<Self> {
+        let mut tenants = Vec::new();
+        for id in secret_store.get("TENANTS").unwrap_or_default().split(',').map(str::trim) {
+            if id.is_empty() {
+                continue;
+            }
+            tenants.push(Tenant::parse(id).ok_or_else(|| anyhow!("invalid tenant id in TENANTS: {}", id))?);
+        }
+
+        // `tenant:key` pairs separated by commas.
+        let mut api_keys = HashMap::new();
+        for pair in secret_store.get("TENANT_API_KEYS").unwrap_or_default().split(',').map(str::trim) {
+            if pair.is_empty() {
+                continue;
+            }
+            let (id, key) = pair
+                .split_once(':')
+                .ok_or_else(|| anyhow!("TENANT_API_KEYS entries must look like tenant:key"))?;
+            let tenant = Tenant::parse(id.trim())
+                .filter(|tenant| tenants.contains(tenant))
+                .ok_or_else(|| anyhow!("TENANT_API_KEYS refers to unknown tenant {}", id))?;
+            api_keys.insert(key.trim().to_string(), tenant);
+        }
+
+        Ok(TenantSettings {
+            tenants,
+            base_domain: secret_store.get("TENANT_BASE_DOMAIN").map(|domain| domain.to_lowercase()),
+            api_keys,
+        })
+    }
+
+    /// Tenants whose data has to be looked after even when no requests come in.
+    pub fn tenants(&self) -> Vec<Tenant> {
+        if self.tenants.is_empty() {
+            vec![Tenant(DEFAULT_TENANT.to_string())]
+        } else {
+            self.tenants.clone()
+        }
+    }
+
+    fn subdomain_tenant(&self, headers: &HeaderMap) -> Option<String> {
+        let base_domain = self.base_domain.as_deref()?;
+        let host = headers.get(header::HOST)?.to_str().ok()?.to_lowercase();
+        let host = host.split(':').next().unwrap_or_default();
+        let subdomain = host.strip_suffix(base_domain)?.strip_suffix('.')?;
+        Some(subdomain.to_string())
+    }
+
+    /// Works out the tenant from the API key, the `X-Tenant-Id` header or the subdomain.
+    /// When more than one is given they have to agree.
+    pub fn resolve(&self, headers: &HeaderMap) -> Result<Tenant, StatusCode> {
+        if self.tenants.is_empty() {
+            return Ok(Tenant(DEFAULT_TENANT.to_string()));
+        }
+
+        let mut candidates = Vec::new();
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            let key = key.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
+            candidates.push(self.api_keys.get(key).cloned().ok_or(StatusCode::UNAUTHORIZED)?);
+        }
+        let named = [
+            headers
+                .get(TENANT_HEADER)
+                .map(|value| value.to_str().unwrap_or_default().to_string()),
+            self.subdomain_tenant(headers),
+        ];
+        for id in named.into_iter().flatten() {
+            let tenant = Tenant::parse(&id).ok_or(StatusCode::BAD_REQUEST)?;
+            if !self.tenants.contains(&tenant) {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            candidates.push(tenant);
+        }
+
+        let tenant = candidates.first().cloned().ok_or(StatusCode::BAD_REQUEST)?;
+        if candidates.iter().any(|candidate| *candidate != tenant) {
+            // E.g. one tenant's API key used against another tenant's subdomain.
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(tenant)
+    }
+}
+
+enum Storage {
+    Mongo(Client),
+    Memory,
+}
+
+/// What a request for a tenant gets to work with.
+#[derive(Clone)]
+pub struct TenantContext {
+    pub repositories: Repositories,
+    pub shared_state: Arc<Mutex<SharedState>>,
+    pub events: EventBus,
+    pub broadcaster: Arc<dyn Broadcaster>,
+}
+
+/// Storage and WebSocket clients of every tenant, set up the first time the tenant is seen.
+pub struct TenantRegistry {
+    storage: Storage,
+    settings: Settings,
+    /// Each tenant is set up behind its own cell, so a slow or failing setup only holds up
+    /// requests for that tenant.
+    contexts: Mutex<HashMap<Tenant, Arc<OnceCell<TenantContext>>>>,
+}
+
+impl TenantRegistry {
+    pub fn mongo(client: Client, settings: Settings) -> Self {
+        TenantRegistry {
+            storage: Storage::Mongo(client),
+            settings,
+            contexts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn in_memory(settings: Settings) -> Self {
+        TenantRegistry {
+            storage: Storage::Memory,
+            settings,
+            contexts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn context(&self, tenant: &Tenant) -> RepositoryResult<TenantContext> {
+        let cell = self.contexts.lock().await.entry(tenant.clone()).or_default().clone();
+        // A failed setup leaves the cell empty, so the next request tries again.
+        let context = cell.get_or_try_init(|| self.set_up(tenant)).await?;
+
+        Ok(context.clone())
+    }
+
+    async fn set_up(&self, tenant: &Tenant) -> RepositoryResult<TenantContext> {
+        let repositories = match &self.storage {
+            Storage::Mongo(client) => Repositories::mongo(client.clone(), &tenant.database_name()).await?,
+            Storage::Memory => Repositories::in_memory(),
+        };
+        let (events, receiver) = EventBus::new();
+        let shared_state = Arc::new(Mutex::new(SharedState::new(&self.settings)));
+        let broadcaster: Arc<dyn Broadcaster> = match &self.storage {
+            Storage::Mongo(client) => {
+                let database = client.database(&tenant.database_name());
+                watch_changes(database.clone(), events.clone()).await;
+                match self.settings.broadcast_backend {
+                    BroadcastBackend::Mongo => Arc::new(MongoBroadcaster::start(&database, shared_state.clone()).await?),
+                    BroadcastBackend::InProcess => Arc::new(InProcessBroadcaster::new(shared_state.clone())),
+                }
+            }
+            Storage::Memory => Arc::new(InProcessBroadcaster::new(shared_state.clone())),
+        };
+        let context = TenantContext {
+            repositories,
+            shared_state,
+            events,
+            broadcaster,
+        };
+        spawn_hold_sweeper(context.repositories.clone(), context.broadcaster.clone());
+        spawn_event_dispatcher(
+            context.repositories.clone(),
+            self.settings.clone(),
+            context.broadcaster.clone(),
+            receiver,
+        );
+
+        Ok(context)
+    }
+}
+
+/// Resolves the tenant of the request and hands the handlers that tenant's repositories
+/// and WebSocket clients, so every handler is scoped to it without knowing about tenants.
+pub async fn resolve_tenant(
+    Extension(tenant_settings): Extension<TenantSettings>,
+    Extension(registry): Extension<Arc<TenantRegistry>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let tenant = tenant_settings.resolve(request.headers())?;
+    let context = registry.context(&tenant).await?;
+
+    request.extensions_mut().insert(context.repositories);
+    request.extensions_mut().insert(context.shared_state);
+    request.extensions_mut().insert(context.events);
+    request.extensions_mut().insert(context.broadcaster);
+    request.extensions_mut().insert(tenant);
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+    use shuttle_runtime::SecretStore;
+
+    use super::{Tenant, TenantRegistry, TenantSettings, API_KEY_HEADER, TENANT_HEADER};
+    use crate::{
+        models::movie_model::{Movie, MovieUpdate},
+        settings::Settings,
+    };
+
+    fn secret_store(secrets: &[(&str, &str)]) -> SecretStore {
+        let secrets: BTreeMap<&str, &str> = secrets.iter().copied().collect();
+        serde_json::from_value(serde_json::json!(secrets)).unwrap()
+    }
+
+    fn tenant_settings() -> TenantSettings {
+        TenantSettings::from_secrets(&secret_store(&[
+            ("TENANTS", "acme,globex"),
+            ("TENANT_API_KEYS", "acme:acme-key,globex:globex-key"),
+            ("TENANT_BASE_DOMAIN", "api.example.com"),
+        ]))
+        .unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn movie(title: &str) -> Movie {
+        Movie {
+            id: None,
+            title: title.to_string(),
+            duration: 120,
+            description: None,
+            poster: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn tenants_cannot_see_or_change_each_others_data() {
+        let registry = TenantRegistry::in_memory(Settings::from_secrets(&secret_store(&[])).unwrap());
+        let acme = registry.context(&Tenant::parse("acme").unwrap()).await.unwrap();
+        let globex = registry.context(&Tenant::parse("globex").unwrap()).await.unwrap();
+
+        let movie = acme.repositories.movies.insert(movie("Alien")).await.unwrap();
+        let id = movie.id.unwrap();
+
+        assert!(globex.repositories.movies.find(id).await.unwrap().is_none());
+        assert!(globex.repositories.movies.list().await.unwrap().is_empty());
+
+        let update = MovieUpdate {
+            title: Some("Renamed".to_string()),
+            duration: None,
+            description: None,
+            poster: None,
+        };
+        assert!(!globex.repositories.movies.update(id, &update).await.unwrap());
+        assert!(!globex.repositories.movies.delete(id).await.unwrap());
+
+        let stored = acme.repositories.movies.find(id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Alien");
+    }
+
+    #[tokio::test]
+    async fn a_tenant_gets_the_same_storage_on_every_request() {
+        let registry = TenantRegistry::in_memory(Settings::from_secrets(&secret_store(&[])).unwrap());
+        let tenant = Tenant::parse("acme").unwrap();
+
+        let movie = registry
+            .context(&tenant)
+            .await
+            .unwrap()
+            .repositories
+            .movies
+            .insert(movie("Alien"))
+            .await
+            .unwrap();
+        let context = registry.context(&tenant).await.unwrap();
+
+        assert!(context.repositories.movies.find(movie.id.unwrap()).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn api_key_of_another_tenant_is_forbidden() {
+        let settings = tenant_settings();
+
+        let by_header = headers(&[(API_KEY_HEADER, "acme-key"), (TENANT_HEADER, "globex")]);
+        assert_eq!(settings.resolve(&by_header), Err(StatusCode::FORBIDDEN));
+
+        let by_subdomain = headers(&[(API_KEY_HEADER, "acme-key"), (header::HOST.as_str(), "globex.api.example.com")]);
+        assert_eq!(settings.resolve(&by_subdomain), Err(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn api_key_of_the_named_tenant_resolves_to_it() {
+        let settings = tenant_settings();
+        let acme = Tenant::parse("acme").unwrap();
+
+        let by_header = headers(&[(API_KEY_HEADER, "acme-key"), (TENANT_HEADER, "acme")]);
+        assert_eq!(settings.resolve(&by_header), Ok(acme.clone()));
+
+        let by_subdomain = headers(&[(API_KEY_HEADER, "acme-key"), (header::HOST.as_str(), "acme.api.example.com")]);
+        assert_eq!(settings.resolve(&by_subdomain), Ok(acme));
+
+        let unknown_key = headers(&[(API_KEY_HEADER, "other-key"), (TENANT_HEADER, "acme")]);
+        assert_eq!(settings.resolve(&unknown_key), Err(StatusCode::UNAUTHORIZED));
+    }
+}
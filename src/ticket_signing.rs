@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use mongodb::bson::oid::ObjectId;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bytes of the HMAC kept in a token. 128 bits is plenty against forgery
+/// and keeps the QR code small.
+const SIGNATURE_LEN: usize = 16;
+const PAYLOAD_LEN: usize = 24;
+
+/// Issues and checks ticket tokens: the booking id and ticket id followed by a
+/// truncated HMAC-SHA256 over both, encoded as unpadded base64url.
+#[derive(Clone)]
+pub struct TicketSigner {
+    key: Arc<Vec<u8>>,
+}
+
+impl TicketSigner {
+    pub fn new(key: &[u8]) -> Self {
+        TicketSigner {
+            key: Arc::new(key.to_vec()),
+        }
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+
+    pub fn sign(&self, booking_id: ObjectId, ticket_id: ObjectId) -> String {
+        let mut token = Vec::with_capacity(PAYLOAD_LEN + SIGNATURE_LEN);
+        token.extend_from_slice(&booking_id.bytes());
+        token.extend_from_slice(&ticket_id.bytes());
+
+        let signature = self.mac(&token).finalize().into_bytes();
+        token.extend_from_slice(&signature[..SIGNATURE_LEN]);
+
+        URL_SAFE_NO_PAD.encode(token)
+    }
+
+    /// Returns the booking id and ticket id of a token, or `None` if it is malformed
+    /// or was not signed with this key.
+    pub fn verify(&self, token: &str) -> Option<(ObjectId, ObjectId)> {
+        let bytes = URL_SAFE_NO_PAD.decode(token.trim()).ok()?;
+        if bytes.len() != PAYLOAD_LEN + SIGNATURE_LEN {
+            return None;
+        }
+
+        let (payload, signature) = bytes.split_at(PAYLOAD_LEN);
+        self.mac(payload).verify_truncated_left(signature).ok()?;
+
+        let booking_id: [u8; 12] = payload[..12].try_into().ok()?;
+        let ticket_id: [u8; 12] = payload[12..].try_into().ok()?;
+        Some((ObjectId::from_bytes(booking_id), ObjectId::from_bytes(ticket_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use mongodb::bson::oid::ObjectId;
+
+    use super::{TicketSigner, PAYLOAD_LEN, SIGNATURE_LEN};
+
+    fn tampered(token: &str, index: usize) -> String {
+        let mut bytes = URL_SAFE_NO_PAD.decode(token).unwrap();
+        bytes[index] ^= 0x01;
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    #[test]
+    fn signed_tickets_verify() {
+        let signer = TicketSigner::new(b"secret");
+        let (booking_id, ticket_id) = (ObjectId::new(), ObjectId::new());
+
+        let token = signer.sign(booking_id, ticket_id);
+        assert_eq!(signer.verify(&token), Some((booking_id, ticket_id)));
+    }
+
+    #[test]
+    fn tampered_payloads_are_rejected() {
+        let signer = TicketSigner::new(b"secret");
+        let token = signer.sign(ObjectId::new(), ObjectId::new());
+
+        assert_eq!(signer.verify(&tampered(&token, 0)), None);
+        assert_eq!(signer.verify(&tampered(&token, PAYLOAD_LEN - 1)), None);
+    }
+
+    #[test]
+    fn tampered_signatures_are_rejected() {
+        let signer = TicketSigner::new(b"secret");
+        let token = signer.sign(ObjectId::new(), ObjectId::new());
+
+        assert_eq!(signer.verify(&tampered(&token, PAYLOAD_LEN)), None);
+        assert_eq!(signer.verify(&tampered(&token, PAYLOAD_LEN + SIGNATURE_LEN - 1)), None);
+    }
+
+    #[test]
+    fn tickets_signed_with_another_key_are_rejected() {
+        let token = TicketSigner::new(b"other").sign(ObjectId::new(), ObjectId::new());
+
+        assert_eq!(TicketSigner::new(b"secret").verify(&token), None);
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        let signer = TicketSigner::new(b"secret");
+        let token = signer.sign(ObjectId::new(), ObjectId::new());
+
+        assert_eq!(signer.verify(&token[..token.len() - 4]), None);
+        assert_eq!(signer.verify(&token[..10]), None);
+        assert_eq!(signer.verify(""), None);
+        assert_eq!(signer.verify("not base64!"), None);
+        assert_eq!(signer.verify("@@@@"), None);
+        assert_eq!(signer.verify(&format!("{token}AA")), None);
+    }
+}
@@ -1,12 +1,57 @@
-use serde::Serializer;
-use mongodb::bson::oid::ObjectId;
+use serde::{Serialize, Serializer};
+use mongodb::bson::{
+    oid::ObjectId, serde_helpers::serialize_bson_datetime_as_rfc3339_string, DateTime,
+};
 
+/// Writes ids as hex strings in JSON, but keeps them as native `ObjectId`s when the
+/// MongoDB driver serializes a document for storage.
 pub fn serialize_object_id<S>(id: &Option<ObjectId>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     match id {
+        Some(id) if !serializer.is_human_readable() => id.serialize(serializer),
         Some(id) => serializer.serialize_str(&id.to_hex()),
         None => serializer.serialize_none(),
     }
-}
\ No newline at end of file
+}
+
+pub fn serialize_required_object_id<S>(id: &ObjectId, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serialize_object_id(&Some(*id), serializer)
+}
+
+pub fn serialize_object_ids<S>(ids: &[ObjectId], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        serializer.collect_seq(ids.iter().map(|id| id.to_hex()))
+    } else {
+        ids.serialize(serializer)
+    }
+}
+
+/// Writes dates as RFC 3339 strings in JSON and as native BSON dates in MongoDB.
+pub fn serialize_datetime<S>(date: &DateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if serializer.is_human_readable() {
+        serialize_bson_datetime_as_rfc3339_string(date, serializer)
+    } else {
+        date.serialize(serializer)
+    }
+}
+
+pub fn serialize_optional_datetime<S>(date: &Option<DateTime>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match date {
+        Some(date) => serialize_datetime(date, serializer),
+        None => serializer.serialize_none(),
+    }
+}
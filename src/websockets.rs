@@ -1,137 +1,790 @@
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    response::IntoResponse, Extension, Json
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Query},
+    http::StatusCode,
+    response::{sse::Event as SseEvent, IntoResponse}, Extension, Json
 };
+use chrono::NaiveDate;
 use futures::{SinkExt, StreamExt};
-use mongodb::Client;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use schemars::gen::SchemaGenerator;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use std::sync::Arc;
-use tokio::sync::{ Mutex, mpsc::{unbounded_channel, UnboundedSender } };
+use std::{collections::{HashMap, HashSet, VecDeque}, fmt, str::FromStr, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc}, time::Duration};
+use tokio::{sync::{ Mutex, Notify, mpsc::UnboundedReceiver }, time::{interval_at, Instant}};
 use serde_json::to_string;
 
-use crate::{add_ws_session, delete_ws_session, get_sessions, models::session_model::SessionUpdate, update_ws_session};
+use crate::{add_ws_session, auth::{AuthUser, Caller, JwtSigner, Partner}, controllers::{hall_controller::{hall_time_zone, session_time_zone}, session_controller::SessionError}, delete_ws_session, broadcast::Broadcaster, events::{Change, DomainEvent, EventBus}, get_sessions, models::{local_time_model::LocalTimes, session_model::{Session, SessionResponse}, user_model::Role, ws_message_model::{ClientMessage, ClientRequest, DeletedRecord, DeletedSession, ErrorCode, Event, Reply, ReplyResult, RequestId, ServerMessage, WsError, WsMetrics, PROTOCOL_VERSIONS}}, repositories::{Repositories, RepositoryResult}, settings::{local_time_in, OverflowPolicy, Settings}, tenancy::Tenant, update_ws_session};
+
+/// What a client can subscribe to, written as `hall:<id>`, `movie:<id>`, `session:<id>:seats`,
+/// `venue:<id>:<YYYY-MM-DD>` or `admin`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    /// Sessions in a hall.
+    Hall(ObjectId),
+    /// Sessions of a movie.
+    Movie(ObjectId),
+    /// Seats of a session being held, booked or released.
+    SessionSeats(ObjectId),
+    /// Sessions of a venue starting on a day, in the hall's time zone.
+    VenueSchedule(ObjectId, NaiveDate),
+    /// Every event, for the back office. Staff only.
+    Admin,
+}
+
+impl FromStr for Topic {
+    type Err = ();
+
+    fn from_str(topic: &str) -> Result<Self, Self::Err> {
+        let id = |id_str: &str| ObjectId::parse_str(id_str).map_err(|_| ());
+        match topic.split(':').collect::<Vec<_>>().as_slice() {
+            ["hall", id_str] => Ok(Topic::Hall(id(id_str)?)),
+            ["movie", id_str] => Ok(Topic::Movie(id(id_str)?)),
+            ["session", id_str, "seats"] => Ok(Topic::SessionSeats(id(id_str)?)),
+            ["venue", id_str, date] => {
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| ())?;
+                Ok(Topic::VenueSchedule(id(id_str)?, date))
+            },
+            ["admin"] => Ok(Topic::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topic::Hall(id) => write!(f, "hall:{}", id),
+            Topic::Movie(id) => write!(f, "movie:{}", id),
+            Topic::SessionSeats(id) => write!(f, "session:{}:seats", id),
+            Topic::VenueSchedule(id, date) => write!(f, "venue:{}:{}", id, date.format("%Y-%m-%d")),
+            Topic::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Topics a change to a session is published to: its hall, its movie and the schedule of
+/// its venue on the day it starts.
+async fn session_topics(repositories: &Repositories, settings: &Settings, hall_id: Option<ObjectId>, movie_id: Option<ObjectId>, start: DateTime) -> Vec<Topic> {
+    let mut topics = Vec::new();
+    if let Some(movie_id) = movie_id {
+        topics.push(Topic::Movie(movie_id));
+    }
+    let Some(hall_id) = hall_id else {
+        return topics;
+    };
+    topics.push(Topic::Hall(hall_id));
+
+    let hall = match repositories.halls.find(hall_id).await {
+        Ok(hall) => hall,
+        Err(e) => {
+            eprintln!("Failed to load hall for session topics: {}", e);
+            None
+        },
+    };
+    if let Some((hall, venue_id)) = hall.and_then(|hall| hall.venue_id.map(|venue_id| (hall, venue_id))) {
+        match hall_time_zone(repositories, settings, &hall).await {
+            Ok(time_zone) => topics.push(Topic::VenueSchedule(venue_id, local_time_in(time_zone, start).date())),
+            Err(e) => eprintln!("Failed to resolve time zone for session topics: {}", e),
+        }
+    }
+
+    topics
+}
+
+/// Adds the topics of `more` that `topics` does not have yet.
+fn merge_topics(topics: &mut Vec<Topic>, more: Vec<Topic>) {
+    for topic in more {
+        if !topics.contains(&topic) {
+            topics.push(topic);
+        }
+    }
+}
+
+/// The session as sent to clients, with its wall-clock times, and the topics it concerns.
+async fn session_change(repositories: &Repositories, settings: &Settings, session: Session) -> (Vec<Topic>, SessionResponse) {
+    let mut topics = session_topics(repositories, settings, session.hall_id, session.movie_id, session.start).await;
+    if let Some(id) = session.id {
+        topics.push(Topic::SessionSeats(id));
+    }
+    let time_zone = session_time_zone(repositories, settings, &session).await;
+
+    let mut response: SessionResponse = session.into();
+    if let Ok(time_zone) = time_zone {
+        response.local = Some(LocalTimes::new(time_zone, response.start, response.end));
+    }
+
+    (topics, response)
+}
+
+/// Looks up what a domain event is about and works out the WebSocket event for it. Returns
+/// `None` when the document is gone by the time the event is handled.
+async fn websocket_event(repositories: &Repositories, settings: &Settings, event: DomainEvent) -> RepositoryResult<Option<(Vec<Topic>, Event)>> {
+    let deleted = |id: ObjectId| DeletedRecord { id: id.to_hex() };
+
+    Ok(match event {
+        DomainEvent::SessionAdded(id) => match repositories.sessions.find(id).await? {
+            Some(session) => {
+                let (topics, session) = session_change(repositories, settings, session).await;
+                Some((topics, Event::AddSession(session)))
+            },
+            None => None,
+        },
+        DomainEvent::SessionUpdated { id, previous } => match repositories.sessions.find(id).await? {
+            Some(session) => {
+                let (mut topics, session) = session_change(repositories, settings, session).await;
+                if let Some(previous) = previous {
+                    merge_topics(&mut topics, session_topics(repositories, settings, previous.hall_id, previous.movie_id, previous.start).await);
+                }
+                Some((topics, Event::UpdateSession(session)))
+            },
+            None => None,
+        },
+        DomainEvent::SessionDeleted { id, previous } => {
+            let mut topics = vec![Topic::SessionSeats(id)];
+            if let Some(previous) = previous {
+                merge_topics(&mut topics, session_topics(repositories, settings, previous.hall_id, previous.movie_id, previous.start).await);
+            }
+            Some((topics, Event::DeleteSession(DeletedSession::new(id.to_hex()))))
+        },
+        DomainEvent::MovieAdded(id) => repositories.movies.find(id).await?.map(|movie| (vec![Topic::Movie(id)], Event::AddMovie(movie))),
+        DomainEvent::MovieUpdated(id) => repositories.movies.find(id).await?.map(|movie| (vec![Topic::Movie(id)], Event::UpdateMovie(movie))),
+        DomainEvent::MovieDeleted(id) => Some((vec![Topic::Movie(id)], Event::DeleteMovie(deleted(id)))),
+        DomainEvent::HallAdded(id) => repositories.halls.find(id).await?.map(|hall| (vec![Topic::Hall(id)], Event::AddHall(hall))),
+        DomainEvent::HallUpdated(id) => repositories.halls.find(id).await?.map(|hall| (vec![Topic::Hall(id)], Event::UpdateHall(hall))),
+        DomainEvent::HallDeleted(id) => Some((vec![Topic::Hall(id)], Event::DeleteHall(deleted(id)))),
+    })
+}
+
+/// Delivers the tenant's domain events to the WebSocket clients subscribed to them.
+pub fn spawn_event_dispatcher(repositories: Repositories, settings: Settings, broadcaster: Arc<dyn Broadcaster>, mut receiver: UnboundedReceiver<Change>) {
+    tokio::spawn(async move {
+        while let Some(Change { event, change_id }) = receiver.recv().await {
+            match websocket_event(&repositories, &settings, event).await {
+                Ok(Some((topics, event))) => broadcaster.publish(topics, event, change_id).await,
+                Ok(None) => {},
+                Err(e) => eprintln!("Failed to dispatch event: {}", e),
+            }
+        }
+    });
+}
+
+/// Events kept for replay. A client that missed more has to resync.
+const EVENT_HISTORY_LEN: usize = 1000;
+
+/// Counters of the hub, for `GET /ws/metrics`.
+#[derive(Default)]
+struct HubMetrics {
+    dropped_messages: AtomicU64,
+    slow_client_disconnects: AtomicU64,
+    timed_out_clients: AtomicU64,
+}
+
+/// Messages waiting to be written to a client. It holds at most `ws_queue_len` of them, so
+/// a client that reads slowly cannot grow memory without bound.
+struct ClientQueue<T> {
+    messages: std::sync::Mutex<VecDeque<T>>,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+    /// Wakes the task writing to the client.
+    ready: Notify,
+    closed: AtomicBool,
+    /// Wakes the task reading from the socket once the queue is closed.
+    closing: Notify,
+    metrics: Arc<HubMetrics>,
+}
+
+impl<T> ClientQueue<T> {
+    /// Queues the message, applying the overflow policy when the queue is full. Returns
+    /// `false` when the client is disconnected.
+    fn push(&self, message: T) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+
+        let mut messages = self.messages.lock().unwrap_or_else(|e| e.into_inner());
+        if messages.len() >= self.capacity {
+            self.metrics.dropped_messages.fetch_add(1, Ordering::Relaxed);
+            match self.overflow_policy {
+                OverflowPolicy::DropOldest => {
+                    messages.pop_front();
+                },
+                OverflowPolicy::Disconnect => {
+                    drop(messages);
+                    self.metrics.slow_client_disconnects.fetch_add(1, Ordering::Relaxed);
+                    self.close();
+                    return false;
+                },
+            }
+        }
+        messages.push_back(message);
+        self.ready.notify_one();
+
+        true
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.messages.lock().unwrap_or_else(|e| e.into_inner()).pop_front()
+    }
+
+    /// Stops taking messages. The ones already queued are still sent before the connection closes.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.ready.notify_one();
+        self.closing.notify_one();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+/// Where the messages of a client go: its WebSocket, or its Server-Sent Events stream.
+enum Sink {
+    WebSocket(Arc<ClientQueue<Message>>),
+    Sse(Arc<ClientQueue<SseEvent>>),
+}
+
+impl Sink {
+    /// Queues the serialized event message numbered `seq`. Returns `false` when the client
+    /// is disconnected.
+    fn push_event(&self, seq: u64, text: &str) -> bool {
+        match self {
+            Sink::WebSocket(queue) => queue.push(Message::text(text)),
+            Sink::Sse(queue) => queue.push(SseEvent::default().id(seq.to_string()).data(text)),
+        }
+    }
+
+    fn close(&self) {
+        match self {
+            Sink::WebSocket(queue) => queue.close(),
+            Sink::Sse(queue) => queue.close(),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match self {
+            Sink::WebSocket(queue) => queue.is_closed(),
+            Sink::Sse(queue) => queue.is_closed(),
+        }
+    }
+}
+
+struct Client {
+    sink: Sink,
+    topics: HashSet<Topic>,
+}
+
+impl Client {
+    fn wants(&self, topics: &[Topic]) -> bool {
+        self.topics.contains(&Topic::Admin) || topics.iter().any(|topic| self.topics.contains(topic))
+    }
+}
+
+/// Event kept for clients that reconnect and resume from the last one they saw.
+struct PublishedEvent {
+    seq: u64,
+    topics: Vec<Topic>,
+    /// The serialized `Event`.
+    event: Value,
+}
 
 pub struct SharedState {
-    clients: Vec<UnboundedSender<Message>>,
+    clients: HashMap<u64, Client>,
+    next_client_id: u64,
+    queue_len: usize,
+    overflow_policy: OverflowPolicy,
+    metrics: Arc<HubMetrics>,
+    /// Sequence number of the latest event. It starts from the Unix time in milliseconds, so
+    /// it keeps increasing across restarts and clients resuming from before one get told to resync.
+    seq: u64,
+    /// The latest `EVENT_HISTORY_LEN` events, oldest first.
+    history: VecDeque<PublishedEvent>,
+}
+
+fn ws_message(message: &ServerMessage) -> Message {
+    Message::text(to_string(message).unwrap_or_else(|_| "{}".to_string()))
+}
+
+/// The event message as sent to clients, the same over WebSockets and Server-Sent Events.
+fn event_text(seq: u64, event: &Value) -> String {
+    to_string(&ServerMessage::Event { seq, event: event.clone() }).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Events for a client of `GET /events`. The client is disconnected when this is dropped.
+pub struct SseClient {
+    queue: Arc<ClientQueue<SseEvent>>,
+}
+
+impl SseClient {
+    /// Waits for the next event. Returns `None` once the client is disconnected.
+    pub async fn next(&self) -> Option<SseEvent> {
+        loop {
+            if let Some(event) = self.queue.pop() {
+                return Some(event);
+            }
+            if self.queue.is_closed() {
+                return None;
+            }
+            self.queue.ready.notified().await;
+        }
+    }
+}
+
+impl Drop for SseClient {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
 }
 
 impl SharedState {
-    pub fn new() -> Self {
+    pub fn new(settings: &Settings) -> Self {
         SharedState {
-            clients: Vec::new(),
+            clients: HashMap::new(),
+            next_client_id: 0,
+            queue_len: settings.ws_queue_len.max(1),
+            overflow_policy: settings.ws_overflow_policy,
+            metrics: Arc::new(HubMetrics::default()),
+            seq: DateTime::now().timestamp_millis().max(0) as u64,
+            history: VecDeque::with_capacity(EVENT_HISTORY_LEN),
         }
     }
 
-    pub fn broadcast(&self, action_type: &str, status: &str, data: Value) {
-        let message = json!({
-            "action_type": action_type,
-            "status": status,
-            "data": data
+    fn queue<T>(&self) -> Arc<ClientQueue<T>> {
+        Arc::new(ClientQueue {
+            messages: std::sync::Mutex::new(VecDeque::new()),
+            capacity: self.queue_len,
+            overflow_policy: self.overflow_policy,
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            closing: Notify::new(),
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    fn connect(&mut self, sink: Sink, topics: HashSet<Topic>) -> u64 {
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+        self.clients.insert(client_id, Client { sink, topics });
+        client_id
+    }
+
+    fn disconnect(&mut self, client_id: u64) {
+        if let Some(client) = self.clients.remove(&client_id) {
+            client.sink.close();
+        }
+    }
+
+    /// Connects a Server-Sent Events client subscribed to `topics` and sends it the events on
+    /// them published after `last_seq`. The flag is set when those are no longer kept.
+    pub fn connect_sse(&mut self, topics: Vec<Topic>, last_seq: Option<u64>) -> (SseClient, bool) {
+        let queue = self.queue();
+        let client_id = self.connect(Sink::Sse(queue.clone()), topics.iter().cloned().collect());
+        let resync_required = last_seq.is_some_and(|last_seq| !self.replay(client_id, &topics, last_seq));
+
+        (SseClient { queue }, resync_required)
+    }
+
+    pub fn metrics(&self) -> WsMetrics {
+        WsMetrics {
+            connected_clients: self.clients.len(),
+            dropped_messages: self.metrics.dropped_messages.load(Ordering::Relaxed),
+            slow_client_disconnects: self.metrics.slow_client_disconnects.load(Ordering::Relaxed),
+            timed_out_clients: self.metrics.timed_out_clients.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Continues numbering events after `seq`, the latest one of an event log shared with
+    /// other instances.
+    pub fn set_seq(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+
+    /// Numbers the event and delivers it to the clients of this instance.
+    pub fn publish(&mut self, topics: Vec<Topic>, event: Value) {
+        self.deliver(self.seq + 1, topics, event);
+    }
+
+    /// Sends an event numbered `seq` to the clients subscribed to any of `topics`, and to
+    /// admin subscribers.
+    pub fn deliver(&mut self, seq: u64, topics: Vec<Topic>, event: Value) {
+        self.seq = self.seq.max(seq);
+        let text = event_text(seq, &event);
+        // Clients that went away or fell too far behind are dropped on the way.
+        self.clients.retain(|_, client| {
+            !client.sink.is_closed() && (!client.wants(&topics) || client.sink.push_event(seq, &text))
         });
-        let message_text = to_string(&message).unwrap_or_else(|_| "{}".to_string());
-        for client in &self.clients {
-            if let Err(e) = client.send(Message::text(message_text.clone())) {
-                eprintln!("Failed to broadcast message: {}", e);
+
+        if self.history.len() == EVENT_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(PublishedEvent { seq, topics, event });
+    }
+
+    /// Numbers and contents of the events kept for replay, oldest first.
+    #[cfg(test)]
+    pub fn history(&self) -> Vec<(u64, Value)> {
+        self.history.iter().map(|published| (published.seq, published.event.clone())).collect()
+    }
+
+    /// Sends the client the events on `topics` published after `last_seq`. Returns `false`,
+    /// without sending anything, when some of them are no longer kept or `last_seq` is not one
+    /// of this server's.
+    fn replay(&self, client_id: u64, topics: &[Topic], last_seq: u64) -> bool {
+        let oldest = self.history.front().map_or(self.seq + 1, |published| published.seq);
+        if last_seq > self.seq || last_seq + 1 < oldest {
+            return false;
+        }
+        let Some(client) = self.clients.get(&client_id) else {
+            return true;
+        };
+
+        let missed = self.history.iter().filter(|published| {
+            published.seq > last_seq
+                && (topics.contains(&Topic::Admin) || published.topics.iter().any(|topic| topics.contains(topic)))
+        });
+        for published in missed {
+            if !client.sink.push_event(published.seq, &event_text(published.seq, &published.event)) {
+                break;
             }
         }
+
+        true
+    }
+}
+
+impl From<StatusCode> for ErrorCode {
+    fn from(status: StatusCode) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => ErrorCode::InvalidRequest,
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl From<StatusCode> for WsError {
+    fn from(status: StatusCode) -> Self {
+        WsError::new(status.into(), status.canonical_reason().unwrap_or("Request failed"))
     }
 }
 
-pub async fn websocket_handler(ws: WebSocketUpgrade, client: Extension<Arc<Client>>, shared_state: Arc<Mutex<SharedState>>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, client, shared_state.clone()))
+impl From<SessionError> for WsError {
+    fn from(err: SessionError) -> Self {
+        match err {
+            SessionError::Status(status) => status.into(),
+            SessionError::HallUnavailable(unavailability) => WsError {
+                code: ErrorCode::HallUnavailable,
+                message: unavailability.message(),
+                details: Some(json!({"unavailability": unavailability})),
+            },
+            SessionError::CrossVenueMove => WsError::new(ErrorCode::CrossVenueMove, SessionError::CrossVenueMove.to_string()),
+            SessionError::InvalidLocalTime(local_time_error) => WsError {
+                code: ErrorCode::InvalidLocalTime,
+                message: local_time_error.message(),
+                details: Some(json!({"local_time": local_time_error})),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WsAuthQuery {
+    /// Access token, for clients that cannot set an `Authorization` header.
+    pub token: Option<String>,
+}
+
+/// Role a client needs for a request, if any.
+fn required_role(request: &ClientRequest) -> Option<Role> {
+    match request {
+        ClientRequest::AddSession { .. } | ClientRequest::UpdateSession { .. } | ClientRequest::DeleteSession { .. } => Some(Role::Staff),
+        _ => None,
+    }
+}
+
+/// JSON Schema of the messages sent over `/ws`, for generating client types. Clients send a
+/// `ClientMessage` and receive `ServerMessage`s.
+pub async fn websocket_schema() -> Json<Value> {
+    let mut generator = SchemaGenerator::default();
+    let client_message = generator.subschema_for::<ClientMessage>();
+    let server_message = generator.subschema_for::<ServerMessage>();
+
+    Json(json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "WebSocket protocol",
+        "protocol_versions": PROTOCOL_VERSIONS,
+        "type": "object",
+        "properties": {
+            "client_message": client_message,
+            "server_message": server_message,
+        },
+        "definitions": generator.definitions(),
+    }))
+}
+
+/// Connected clients and dropped messages of the tenant's hub.
+pub async fn websocket_metrics(Extension(shared_state): Extension<Arc<Mutex<SharedState>>>) -> Json<WsMetrics> {
+    Json(shared_state.lock().await.metrics())
+}
+
+/// Upgrades the connection and joins the hub of the tenant the request resolved to.
+/// Clients identify with a bearer token, a `?token=` query parameter, a partner API key or
+/// an `authenticate` first message. Events are only delivered for the topics a client
+/// subscribed to, and actions that change data need the matching role.
+#[allow(clippy::too_many_arguments)]
+pub async fn websocket_handler(ws: WebSocketUpgrade, Query(query): Query<WsAuthQuery>, user: Option<AuthUser>, partner: Option<Extension<Partner>>, Extension(signer): Extension<JwtSigner>, Extension(tenant): Extension<Tenant>, repositories: Extension<Repositories>, settings: Extension<Settings>, Extension(events): Extension<EventBus>, Extension(shared_state): Extension<Arc<Mutex<SharedState>>>) -> Result<impl IntoResponse, StatusCode> {
+    let caller = match (partner, user, query.token) {
+        (Some(Extension(partner)), _, _) => Some(Caller::Partner(partner)),
+        (None, Some(user), _) => Some(Caller::User(user)),
+        (None, None, Some(token)) => Some(Caller::User(signer.verify(&token, &tenant).ok_or(StatusCode::UNAUTHORIZED)?)),
+        (None, None, None) => None,
+    };
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, caller, signer, tenant, repositories, settings, events, shared_state)))
+}
+
+fn parse_topics(topics: &[String]) -> Result<Vec<Topic>, WsError> {
+    topics
+        .iter()
+        .map(|topic| topic.parse().map_err(|_| WsError::new(ErrorCode::InvalidRequest, format!("Unknown topic `{}`", topic))))
+        .collect()
+}
+
+/// Picks the highest protocol version both sides speak.
+fn negotiate_version(versions: &[u32]) -> Result<u32, WsError> {
+    versions
+        .iter()
+        .copied()
+        .filter(|version| PROTOCOL_VERSIONS.contains(version))
+        .max()
+        .ok_or_else(|| WsError::new(ErrorCode::UnsupportedVersion, format!("Supported protocol versions are {:?}", PROTOCOL_VERSIONS)))
 }
 
-async fn handle_socket(socket: WebSocket, client: Extension<Arc<Client>>, shared_state: Arc<Mutex<SharedState>>) {
+#[allow(clippy::too_many_arguments)]
+async fn handle_socket(socket: WebSocket, mut caller: Option<Caller>, signer: JwtSigner, tenant: Tenant, repositories: Extension<Repositories>, settings: Extension<Settings>, events: EventBus, shared_state: Arc<Mutex<SharedState>>) {
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = unbounded_channel::<Message>();
+    let (client_id, queue) = {
+        let mut state = shared_state.lock().await;
+        let queue = state.queue();
+        (state.connect(Sink::WebSocket(queue.clone()), HashSet::new()), queue)
+    };
 
+    let outgoing = queue.clone();
     tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
-            if let Err(e) = sender.send(message).await {
-                eprintln!("Failed to send message: {}", e);
-                break;
+        loop {
+            outgoing.ready.notified().await;
+            while let Some(message) = outgoing.pop() {
+                if let Err(e) = sender.send(message).await {
+                    eprintln!("Failed to send message: {}", e);
+                    outgoing.close();
+                    return;
+                }
+            }
+            if outgoing.is_closed() {
+                let _ = sender.close().await;
+                return;
             }
         }
     });
 
-    {
-        let mut state = shared_state.lock().await;
-        state.clients.push(tx);
-    }
-    
-    while let Some(Ok(Message::Text(text))) = receiver.next().await {
-        let request: Value = serde_json::from_str(&text).unwrap_or_else(|_| {
-            eprintln!("Failed to parse request text to JSON.");
-            serde_json::Value::Null
-        });
-        let state = shared_state.lock().await;
-
-        match request["action"].as_str() {
-            Some(action_type) => {
-                let (status, data) = match action_type {
-                    "get_sessions" => {
-                        let response = get_sessions(client.clone()).await;
-                        match response {
-                            Ok(sessions) => ("success", json!(sessions)),
-                            Err(e) => {
-                                eprintln!("Failed to get sessions: {}", e);
-                                ("error", json!({"error": "Failed to get sessions"}))
-                            },
-                        }
-                    },
-                    "add_session" => {
-                        if let Ok(session_data) = serde_json::from_value::<SessionUpdate>(request["data"].clone()) {
-                            let response = add_ws_session(client.clone(), Json(session_data)).await;
-                            match response {
-                                Ok(session) => ("success", json!(session)),
-                                Err(e) => {
-                                    eprintln!("Failed to add session: {}", e);
-                                    ("error", json!({"error": "Failed to add session"}))
-                                },
-                            }
-                        } else {
-                            eprintln!("Failed to parse session data");
-                            ("error", json!({"error": "Failed to parse session data"}))
-                        }
-                    },
-                    "update_session" => {
-                        if let Ok(session_update) = serde_json::from_value::<SessionUpdate>(request["data"].clone()) {
-                            let id_str = request["id"].as_str().unwrap_or_default();
-                            let response = update_ws_session(client.clone(), axum::extract::Path(id_str.to_string()), Json(session_update)).await;
-                            match response {
-                                Ok(session) => ("success", json!(session)),
-                                Err(e) => {
-                                    eprintln!("Failed to update session: {}", e);
-                                    ("error", json!({"error": "Failed to update session"}))
-                                },
-                            }
-                        } else {
-                            eprintln!("Failed to parse session update data");
-                            ("error", json!({"error": "Failed to parse session update data"}))
-                        }
-                    },
-                    "delete_session" => {
-                        let id_str = request["id"].as_str().unwrap_or_default();
-                        let response = delete_ws_session(client.clone(), axum::extract::Path(id_str.to_string())).await;
-                        match response {
-                            Ok(_) => ("success", json!({"message": "Session deleted successfully", "_id": id_str})),
-                            Err(e) => {
-                                eprintln!("Failed to delete session: {}", e);
-                                ("error", json!({"error": "Failed to delete session"}))
-                            },
-                        }
-                    },
-                    _ => {
-                        eprintln!("Unsupported action received.");
-                        ("error", json!({"error": "Unsupported action"}))
+    // Answers a request of this client, echoing its id.
+    let reply = |id: Option<RequestId>, action_type: &str, result: Result<Reply, WsError>| {
+        let result = match result {
+            Ok(reply) => ReplyResult::Success(reply),
+            Err(error) => ReplyResult::Error {
+                action_type: action_type.to_string(),
+                error,
+            },
+        };
+        queue.push(ws_message(&ServerMessage::Reply { id, result }));
+    };
+
+    let mut version = None;
+    // Requests other than `hello` handled so far.
+    let mut requests = 0;
+    let ping_interval = Duration::from_secs(settings.ws_ping_interval_seconds.max(1));
+    let client_timeout = Duration::from_secs(settings.ws_client_timeout_seconds);
+    let mut heartbeat = interval_at(Instant::now() + ping_interval, ping_interval);
+    let mut last_seen = Instant::now();
+    loop {
+        let text = tokio::select! {
+            message = receiver.next() => {
+                last_seen = Instant::now();
+                match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    // Pongs and pings only show the client is still there.
+                    Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Binary(_))) => continue,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                }
+            },
+            _ = heartbeat.tick() => {
+                if last_seen.elapsed() >= client_timeout {
+                    eprintln!("WebSocket client {} timed out.", client_id);
+                    queue.metrics.timed_out_clients.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+                queue.push(Message::Ping(Default::default()));
+                continue;
+            },
+            _ = queue.closing.notified() => break,
+        };
+
+        let raw: Value = match serde_json::from_str(&text) {
+            Ok(raw) => raw,
+            Err(e) => {
+                reply(None, "", Err(WsError::new(ErrorCode::InvalidRequest, format!("Request is not valid JSON: {}", e))));
+                continue;
+            }
+        };
+        // Kept from the raw message so even a malformed request gets a reply it can be matched to.
+        let id = RequestId::deserialize(&raw["id"]).ok();
+        let action_type = raw["action"].as_str().unwrap_or_default().to_string();
+        let request = match serde_json::from_value::<ClientMessage>(raw) {
+            Ok(message) => message.request,
+            Err(e) => {
+                reply(id, &action_type, Err(WsError::new(ErrorCode::InvalidRequest, e.to_string())));
+                continue;
+            }
+        };
+
+        if let ClientRequest::Hello { versions } = &request {
+            if version.is_some() || requests > 0 {
+                reply(id, &action_type, Err(WsError::new(ErrorCode::InvalidRequest, "Hello has to be the first message")));
+                continue;
+            }
+            match negotiate_version(versions) {
+                Ok(negotiated) => {
+                    version = Some(negotiated);
+                    reply(id, &action_type, Ok(Reply::Hello { version: negotiated }));
+                },
+                Err(error) => reply(id, &action_type, Err(error)),
+            }
+            continue;
+        }
+        requests += 1;
+
+        if let ClientRequest::Authenticate { token } = &request {
+            match signer.verify(token, &tenant) {
+                Some(user) if requests == 1 && caller.is_none() => {
+                    reply(id, &action_type, Ok(Reply::Authenticate { role: user.role }));
+                    caller = Some(Caller::User(user));
+                }
+                Some(_) => reply(id, &action_type, Err(WsError::new(ErrorCode::InvalidRequest, "Authenticate has to be the first request of an anonymous connection"))),
+                None => reply(id, &action_type, Err(WsError::new(ErrorCode::Unauthorized, "Invalid or expired token"))),
+            }
+            continue;
+        }
+
+        if let Some(role) = required_role(&request) {
+            if !caller.as_ref().is_some_and(|caller| caller.has_role(role)) {
+                eprintln!("Rejected {} from {}.", action_type, caller.as_ref().map_or("an anonymous client".to_string(), ToString::to_string));
+                let code = if caller.is_some() { ErrorCode::Forbidden } else { ErrorCode::Unauthorized };
+                reply(id, &action_type, Err(WsError::new(code, "Changing sessions needs the staff role")));
+                continue;
+            }
+        }
+        let identity = caller.as_ref().map_or("anonymous".to_string(), ToString::to_string);
+
+        // The reply and, for changes, the event to publish.
+        let result: Result<(Reply, Option<DomainEvent>), WsError> = match request {
+            ClientRequest::Hello { .. } | ClientRequest::Authenticate { .. } => continue,
+            ClientRequest::Subscribe { topics, last_seq } => match parse_topics(&topics) {
+                Ok(topics) if topics.contains(&Topic::Admin) && !caller.as_ref().is_some_and(|caller| caller.has_role(Role::Staff)) => {
+                    Err(WsError::new(ErrorCode::Forbidden, "The admin topic is for staff only"))
+                },
+                Ok(topics) => {
+                    // Replayed under the same lock, so no event is missed or sent twice in between.
+                    let mut state = shared_state.lock().await;
+                    let resync_required = last_seq.is_some_and(|last_seq| !state.replay(client_id, &topics, last_seq));
+                    let seq = state.seq;
+                    let Some(client) = state.clients.get_mut(&client_id) else {
+                        continue;
+                    };
+                    client.topics.extend(topics);
+                    let topics = client.topics.iter().map(ToString::to_string).collect();
+                    Ok((Reply::Subscribe { topics, seq, resync_required }, None))
+                },
+                Err(error) => Err(error),
+            },
+            ClientRequest::Unsubscribe { topics } => match parse_topics(&topics) {
+                Ok(topics) => {
+                    let mut state = shared_state.lock().await;
+                    let Some(client) = state.clients.get_mut(&client_id) else {
+                        continue;
+                    };
+                    for topic in &topics {
+                        client.topics.remove(topic);
                     }
+                    let topics = client.topics.iter().map(ToString::to_string).collect();
+                    Ok((Reply::Unsubscribe { topics }, None))
+                },
+                Err(error) => Err(error),
+            },
+            ClientRequest::GetSessions => match get_sessions(repositories.clone(), settings.clone()).await {
+                Ok(sessions) => Ok((Reply::GetSessions(sessions), None)),
+                Err(e) => {
+                    eprintln!("Failed to get sessions: {}", e);
+                    Err(e.into())
+                },
+            },
+            ClientRequest::AddSession { data } => match add_ws_session(repositories.clone(), settings.clone(), Json(data)).await {
+                Ok(session) => {
+                    println!("Audit [{}]: {} added session {}", tenant, identity, session.id.map(|id| id.to_hex()).unwrap_or_default());
+                    let event = session.id.map(DomainEvent::SessionAdded);
+                    Ok((Reply::AddSession(session), event))
+                },
+                Err(e) => {
+                    eprintln!("Failed to add session: {}", e);
+                    Err(e.into())
+                },
+            },
+            ClientRequest::UpdateSession { session_id, data } => {
+                // Subscribers of the hall, movie or day the session moves away from hear about it too.
+                let previous = match ObjectId::parse_str(&session_id) {
+                    Ok(session_id) => repositories.sessions.find(session_id).await.ok().flatten(),
+                    Err(_) => None,
                 };
-                state.broadcast(action_type, status, data);
+                match update_ws_session(repositories.clone(), settings.clone(), axum::extract::Path(session_id.clone()), Json(data)).await {
+                    Ok(session) => {
+                        println!("Audit [{}]: {} updated session {}", tenant, identity, session_id);
+                        let event = session.id.map(|id| DomainEvent::SessionUpdated { id, previous });
+                        Ok((Reply::UpdateSession(session), event))
+                    },
+                    Err(e) => {
+                        eprintln!("Failed to update session: {}", e);
+                        Err(e.into())
+                    },
+                }
+            },
+            ClientRequest::DeleteSession { session_id } => match delete_ws_session(repositories.clone(), axum::extract::Path(session_id.clone())).await {
+                Ok(session) => {
+                    println!("Audit [{}]: {} deleted session {}", tenant, identity, session_id);
+                    let event = session.id.map(|id| DomainEvent::SessionDeleted { id, previous: Some(session) });
+                    Ok((Reply::DeleteSession(DeletedSession::new(session_id)), event))
+                },
+                Err(e) => {
+                    eprintln!("Failed to delete session: {}", e);
+                    Err(e.into())
+                },
             },
-            None => {
-                eprintln!("Action type is missing.");
-                state.broadcast("error", "error", json!({"error": "Action type is missing"}));
+            ClientRequest::Unsupported => {
+                eprintln!("Unsupported action received.");
+                Err(WsError::new(ErrorCode::UnsupportedAction, format!("Unsupported action `{}`", action_type)))
             }
+        };
+
+        match result {
+            Ok((reply_data, event)) => {
+                if let Some(event) = event {
+                    events.publish(event);
+                }
+                reply(id, &action_type, Ok(reply_data));
+            },
+            Err(error) => reply(id, &action_type, Err(error)),
         }
     }
+
+    shared_state.lock().await.disconnect(client_id);
 }